@@ -0,0 +1,434 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use async_std::channel::Sender;
+use async_std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::store::{Record, Source};
+use crate::wrappers::pokemon::{normalize_name, Species};
+
+#[derive(thiserror::Error, Debug)]
+pub enum CacheError {
+    #[error("Can't read cache file {path}: {source}")]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("Can't write cache file {path}: {source}")]
+    Write {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct CacheEntry {
+    /// Original description, as returned by the pokemon api.
+    pub description: String,
+    /// Description in Shakespeare's style.
+    pub translation: String,
+    /// Unix timestamp (seconds) of when the entry was cached.
+    pub cached_at: u64,
+    /// Pokédex number, unknown for the entries cached before it was kept.
+    #[serde(default)]
+    pub id: Option<u32>,
+}
+
+impl CacheEntry {
+    /// Seconds since the entry was cached.
+    pub fn age(&self) -> u64 {
+        now().saturating_sub(self.cached_at)
+    }
+
+    /// Record of the machine translation of the entry.
+    pub fn into_record(self, pokemon_name: &str) -> Record {
+        Record {
+            name: pokemon_name.to_string(),
+            description: self.description,
+            translation: self.translation,
+            source: Source::Machine,
+            recorded_at: self.cached_at,
+            id: self.id,
+        }
+    }
+}
+
+/// Memoized translated descriptions, by pokemon name.
+///
+/// Clones share the same entries. When created with `load` the changes are also written to the
+/// file in the background, so the cache survives restarts and can be warmed up from the command
+/// line.
+#[derive(Clone, Debug, Default)]
+pub struct TranslationCache {
+    entries: Arc<RwLock<HashMap<String, CacheEntry>>>,
+    file: Option<CacheFile>,
+    /// Entries older than this are ignored, they never expire if not set.
+    ttl: Option<Duration>,
+    lookups: Arc<Lookups>,
+}
+
+/// File of a persisted cache.
+#[derive(Clone, Debug)]
+struct CacheFile {
+    path: PathBuf,
+    /// Wakes up the saving task, a change made while a save is pending is saved with it.
+    changed: Sender<()>,
+    /// Held while writing, so the saves don't overlap.
+    writing: Arc<Mutex<()>>,
+}
+
+/// Outcome of the lookups of a cache, for the hit ratio.
+#[derive(Debug, Default)]
+struct Lookups {
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl TranslationCache {
+    /// Create an in-memory cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a cache persisted in `path`, loading the entries already in the file.
+    ///
+    /// A corrupt file only loses the cached translations, so the cache starts empty and the file
+    /// is replaced on the next save.
+    pub fn load(path: &Path) -> Result<Self, CacheError> {
+        let entries = if path.exists() {
+            let content = std::fs::read_to_string(path).map_err(|source| CacheError::Read {
+                path: path.to_path_buf(),
+                source,
+            })?;
+            serde_json::from_str(&content).unwrap_or_else(|e| {
+                tide::log::warn!(
+                    "Invalid cache file {}: {}, starting empty",
+                    path.display(),
+                    e
+                );
+                HashMap::new()
+            })
+        } else {
+            HashMap::new()
+        };
+
+        let entries = Arc::new(RwLock::new(entries));
+        let writing = Arc::new(Mutex::new(()));
+        let (changed, saves) = async_std::channel::bounded(1);
+
+        // the task holds no sender, it ends with the last clone of the cache
+        {
+            let entries = entries.clone();
+            let path = path.to_path_buf();
+            let writing = writing.clone();
+            async_std::task::spawn(async move {
+                while saves.recv().await.is_ok() {
+                    if let Err(e) = write_entries(&entries, &path, &writing).await {
+                        tide::log::error!("Error: {}, saving cache", e);
+                    }
+                }
+            });
+        }
+
+        Ok(Self {
+            entries,
+            file: Some(CacheFile {
+                path: path.to_path_buf(),
+                changed,
+                writing,
+            }),
+            ttl: None,
+            lookups: Arc::default(),
+        })
+    }
+
+    /// Set how long the entries are valid.
+    pub fn set_ttl(&mut self, ttl: Option<Duration>) {
+        self.ttl = ttl;
+    }
+
+    pub fn ttl(&self) -> Option<Duration> {
+        self.ttl
+    }
+
+    /// Get the entry of a pokemon for a client request, unless it's expired. The lookup counts
+    /// as a hit or a miss.
+    pub fn get(&self, pokemon_name: &str) -> Option<CacheEntry> {
+        let entry = self.peek(pokemon_name);
+
+        let counter = match entry {
+            Some(_) => &self.lookups.hits,
+            None => &self.lookups.misses,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        entry
+    }
+
+    /// Get the entry of a pokemon, unless it's expired, without counting the lookup. For the
+    /// lookups made by the service itself, e.g. to skip the pokemons already cached.
+    pub fn peek(&self, pokemon_name: &str) -> Option<CacheEntry> {
+        self.entries
+            .read()
+            .expect("cache lock poisoned")
+            .get(&normalize_name(pokemon_name))
+            .filter(|entry| match self.ttl {
+                Some(ttl) => entry.age() < ttl.as_secs(),
+                None => true,
+            })
+            .cloned()
+    }
+
+    /// Lookups that found a valid entry.
+    pub fn hits(&self) -> u64 {
+        self.lookups.hits.load(Ordering::Relaxed)
+    }
+
+    /// Lookups without entry or with an expired one.
+    pub fn misses(&self) -> u64 {
+        self.lookups.misses.load(Ordering::Relaxed)
+    }
+
+    /// Add an entry without pokédex number.
+    pub fn insert(&self, pokemon_name: &str, description: &str, translation: &str) -> CacheEntry {
+        self.put(
+            pokemon_name,
+            CacheEntry {
+                description: description.to_string(),
+                translation: translation.to_string(),
+                cached_at: now(),
+                id: None,
+            },
+        )
+    }
+
+    /// Add the entry of a species, with its pokédex number.
+    pub fn insert_species(
+        &self,
+        pokemon_name: &str,
+        species: &Species,
+        translation: &str,
+    ) -> CacheEntry {
+        self.put(
+            pokemon_name,
+            CacheEntry {
+                description: species.description.clone(),
+                translation: translation.to_string(),
+                cached_at: now(),
+                id: Some(species.id),
+            },
+        )
+    }
+
+    fn put(&self, pokemon_name: &str, entry: CacheEntry) -> CacheEntry {
+        self.entries
+            .write()
+            .expect("cache lock poisoned")
+            .insert(normalize_name(pokemon_name), entry.clone());
+        self.changed();
+
+        entry
+    }
+
+    /// Forget the entry of a pokemon, returning it if it was cached.
+    pub fn remove(&self, pokemon_name: &str) -> Option<CacheEntry> {
+        let entry = self
+            .entries
+            .write()
+            .expect("cache lock poisoned")
+            .remove(&normalize_name(pokemon_name));
+
+        if entry.is_some() {
+            self.changed();
+        }
+
+        entry
+    }
+
+    /// Forget every entry, returning how many there were.
+    pub fn clear(&self) -> usize {
+        let removed = {
+            let mut entries = self.entries.write().expect("cache lock poisoned");
+            let removed = entries.len();
+            entries.clear();
+            removed
+        };
+        self.changed();
+
+        removed
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.read().expect("cache lock poisoned").len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Save the entries in the background, a failure is only logged since the entries are still
+    /// memoized.
+    fn changed(&self) {
+        if let Some(file) = &self.file {
+            // if full, a save is already pending and will include this change
+            let _ = file.changed.try_send(());
+        }
+    }
+
+    /// Write the entries to the file now, e.g. before exiting.
+    pub async fn save(&self) -> Result<(), CacheError> {
+        match &self.file {
+            Some(file) => write_entries(&self.entries, &file.path, &file.writing).await,
+            None => Ok(()),
+        }
+    }
+}
+
+/// Write the entries to the file, out of the executor threads. The file is replaced at once, so
+/// it's never left half written.
+async fn write_entries(
+    entries: &RwLock<HashMap<String, CacheEntry>>,
+    path: &Path,
+    writing: &Mutex<()>,
+) -> Result<(), CacheError> {
+    let _writing = writing.lock().await;
+    let content = {
+        let entries = entries.read().expect("cache lock poisoned");
+        serde_json::to_string(&*entries).expect("cache entries are serializable")
+    };
+    let path = path.to_path_buf();
+    async_std::task::spawn_blocking(move || {
+        write_atomic(&path, content.as_bytes()).map_err(|source| CacheError::Write { path, source })
+    })
+    .await
+}
+
+/// Replace the file with the content, writing it next to it first.
+pub(crate) fn write_atomic(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_name);
+
+    std::fs::write(&temp_path, content)?;
+    std::fs::rename(&temp_path, path)
+}
+
+/// Current unix timestamp, in seconds.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "poke-spearify-{}-{}.json",
+            name,
+            std::process::id()
+        ))
+    }
+
+    #[async_std::test]
+    async fn persist_entries() {
+        let path = cache_path("cache");
+
+        let cache = TranslationCache::load(&path).unwrap();
+        assert!(cache.is_empty());
+        cache.insert("charizard", "Spits fire", "Spits fire, forsooth");
+        let species = Species {
+            id: 132,
+            description: "Can copy".to_string(),
+        };
+        cache.insert_species("ditto", &species, "Can copy, verily");
+        cache.save().await.unwrap();
+
+        let reloaded = TranslationCache::load(&path).unwrap();
+        let entry = reloaded.get("charizard").unwrap();
+        assert_eq!("Spits fire", entry.description);
+        assert_eq!("Spits fire, forsooth", entry.translation);
+        assert_eq!(None, entry.id);
+        assert_eq!(Some(132), reloaded.get("ditto").unwrap().id);
+
+        assert!(cache.remove("ditto").is_some());
+        assert!(cache.remove("charizard").is_some());
+        cache.save().await.unwrap();
+        assert!(TranslationCache::load(&path).unwrap().is_empty());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[async_std::test]
+    async fn save_in_background() {
+        let path = cache_path("background-cache");
+
+        let cache = TranslationCache::load(&path).unwrap();
+        for _ in 0..10 {
+            cache.insert("charizard", "Spits fire", "Spits fire, forsooth");
+        }
+
+        let mut saved = false;
+        for _ in 0..50 {
+            async_std::task::sleep(Duration::from_millis(20)).await;
+            if TranslationCache::load(&path).unwrap().len() == 1 {
+                saved = true;
+                break;
+            }
+        }
+        assert!(saved);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn count_lookups() {
+        let cache = TranslationCache::new();
+        cache.insert("charizard", "Spits fire", "Spits fire, forsooth");
+
+        assert!(cache.get("charizard").is_some());
+        assert!(cache.get("ditto").is_none());
+        assert!(cache.peek("charizard").is_some());
+        assert!(cache.peek("ditto").is_none());
+
+        assert_eq!(1, cache.hits());
+        assert_eq!(1, cache.misses());
+    }
+
+    #[test]
+    fn load_corrupt_file() {
+        let path = cache_path("corrupt-cache");
+        std::fs::write(&path, "{\"charizard\": {").unwrap();
+
+        let cache = TranslationCache::load(&path).unwrap();
+        assert!(cache.is_empty());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn expired_entries() {
+        let mut cache = TranslationCache::new();
+        cache.insert("charizard", "Spits fire", "Spits fire, forsooth");
+        cache
+            .entries
+            .write()
+            .unwrap()
+            .get_mut("charizard")
+            .unwrap()
+            .cached_at -= 120;
+
+        cache.set_ttl(Some(Duration::from_secs(60)));
+        assert!(cache.get("charizard").is_none());
+
+        cache.set_ttl(Some(Duration::from_secs(600)));
+        assert_eq!(120, cache.get("charizard").unwrap().age());
+    }
+}
@@ -0,0 +1,268 @@
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context};
+use async_signal::{Signal, Signals};
+use async_std::prelude::FutureExt;
+use clap::{Parser, Subcommand};
+use futures::StreamExt;
+use tide::listener::Listener;
+use tide_tracing::TraceMiddleware;
+
+use crate::config::{Config, Overrides};
+use crate::controllers::translate::validate_text;
+use crate::middlewares::api_key::ApiKeyMiddleware;
+use crate::middlewares::json_error::JsonErrorMiddleware;
+use crate::middlewares::logger::LogMiddleware;
+use crate::middlewares::metrics::MetricsMiddleware;
+use crate::middlewares::rate_limit::RateLimitMiddleware;
+use crate::middlewares::requestid::RequestIdMiddleware;
+use crate::server::{self, State};
+use crate::store::{ExportFormat, ImportFormat, TranslationStore};
+use crate::telemetry;
+use crate::wrappers::errors::ErrorKind;
+
+/// Pokémon descriptions in Shakespeare's style.
+#[derive(Parser, Debug)]
+#[command(version, about)]
+pub struct Cli {
+    #[command(flatten)]
+    pub overrides: Overrides,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Start the http server, the default command.
+    Serve,
+
+    /// Print the description of a pokemon in Shakespeare's style.
+    Lookup {
+        /// Name or id of the pokemon.
+        name: String,
+    },
+
+    /// Print a text translated to Shakespeare's style.
+    Translate { text: String },
+
+    /// Translate the pokemons listed in a file, one per line, and store them in the cache file.
+    WarmCache { file: PathBuf },
+
+    /// Print the recorded translations, or write them to a file.
+    Export {
+        /// jsonl, csv or markdown.
+        #[arg(long, default_value = "jsonl")]
+        format: ExportFormat,
+
+        /// File to write, stdout if not set.
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Import manual translations, served instead of the machine ones.
+    Import {
+        /// JSON Lines or CSV file with `name` and `translation` fields.
+        file: PathBuf,
+
+        /// jsonl or csv, guessed from the file extension if not set.
+        #[arg(long)]
+        format: Option<ImportFormat>,
+    },
+
+    /// Validate the settings and print the effective config.
+    CheckConfig,
+}
+
+pub async fn run(cli: Cli) -> anyhow::Result<()> {
+    let config = Config::load(&cli.overrides)?;
+
+    match cli.command.unwrap_or(Command::Serve) {
+        Command::Serve => serve(&config).await,
+        Command::Lookup { name } => lookup(&config, &name).await,
+        Command::Translate { text } => translate(&config, &text).await,
+        Command::WarmCache { file } => warm_cache(&config, &file).await,
+        Command::Export { format, output } => export(&config, format, output.as_deref()),
+        Command::Import { file, format } => import(&config, &file, format).await,
+        Command::CheckConfig => check_config(&config),
+    }
+}
+
+async fn serve(config: &Config) -> anyhow::Result<()> {
+    tide::log::start();
+
+    let tracer_provider = telemetry::init(&config.tracing)?;
+    let state = State::from_config(config)?;
+    if config.admin.token.is_none() {
+        tide::log::warn!("The admin api is disabled, set ADMIN_TOKEN to enable it");
+    }
+    if let Some(warmup) = state.warmup.clone() {
+        warmup.spawn();
+    }
+    let metrics = state.metrics.clone();
+    let cache = state.cache.clone();
+    let store = state.store.clone();
+    let mut app = server::build_with_state(state).await;
+
+    app.with(TraceMiddleware::new());
+    app.with(RequestIdMiddleware::with_policy(config.request_id.policy));
+    app.with(LogMiddleware::new());
+    app.with(MetricsMiddleware::new(metrics));
+    app.with(JsonErrorMiddleware::new());
+    // Inside JsonErrorMiddleware, so the rejections are rendered as problems.
+    if config.rate_limit.enabled {
+        app.with(RateLimitMiddleware::new(&config.rate_limit));
+    }
+    if config.auth.enabled {
+        let keys = config.auth.load_keys()?;
+        app.with(ApiKeyMiddleware::new(keys, config.auth.quota_window()));
+    }
+
+    let mut listener = app
+        .bind(config.address())
+        .await
+        .context("Can't bind the port")?;
+
+    for info in listener.info().iter() {
+        println!("Server listening on {}", info);
+    }
+
+    // serve until stopped, then flush what is only kept in memory
+    let mut signals =
+        Signals::new([Signal::Term, Signal::Int]).context("Can't listen for stop signals")?;
+    let stopped = async {
+        if let Some(signal) = signals.next().await {
+            tide::log::info!("Stopping on {:?}", signal?);
+        }
+        Ok(())
+    };
+    listener.accept().race(stopped).await?;
+
+    if let Err(e) = cache.save().await {
+        tide::log::error!("Error: {}, saving cache", e);
+    }
+    store.flush().await;
+    if let Some(tracer_provider) = tracer_provider {
+        tracer_provider.shutdown()?;
+    }
+    Ok(())
+}
+
+async fn lookup(config: &Config, name: &str) -> anyhow::Result<()> {
+    let state = State::from_config(config)?;
+    let entry = state.translated_description(name).await?;
+
+    println!("{}", entry.translation);
+    Ok(())
+}
+
+async fn translate(config: &Config, text: &str) -> anyhow::Result<()> {
+    if let Err(reason) = validate_text(text) {
+        bail!(reason);
+    }
+
+    let translation = config.shakespeare_wrapper().get_translation(text).await?;
+
+    println!("{}", translation);
+    Ok(())
+}
+
+async fn warm_cache(config: &Config, file: &Path) -> anyhow::Result<()> {
+    if config.cache.path.is_none() {
+        bail!("warm-cache needs a cache file, set it with --cache-file or CACHE_FILE");
+    }
+
+    let content =
+        std::fs::read_to_string(file).with_context(|| format!("Can't read {}", file.display()))?;
+    let state = State::from_config(config)?;
+
+    let names = content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'));
+
+    let mut failed = 0;
+    for name in names {
+        if state.cache.peek(name).is_some() {
+            println!("{}: already cached", name);
+            continue;
+        }
+
+        match state.translated_description(name).await {
+            Ok(_) => println!("{}: cached", name),
+            // Nothing else can be translated until the quota is restored.
+            Err(e) if e.kind() == ErrorKind::TooManyRequests => {
+                bail!("{}: {}, stopping", name, e);
+            }
+            Err(e) => {
+                failed += 1;
+                eprintln!("{}: {}", name, e);
+            }
+        }
+    }
+
+    state.cache.save().await?;
+    println!("{} pokemons in the cache", state.cache.len());
+
+    if failed > 0 {
+        bail!("{} pokemons couldn't be cached", failed);
+    }
+    Ok(())
+}
+
+fn export(config: &Config, format: ExportFormat, output: Option<&Path>) -> anyhow::Result<()> {
+    let path = match &config.store.path {
+        Some(path) => path,
+        None => bail!("export needs a store file, set it with --store-file or STORE_FILE"),
+    };
+    let store = TranslationStore::load(path)?;
+
+    match output {
+        Some(output) => {
+            let file = std::fs::File::create(output)
+                .with_context(|| format!("Can't create {}", output.display()))?;
+            let mut writer = BufWriter::new(file);
+            store.export(format, &mut writer)?;
+            writer.flush()?;
+            eprintln!("{} translations exported", store.len());
+        }
+        None => store.export(format, std::io::stdout().lock())?,
+    }
+
+    Ok(())
+}
+
+async fn import(config: &Config, file: &Path, format: Option<ImportFormat>) -> anyhow::Result<()> {
+    let path = match &config.overrides.path {
+        Some(path) => path,
+        None => {
+            bail!("import needs an overrides file, set it with --overrides-file or OVERRIDES_FILE")
+        }
+    };
+    let overrides = TranslationStore::load(path)?;
+
+    let content =
+        std::fs::File::open(file).with_context(|| format!("Can't read {}", file.display()))?;
+    let format = format.unwrap_or_else(|| ImportFormat::from_path(file));
+    let imported = overrides.import(format, content).await?;
+
+    println!(
+        "{} translations imported, {} manual translations",
+        imported,
+        overrides.len()
+    );
+    Ok(())
+}
+
+fn check_config(config: &Config) -> anyhow::Result<()> {
+    print!("{}", toml::to_string_pretty(config)?);
+
+    let api_key = match config.translation.api_key {
+        Some(_) => "set",
+        None => "not set",
+    };
+    println!("\n# translation.api_key is {}", api_key);
+
+    Ok(())
+}
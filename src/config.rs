@@ -0,0 +1,1137 @@
+//! Typed settings of the service.
+//!
+//! Settings are resolved in the following order, each layer overriding the previous one:
+//!
+//! 1. Defaults.
+//! 2. TOML file, given by `--config` or the `CONFIG_FILE` environment variable.
+//! 3. Environment variables (a `.env` file is loaded by `main` before).
+//! 4. Command line flags.
+
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+use tide::http::headers::HeaderValue;
+use tide::http::Url;
+use tide::security::{CorsMiddleware, Origin};
+
+use crate::wrappers::pokemon::{PokemonWrapper, POKEMON_SERVICE_URI};
+use crate::wrappers::shakespeare::{ShakespeareWrapper, TRANSLATION_SERVICE_URI};
+use crate::wrappers::DEFAULT_TIMEOUT;
+
+pub const CONFIG_FILE_ENV: &str = "CONFIG_FILE";
+
+#[derive(thiserror::Error, Debug)]
+pub enum ConfigError {
+    #[error("Can't read config file {path}: {source}")]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("Invalid config file {path}: {source}")]
+    Parse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+
+    #[error("Invalid value \"{value}\" for environment variable {name}")]
+    InvalidEnv { name: &'static str, value: String },
+
+    #[error("Invalid setting {setting}: {reason}")]
+    Invalid {
+        setting: &'static str,
+        reason: String,
+    },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub pokemon: PokemonConfig,
+    pub translation: TranslationConfig,
+    pub cache: CacheConfig,
+    pub store: StoreConfig,
+    pub overrides: OverridesConfig,
+    pub warmup: WarmupConfig,
+    pub admin: AdminConfig,
+    pub auth: AuthConfig,
+    pub rate_limit: RateLimitConfig,
+    pub cors: CorsConfig,
+    pub versions: VersionsConfig,
+    pub tracing: TracingConfig,
+    pub request_id: RequestIdConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub host: String,
+    pub port: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PokemonConfig {
+    pub base_url: String,
+    pub timeout_secs: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TranslationConfig {
+    pub base_url: String,
+    /// Secret of a funtranslations subscription, never serialized.
+    #[serde(skip_serializing)]
+    pub api_key: Option<String>,
+    pub timeout_secs: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    /// File to persist the translations, they are only kept in memory if not set.
+    pub path: Option<PathBuf>,
+    /// Seconds a translation is valid, they never expire if not set.
+    pub ttl_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StoreConfig {
+    /// JSON Lines file recording every translation served, to be exported.
+    pub path: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OverridesConfig {
+    /// JSON Lines file with the imported manual translations.
+    pub path: Option<PathBuf>,
+}
+
+/// Background pre-translation of the whole pokédex, see `warmup`.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WarmupConfig {
+    pub enabled: bool,
+    /// Seconds between two translations.
+    pub interval_secs: u64,
+    /// Max translations per hour, by default less than half of the 5 of the funtranslations free
+    /// plan, the rest is left for the requests.
+    pub hourly_budget: u32,
+    /// Max translations per day, by default half of the 60 of the free plan.
+    pub daily_budget: u32,
+    /// Translations of the api quota kept for the requests, nothing is warmed up while the quota
+    /// left is at or below it.
+    pub quota_reserve: u32,
+    /// File to persist the progress, so it resumes after restarts.
+    pub progress_path: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AdminConfig {
+    /// Bearer token required by the `/admin` routes, which are disabled if not set. Never
+    /// serialized.
+    #[serde(skip_serializing)]
+    pub token: Option<String>,
+}
+
+/// API keys required to call the public routes, see `middlewares::api_key`.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    pub enabled: bool,
+    /// TOML file with the `[[keys]]` allowed, required if enabled.
+    pub keys_path: Option<PathBuf>,
+    /// Length of the window the key quotas are counted in.
+    pub quota_window_secs: u64,
+}
+
+/// Token buckets limiting the requests of each client ip, see `middlewares::rate_limit`. The
+/// WebSocket sessions get a bucket with these settings even if disabled.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitConfig {
+    pub enabled: bool,
+    /// Size of the bucket, i.e. max burst of requests.
+    pub capacity: u32,
+    /// Tokens added to the bucket every second.
+    pub refill_per_sec: f64,
+    /// Proxies whose `X-Forwarded-For` header is trusted to find the client ip.
+    pub trusted_proxies: Vec<IpAddr>,
+}
+
+/// Cross-origin requests allowed to browser clients.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorsConfig {
+    pub enabled: bool,
+    /// Origins allowed, `*` allows any.
+    pub allowed_origins: Vec<String>,
+    pub allowed_methods: Vec<String>,
+    pub allowed_headers: Vec<String>,
+    /// Response headers readable by the browser clients.
+    pub exposed_headers: Vec<String>,
+    /// Seconds the browsers can cache a preflight response.
+    pub max_age_secs: u64,
+    /// Allow cookies and auth headers, not compatible with the `*` origin.
+    pub allow_credentials: bool,
+}
+
+/// Lifecycle of each api version, see `middlewares::deprecation`.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VersionsConfig {
+    /// Also applies to the unversioned routes, which are aliases of v1.
+    pub v1: VersionConfig,
+    pub v2: VersionConfig,
+}
+
+/// OpenTelemetry traces, exported with OTLP over HTTP.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TracingConfig {
+    pub enabled: bool,
+    /// Url of the traces endpoint of the collector.
+    pub endpoint: String,
+    pub protocol: OtlpProtocol,
+    /// `service.name` of the exported spans.
+    pub service_name: String,
+}
+
+/// Encoding of the exported spans.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum OtlpProtocol {
+    #[serde(rename = "http/protobuf")]
+    HttpProtobuf,
+    #[serde(rename = "http/json")]
+    HttpJson,
+}
+
+impl std::str::FromStr for OtlpProtocol {
+    type Err = String;
+
+    fn from_str(protocol: &str) -> Result<Self, Self::Err> {
+        match protocol {
+            "http/protobuf" => Ok(OtlpProtocol::HttpProtobuf),
+            "http/json" => Ok(OtlpProtocol::HttpJson),
+            _ => Err(format!(
+                "Unsupported protocol {}, use http/protobuf or http/json",
+                protocol
+            )),
+        }
+    }
+}
+
+/// Correlation ids given by the clients in `X-Request-Id`.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RequestIdConfig {
+    pub policy: RequestIdPolicy,
+}
+
+/// Ids accepted from the clients, the rest are replaced by a new UUID.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RequestIdPolicy {
+    #[default]
+    Uuid,
+    /// UUIDs and ULIDs.
+    UuidOrUlid,
+    /// Any id of up to 128 letters, digits, `-`, `_`, `.` or `:`.
+    Any,
+}
+
+impl std::str::FromStr for RequestIdPolicy {
+    type Err = String;
+
+    fn from_str(policy: &str) -> Result<Self, Self::Err> {
+        match policy {
+            "uuid" => Ok(RequestIdPolicy::Uuid),
+            "uuid_or_ulid" => Ok(RequestIdPolicy::UuidOrUlid),
+            "any" => Ok(RequestIdPolicy::Any),
+            _ => Err(format!(
+                "Unsupported policy {}, use uuid, uuid_or_ulid or any",
+                policy
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VersionConfig {
+    /// HTTP date since the version is deprecated, announced in the `Deprecation` header.
+    pub deprecation: Option<String>,
+    /// HTTP date when the version will be removed, announced in the `Sunset` header.
+    pub sunset: Option<String>,
+    /// Migration guide, sent as a `Link` header with the `deprecation` relation.
+    pub link: Option<String>,
+}
+
+/// API key of a client, as listed in the keys file.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ApiKey {
+    pub key: String,
+    /// Name of the client, attached to its requests and logs.
+    pub client: String,
+    /// Max requests per quota window, unlimited if not set.
+    pub quota: Option<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct KeysFile {
+    #[serde(default)]
+    keys: Vec<ApiKey>,
+}
+
+/// Settings that can be given as command line flags.
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct Overrides {
+    /// Path of the TOML config file.
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Address to listen on.
+    #[arg(long, global = true)]
+    pub host: Option<String>,
+
+    /// Port to listen on.
+    #[arg(long, global = true)]
+    pub port: Option<u16>,
+
+    /// Base url of the pokemon api.
+    #[arg(long, global = true)]
+    pub pokemon_api_url: Option<String>,
+
+    /// Base url of the translation api.
+    #[arg(long, global = true)]
+    pub translation_api_url: Option<String>,
+
+    /// Secret of a funtranslations subscription.
+    #[arg(long, global = true)]
+    pub translation_api_key: Option<String>,
+
+    /// File to persist the translations.
+    #[arg(long, global = true)]
+    pub cache_file: Option<PathBuf>,
+
+    /// File recording the translations served.
+    #[arg(long, global = true)]
+    pub store_file: Option<PathBuf>,
+
+    /// File with the imported manual translations.
+    #[arg(long, global = true)]
+    pub overrides_file: Option<PathBuf>,
+
+    /// Bearer token of the admin api.
+    #[arg(long, global = true)]
+    pub admin_token: Option<String>,
+
+    /// TOML file with the API keys allowed, enables the API key authentication.
+    #[arg(long, global = true)]
+    pub api_keys_file: Option<PathBuf>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            host: "0.0.0.0".to_string(),
+            port: 5000,
+        }
+    }
+}
+
+impl Default for PokemonConfig {
+    fn default() -> Self {
+        Self {
+            base_url: POKEMON_SERVICE_URI.to_string(),
+            timeout_secs: DEFAULT_TIMEOUT.as_secs(),
+        }
+    }
+}
+
+impl Default for TranslationConfig {
+    fn default() -> Self {
+        Self {
+            base_url: TRANSLATION_SERVICE_URI.to_string(),
+            api_key: None,
+            timeout_secs: DEFAULT_TIMEOUT.as_secs(),
+        }
+    }
+}
+
+impl Default for WarmupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_secs: 60,
+            hourly_budget: 2,
+            daily_budget: 30,
+            quota_reserve: 2,
+            progress_path: None,
+        }
+    }
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            keys_path: None,
+            quota_window_secs: 60 * 60,
+        }
+    }
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            capacity: 10,
+            refill_per_sec: 0.5,
+            trusted_proxies: Vec::new(),
+        }
+    }
+}
+
+impl Default for TracingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            endpoint: "http://localhost:4318/v1/traces".to_string(),
+            protocol: OtlpProtocol::HttpProtobuf,
+            service_name: "poke-spearify".to_string(),
+        }
+    }
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        let strings = |values: &[&str]| values.iter().map(|value| value.to_string()).collect();
+        Self {
+            enabled: false,
+            allowed_origins: strings(&["*"]),
+            allowed_methods: strings(&["GET", "POST", "OPTIONS"]),
+            allowed_headers: strings(&[
+                "Content-Type",
+                "X-Api-Key",
+                "X-Request-Id",
+                "traceparent",
+                "tracestate",
+            ]),
+            exposed_headers: strings(&[
+                "X-Request-Id",
+                "traceparent",
+                "tracestate",
+                "Retry-After",
+                "RateLimit-Limit",
+                "RateLimit-Remaining",
+                "RateLimit-Reset",
+                "Deprecation",
+                "Sunset",
+                "Link",
+            ]),
+            max_age_secs: 24 * 60 * 60,
+            allow_credentials: false,
+        }
+    }
+}
+
+impl CorsConfig {
+    /// Middleware answering the preflight requests and adding the CORS headers.
+    pub fn middleware(&self) -> CorsMiddleware {
+        let header = |values: &[String]| -> HeaderValue {
+            values
+                .join(", ")
+                .parse()
+                .expect("CORS settings are validated")
+        };
+
+        let origin = if self.allowed_origins.iter().any(|origin| origin == "*") {
+            Origin::Any
+        } else {
+            Origin::List(self.allowed_origins.clone())
+        };
+
+        let mut cors = CorsMiddleware::new()
+            .allow_origin(origin)
+            .allow_methods(header(&self.allowed_methods))
+            .allow_headers(header(&self.allowed_headers))
+            .max_age(header(&[self.max_age_secs.to_string()]))
+            .allow_credentials(self.allow_credentials);
+        if !self.exposed_headers.is_empty() {
+            cors = cors.expose_headers(header(&self.exposed_headers));
+        }
+        cors
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        let lists = [
+            ("cors.allowed_origins", &self.allowed_origins),
+            ("cors.allowed_methods", &self.allowed_methods),
+            ("cors.allowed_headers", &self.allowed_headers),
+            ("cors.exposed_headers", &self.exposed_headers),
+        ];
+        for (setting, values) in lists {
+            let invalid = values
+                .iter()
+                .find(|value| value.trim().is_empty() || !value.is_ascii() || value.contains(','));
+            if let Some(value) = invalid {
+                return Err(ConfigError::Invalid {
+                    setting,
+                    reason: format!("invalid value \"{}\"", value),
+                });
+            }
+        }
+
+        if self.allow_credentials && self.allowed_origins.iter().any(|origin| origin == "*") {
+            return Err(ConfigError::Invalid {
+                setting: "cors.allow_credentials",
+                reason: "can't be used with the * origin".to_string(),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+impl VersionConfig {
+    pub fn deprecation(&self) -> Option<SystemTime> {
+        self.deprecation
+            .as_deref()
+            .and_then(|date| httpdate::parse_http_date(date).ok())
+    }
+
+    pub fn sunset(&self) -> Option<SystemTime> {
+        self.sunset
+            .as_deref()
+            .and_then(|date| httpdate::parse_http_date(date).ok())
+    }
+}
+
+impl VersionsConfig {
+    fn validate(&self) -> Result<(), ConfigError> {
+        let versions = [
+            (
+                ("versions.v1.deprecation", "versions.v1.sunset"),
+                "versions.v1.link",
+                &self.v1,
+            ),
+            (
+                ("versions.v2.deprecation", "versions.v2.sunset"),
+                "versions.v2.link",
+                &self.v2,
+            ),
+        ];
+        for ((deprecation, sunset), link, version) in versions {
+            let dates = [
+                (deprecation, &version.deprecation),
+                (sunset, &version.sunset),
+            ];
+            for (setting, date) in dates {
+                if let Some(date) = date {
+                    httpdate::parse_http_date(date).map_err(|_| ConfigError::Invalid {
+                        setting,
+                        reason: format!(
+                            "\"{}\" is not an HTTP date, e.g. Sun, 06 Nov 1994 08:49:37 GMT",
+                            date
+                        ),
+                    })?;
+                }
+            }
+
+            if let (Some(deprecated_at), Some(sunset_at)) =
+                (version.deprecation(), version.sunset())
+            {
+                if sunset_at < deprecated_at {
+                    return Err(ConfigError::Invalid {
+                        setting: sunset,
+                        reason: "must not be before the deprecation".to_string(),
+                    });
+                }
+            }
+
+            if let Some(url) = &version.link {
+                validate_url(link, url)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl AuthConfig {
+    /// Read the API keys from `keys_path`, none if not set.
+    pub fn load_keys(&self) -> Result<Vec<ApiKey>, ConfigError> {
+        let path = match &self.keys_path {
+            Some(path) => path,
+            None => return Ok(Vec::new()),
+        };
+
+        let content = std::fs::read_to_string(path).map_err(|source| ConfigError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let file: KeysFile = toml::from_str(&content).map_err(|source| ConfigError::Parse {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        Ok(file.keys)
+    }
+
+    pub fn quota_window(&self) -> Duration {
+        Duration::from_secs(self.quota_window_secs)
+    }
+}
+
+impl Config {
+    /// Resolve the settings from every layer and validate them.
+    pub fn load(overrides: &Overrides) -> Result<Self, ConfigError> {
+        let path = overrides
+            .config
+            .clone()
+            .or_else(|| std::env::var(CONFIG_FILE_ENV).ok().map(PathBuf::from));
+
+        let mut config = match path {
+            Some(path) => Self::from_file(&path)?,
+            None => Self::default(),
+        };
+        config.apply_env(|name| std::env::var(name).ok())?;
+        config.apply_overrides(overrides);
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let content = std::fs::read_to_string(path).map_err(|source| ConfigError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        toml::from_str(&content).map_err(|source| ConfigError::Parse {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    /// Override the settings with the environment variables returned by `var`.
+    pub fn apply_env<F>(&mut self, var: F) -> Result<(), ConfigError>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        if let Some(host) = var("HOST") {
+            self.server.host = host;
+        }
+        if let Some(port) = var("PORT") {
+            self.server.port = parse_env("PORT", port)?;
+        }
+        if let Some(base_url) = var("POKEMON_API_URL") {
+            self.pokemon.base_url = base_url;
+        }
+        if let Some(timeout) = var("POKEMON_API_TIMEOUT") {
+            self.pokemon.timeout_secs = parse_env("POKEMON_API_TIMEOUT", timeout)?;
+        }
+        if let Some(base_url) = var("TRANSLATION_API_URL") {
+            self.translation.base_url = base_url;
+        }
+        if let Some(api_key) = var("TRANSLATION_API_KEY") {
+            self.translation.api_key = Some(api_key);
+        }
+        if let Some(timeout) = var("TRANSLATION_API_TIMEOUT") {
+            self.translation.timeout_secs = parse_env("TRANSLATION_API_TIMEOUT", timeout)?;
+        }
+        if let Some(path) = var("CACHE_FILE") {
+            self.cache.path = Some(PathBuf::from(path));
+        }
+        if let Some(ttl) = var("CACHE_TTL") {
+            self.cache.ttl_secs = Some(parse_env("CACHE_TTL", ttl)?);
+        }
+        if let Some(path) = var("STORE_FILE") {
+            self.store.path = Some(PathBuf::from(path));
+        }
+        if let Some(path) = var("OVERRIDES_FILE") {
+            self.overrides.path = Some(PathBuf::from(path));
+        }
+        if let Some(token) = var("ADMIN_TOKEN") {
+            self.admin.token = Some(token);
+        }
+        if let Some(enabled) = var("AUTH_ENABLED") {
+            self.auth.enabled = parse_env("AUTH_ENABLED", enabled)?;
+        }
+        if let Some(path) = var("API_KEYS_FILE") {
+            self.auth.keys_path = Some(PathBuf::from(path));
+        }
+        if let Some(enabled) = var("RATE_LIMIT_ENABLED") {
+            self.rate_limit.enabled = parse_env("RATE_LIMIT_ENABLED", enabled)?;
+        }
+        if let Some(capacity) = var("RATE_LIMIT_CAPACITY") {
+            self.rate_limit.capacity = parse_env("RATE_LIMIT_CAPACITY", capacity)?;
+        }
+        if let Some(refill) = var("RATE_LIMIT_REFILL_PER_SEC") {
+            self.rate_limit.refill_per_sec = parse_env("RATE_LIMIT_REFILL_PER_SEC", refill)?;
+        }
+        if let Some(proxies) = var("TRUSTED_PROXIES") {
+            self.rate_limit.trusted_proxies = proxies
+                .split(',')
+                .map(str::trim)
+                .filter(|proxy| !proxy.is_empty())
+                .map(|proxy| parse_env("TRUSTED_PROXIES", proxy.to_string()))
+                .collect::<Result<_, _>>()?;
+        }
+        if let Some(enabled) = var("CORS_ENABLED") {
+            self.cors.enabled = parse_env("CORS_ENABLED", enabled)?;
+        }
+        if let Some(origins) = var("CORS_ALLOWED_ORIGINS") {
+            self.cors.allowed_origins = origins
+                .split(',')
+                .map(str::trim)
+                .filter(|origin| !origin.is_empty())
+                .map(str::to_string)
+                .collect();
+        }
+        if let Some(date) = var("API_V1_DEPRECATION") {
+            self.versions.v1.deprecation = Some(date);
+        }
+        if let Some(date) = var("API_V1_SUNSET") {
+            self.versions.v1.sunset = Some(date);
+        }
+        if let Some(link) = var("API_V1_LINK") {
+            self.versions.v1.link = Some(link);
+        }
+        if let Some(date) = var("API_V2_DEPRECATION") {
+            self.versions.v2.deprecation = Some(date);
+        }
+        if let Some(date) = var("API_V2_SUNSET") {
+            self.versions.v2.sunset = Some(date);
+        }
+        if let Some(link) = var("API_V2_LINK") {
+            self.versions.v2.link = Some(link);
+        }
+        if let Some(enabled) = var("TRACING_ENABLED") {
+            self.tracing.enabled = parse_env("TRACING_ENABLED", enabled)?;
+        }
+        if let Some(endpoint) = var("OTEL_EXPORTER_OTLP_TRACES_ENDPOINT") {
+            self.tracing.endpoint = endpoint;
+        }
+        if let Some(protocol) = var("OTEL_EXPORTER_OTLP_TRACES_PROTOCOL") {
+            self.tracing.protocol = parse_env("OTEL_EXPORTER_OTLP_TRACES_PROTOCOL", protocol)?;
+        }
+        if let Some(service_name) = var("OTEL_SERVICE_NAME") {
+            self.tracing.service_name = service_name;
+        }
+        if let Some(policy) = var("REQUEST_ID_POLICY") {
+            self.request_id.policy = parse_env("REQUEST_ID_POLICY", policy)?;
+        }
+        if let Some(enabled) = var("WARMUP_ENABLED") {
+            self.warmup.enabled = parse_env("WARMUP_ENABLED", enabled)?;
+        }
+        if let Some(path) = var("WARMUP_PROGRESS_FILE") {
+            self.warmup.progress_path = Some(PathBuf::from(path));
+        }
+
+        Ok(())
+    }
+
+    pub fn apply_overrides(&mut self, overrides: &Overrides) {
+        if let Some(host) = &overrides.host {
+            self.server.host = host.clone();
+        }
+        if let Some(port) = overrides.port {
+            self.server.port = port;
+        }
+        if let Some(base_url) = &overrides.pokemon_api_url {
+            self.pokemon.base_url = base_url.clone();
+        }
+        if let Some(base_url) = &overrides.translation_api_url {
+            self.translation.base_url = base_url.clone();
+        }
+        if let Some(api_key) = &overrides.translation_api_key {
+            self.translation.api_key = Some(api_key.clone());
+        }
+        if let Some(path) = &overrides.cache_file {
+            self.cache.path = Some(path.clone());
+        }
+        if let Some(path) = &overrides.store_file {
+            self.store.path = Some(path.clone());
+        }
+        if let Some(path) = &overrides.overrides_file {
+            self.overrides.path = Some(path.clone());
+        }
+        if let Some(token) = &overrides.admin_token {
+            self.admin.token = Some(token.clone());
+        }
+        if let Some(path) = &overrides.api_keys_file {
+            self.auth.enabled = true;
+            self.auth.keys_path = Some(path.clone());
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.server.port == 0 {
+            return Err(ConfigError::Invalid {
+                setting: "server.port",
+                reason: "must be greater than 0".to_string(),
+            });
+        }
+
+        validate_url("pokemon.base_url", &self.pokemon.base_url)?;
+        validate_url("translation.base_url", &self.translation.base_url)?;
+
+        if self.pokemon.timeout_secs == 0 {
+            return Err(ConfigError::Invalid {
+                setting: "pokemon.timeout_secs",
+                reason: "must be greater than 0".to_string(),
+            });
+        }
+        if self.translation.timeout_secs == 0 {
+            return Err(ConfigError::Invalid {
+                setting: "translation.timeout_secs",
+                reason: "must be greater than 0".to_string(),
+            });
+        }
+
+        if self.warmup.enabled && self.warmup.interval_secs == 0 {
+            return Err(ConfigError::Invalid {
+                setting: "warmup.interval_secs",
+                reason: "must be greater than 0".to_string(),
+            });
+        }
+
+        if let Some(api_key) = &self.translation.api_key {
+            if api_key.trim().is_empty() {
+                return Err(ConfigError::Invalid {
+                    setting: "translation.api_key",
+                    reason: "must not be empty".to_string(),
+                });
+            }
+        }
+        if self.auth.enabled && self.auth.keys_path.is_none() {
+            return Err(ConfigError::Invalid {
+                setting: "auth.keys_path",
+                reason: "is required when auth is enabled".to_string(),
+            });
+        }
+        self.cors.validate()?;
+        self.versions.validate()?;
+
+        if self.tracing.enabled {
+            validate_url("tracing.endpoint", &self.tracing.endpoint)?;
+            if self.tracing.service_name.trim().is_empty() {
+                return Err(ConfigError::Invalid {
+                    setting: "tracing.service_name",
+                    reason: "must not be empty".to_string(),
+                });
+            }
+        }
+
+        if self.cache.ttl_secs == Some(0) {
+            return Err(ConfigError::Invalid {
+                setting: "cache.ttl_secs",
+                reason: "must be greater than 0".to_string(),
+            });
+        }
+
+        if self.rate_limit.capacity == 0 {
+            return Err(ConfigError::Invalid {
+                setting: "rate_limit.capacity",
+                reason: "must be greater than 0".to_string(),
+            });
+        }
+        let refill_per_sec = self.rate_limit.refill_per_sec;
+        if refill_per_sec.is_nan() || refill_per_sec <= 0.0 {
+            return Err(ConfigError::Invalid {
+                setting: "rate_limit.refill_per_sec",
+                reason: "must be greater than 0".to_string(),
+            });
+        }
+        if self.auth.quota_window_secs == 0 {
+            return Err(ConfigError::Invalid {
+                setting: "auth.quota_window_secs",
+                reason: "must be greater than 0".to_string(),
+            });
+        }
+        if let Some(token) = &self.admin.token {
+            if token.trim().is_empty() {
+                return Err(ConfigError::Invalid {
+                    setting: "admin.token",
+                    reason: "must not be empty".to_string(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Address to bind the server, e.g. `0.0.0.0:5000`.
+    pub fn address(&self) -> String {
+        format!("{}:{}", self.server.host, self.server.port)
+    }
+
+    pub fn pokemon_wrapper(&self) -> PokemonWrapper {
+        let mut pokemon_wrapper = PokemonWrapper::with_base_url(&self.pokemon.base_url);
+        pokemon_wrapper.set_timeout(Duration::from_secs(self.pokemon.timeout_secs));
+        pokemon_wrapper
+    }
+
+    pub fn shakespeare_wrapper(&self) -> ShakespeareWrapper {
+        let mut shakespeare_wrapper = ShakespeareWrapper::with_base_url(&self.translation.base_url);
+        shakespeare_wrapper.set_api_key(self.translation.api_key.clone());
+        shakespeare_wrapper.set_timeout(Duration::from_secs(self.translation.timeout_secs));
+        shakespeare_wrapper
+    }
+}
+
+fn parse_env<T: std::str::FromStr>(name: &'static str, value: String) -> Result<T, ConfigError> {
+    value
+        .parse()
+        .map_err(|_| ConfigError::InvalidEnv { name, value })
+}
+
+fn validate_url(setting: &'static str, url: &str) -> Result<(), ConfigError> {
+    let parsed = Url::parse(url).map_err(|e| ConfigError::Invalid {
+        setting,
+        reason: e.to_string(),
+    })?;
+
+    match parsed.scheme() {
+        "http" | "https" => Ok(()),
+        scheme => Err(ConfigError::Invalid {
+            setting,
+            reason: format!("unsupported scheme {}", scheme),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    #[test]
+    fn layers_precedence() {
+        let mut config: Config = toml::from_str(
+            r#"
+            [server]
+            port = 8080
+            host = "127.0.0.1"
+
+            [translation]
+            api_key = "from-file"
+            "#,
+        )
+        .unwrap();
+
+        let env: HashMap<&str, &str> = vec![("PORT", "9090"), ("TRANSLATION_API_KEY", "from-env")]
+            .into_iter()
+            .collect();
+        config
+            .apply_env(|name| env.get(name).map(|value| value.to_string()))
+            .unwrap();
+
+        config.apply_overrides(&Overrides {
+            translation_api_key: Some("from-cli".to_string()),
+            ..Overrides::default()
+        });
+
+        assert_eq!("127.0.0.1", config.server.host);
+        assert_eq!(9090, config.server.port);
+        assert_eq!(Some("from-cli".to_string()), config.translation.api_key);
+        assert_eq!(POKEMON_SERVICE_URI, config.pokemon.base_url);
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn invalid_env() {
+        let mut config = Config::default();
+        let result = config.apply_env(|name| match name {
+            "PORT" => Some("http".to_string()),
+            _ => None,
+        });
+
+        assert!(matches!(
+            result,
+            Err(ConfigError::InvalidEnv { name: "PORT", .. })
+        ));
+    }
+
+    #[test]
+    fn unknown_setting_in_file() {
+        let result = toml::from_str::<Config>("[server]\nprot = 8080\n");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn invalid_base_url() {
+        let mut config = Config::default();
+        config.pokemon.base_url = "pokeapi.co".to_string();
+
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::Invalid {
+                setting: "pokemon.base_url",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn secrets_are_not_serialized() {
+        let mut config = Config::default();
+        config.translation.api_key = Some("secret".to_string());
+        config.admin.token = Some("secret".to_string());
+
+        let serialized = toml::to_string(&config).unwrap();
+        assert!(!serialized.contains("secret"));
+    }
+
+    #[test]
+    fn api_keys_file() {
+        let path =
+            std::env::temp_dir().join(format!("poke-spearify-keys-{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"
+            [[keys]]
+            key = "team-key"
+            client = "content-team"
+
+            [[keys]]
+            key = "scraper-key"
+            client = "scraper"
+            quota = 100
+            "#,
+        )
+        .unwrap();
+
+        let mut config = Config::default();
+        config.auth.enabled = true;
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::Invalid {
+                setting: "auth.keys_path",
+                ..
+            })
+        ));
+
+        config.auth.keys_path = Some(path.clone());
+        config.validate().unwrap();
+        let keys = config.auth.load_keys().unwrap();
+        assert_eq!(2, keys.len());
+        assert_eq!("scraper", keys[1].client);
+        assert_eq!(Some(100), keys[1].quota);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn cors_credentials_with_any_origin() {
+        let mut config = Config::default();
+        config.cors.allow_credentials = true;
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::Invalid {
+                setting: "cors.allow_credentials",
+                ..
+            })
+        ));
+
+        config.cors.allowed_origins = vec!["https://frontend.example.com".to_string()];
+        config.validate().unwrap();
+    }
+
+    #[test]
+    fn version_dates() {
+        let mut config = Config::default();
+        config.versions.v1.deprecation = Some("2026-01-01".to_string());
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::Invalid {
+                setting: "versions.v1.deprecation",
+                ..
+            })
+        ));
+
+        config.versions.v1.deprecation = Some("Thu, 01 Jan 2026 00:00:00 GMT".to_string());
+        config.versions.v1.sunset = Some("Wed, 31 Dec 2025 00:00:00 GMT".to_string());
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::Invalid {
+                setting: "versions.v1.sunset",
+                ..
+            })
+        ));
+
+        config.versions.v1.sunset = Some("Fri, 01 Jan 2027 00:00:00 GMT".to_string());
+        config.validate().unwrap();
+
+        config
+            .apply_env(|name| match name {
+                "API_V2_LINK" => Some("docs/migration".to_string()),
+                _ => None,
+            })
+            .unwrap();
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::Invalid {
+                setting: "versions.v2.link",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn tracing_env() {
+        let mut config = Config::default();
+        config
+            .apply_env(|name| match name {
+                "TRACING_ENABLED" => Some("true".to_string()),
+                "OTEL_EXPORTER_OTLP_TRACES_PROTOCOL" => Some("http/json".to_string()),
+                "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT" => Some("collector:4318".to_string()),
+                _ => None,
+            })
+            .unwrap();
+
+        assert_eq!(OtlpProtocol::HttpJson, config.tracing.protocol);
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::Invalid {
+                setting: "tracing.endpoint",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn request_id_policy_env() {
+        let mut config = Config::default();
+        config
+            .apply_env(|name| match name {
+                "REQUEST_ID_POLICY" => Some("uuid_or_ulid".to_string()),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(RequestIdPolicy::UuidOrUlid, config.request_id.policy);
+
+        let result = config.apply_env(|name| match name {
+            "REQUEST_ID_POLICY" => Some("ulid".to_string()),
+            _ => None,
+        });
+        assert!(matches!(
+            result,
+            Err(ConfigError::InvalidEnv {
+                name: "REQUEST_ID_POLICY",
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,327 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use async_std::channel::{self, Sender};
+use futures::{AsyncReadExt, StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use tide::{Body, Request, Response};
+use utoipa::ToSchema;
+
+use crate::server::State;
+use crate::store::{ExportFormat, ImportFormat, StoreError};
+use crate::warmup::Progress;
+use crate::wrappers::pokemon::normalize_name;
+
+/// Max size of an import body, far more than a row for every pokemon.
+const IMPORT_MAX_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Size of the chunks an export is streamed in.
+const EXPORT_CHUNK_BYTES: usize = 16 * 1024;
+/// Chunks written ahead of the client.
+const EXPORT_CHUNKS_AHEAD: usize = 4;
+
+#[derive(Deserialize)]
+struct ExportQuery {
+    format: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ImportQuery {
+    format: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, ToSchema)]
+pub struct ImportResponse {
+    /// Rows in the imported file.
+    pub imported: usize,
+    /// Manual translations after the import.
+    pub total: usize,
+}
+
+#[derive(Deserialize, Serialize, ToSchema)]
+pub struct UpstreamStatus {
+    pub base_url: String,
+    /// Errors returned by the wrapper since the start, by error code.
+    pub errors: BTreeMap<String, u64>,
+}
+
+#[derive(Deserialize, Serialize, ToSchema)]
+pub struct UpstreamsStatus {
+    pub pokemon: UpstreamStatus,
+    pub translation: UpstreamStatus,
+}
+
+#[derive(Deserialize, Serialize, ToSchema)]
+pub struct ClearCacheResponse {
+    pub removed: usize,
+}
+
+#[derive(Deserialize, Serialize, ToSchema)]
+pub struct WarmupStatus {
+    pub enabled: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remaining_hourly: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remaining_daily: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub progress: Option<Progress>,
+}
+
+/// Effective settings, without secrets.
+#[utoipa::path(
+    get,
+    path = "/admin/config",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Effective settings", content_type = "application/json"),
+        (status = 401, description = "Missing or invalid admin token", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("admin_token" = [])),
+)]
+pub async fn config(req: Request<State>) -> tide::Result {
+    let mut res = Response::new(200);
+    res.set_body(Body::from_json(&*req.state().config)?);
+    Ok(res)
+}
+
+/// Base url and error counters of the upstream apis.
+#[utoipa::path(
+    get,
+    path = "/admin/upstreams",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Status of the upstream apis", body = UpstreamsStatus),
+        (status = 401, description = "Missing or invalid admin token", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("admin_token" = [])),
+)]
+pub async fn upstreams(req: Request<State>) -> tide::Result {
+    let state = req.state();
+    let errors = |counts: BTreeMap<&'static str, u64>| {
+        counts
+            .into_iter()
+            .map(|(code, count)| (code.to_string(), count))
+            .collect()
+    };
+
+    let status = UpstreamsStatus {
+        pokemon: UpstreamStatus {
+            base_url: state.pokemon_wrapper.base_url().to_string(),
+            errors: errors(state.pokemon_wrapper.errors().counts()),
+        },
+        translation: UpstreamStatus {
+            base_url: state.shakespeare_wrapper.base_url().to_string(),
+            errors: errors(state.shakespeare_wrapper.errors().counts()),
+        },
+    };
+
+    let mut res = Response::new(200);
+    res.set_body(Body::from_json(&status)?);
+    Ok(res)
+}
+
+/// Forget every cached translation.
+#[utoipa::path(
+    delete,
+    path = "/admin/cache",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Number of translations removed", body = ClearCacheResponse),
+        (status = 401, description = "Missing or invalid admin token", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("admin_token" = [])),
+)]
+pub async fn clear_cache(req: Request<State>) -> tide::Result {
+    let removed = req.state().cache.clear();
+
+    let mut res = Response::new(200);
+    res.set_body(Body::from_json(&ClearCacheResponse { removed })?);
+    Ok(res)
+}
+
+/// Forget the cached translation of a pokemon, so the next lookup calls the upstream apis.
+#[utoipa::path(
+    delete,
+    path = "/admin/cache/{pokemon_name}",
+    tag = "admin",
+    params(("pokemon_name" = String, Path, description = "Name of the pokemon")),
+    responses(
+        (status = 204, description = "Translation removed"),
+        (status = 401, description = "Missing or invalid admin token", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "The pokemon is not in the cache", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("admin_token" = [])),
+)]
+pub async fn clear_cache_entry(req: Request<State>) -> tide::Result {
+    let pokemon_name = &normalize_name(req.param("pokemon_name")?);
+
+    match req.state().cache.remove(pokemon_name) {
+        Some(_) => Ok(Response::new(204)),
+        None => Err(tide::Error::from_str(
+            404,
+            format!("{} is not in the cache", pokemon_name),
+        )),
+    }
+}
+
+/// Progress of the pokédex warm up.
+#[utoipa::path(
+    get,
+    path = "/admin/warmup",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Warm up status", body = WarmupStatus),
+        (status = 401, description = "Missing or invalid admin token", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("admin_token" = [])),
+)]
+pub async fn warmup(req: Request<State>) -> tide::Result {
+    let status = match &req.state().warmup {
+        Some(warmup) => {
+            let progress = warmup.progress();
+            WarmupStatus {
+                enabled: true,
+                remaining_hourly: Some(progress.usage.remaining_hourly(warmup.config())),
+                remaining_daily: Some(progress.usage.remaining_daily(warmup.config())),
+                progress: Some(progress),
+            }
+        }
+        None => WarmupStatus {
+            enabled: false,
+            remaining_hourly: None,
+            remaining_daily: None,
+            progress: None,
+        },
+    };
+
+    let mut res = Response::new(200);
+    res.set_body(Body::from_json(&status)?);
+    Ok(res)
+}
+
+/// Dump the recorded translations, as JSON Lines unless another `format` is given.
+#[utoipa::path(
+    get,
+    path = "/admin/export",
+    tag = "admin",
+    params(("format" = Option<String>, Query, description = "jsonl (default), csv or markdown")),
+    responses(
+        (status = 200, description = "Recorded translations", content(
+            ("application/x-ndjson" = String),
+            ("text/csv" = String),
+            ("text/markdown" = String),
+        )),
+        (status = 400, description = "Unknown format", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid admin token", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("admin_token" = [])),
+)]
+pub async fn export(req: Request<State>) -> tide::Result {
+    let query: ExportQuery = req.query()?;
+    let format: ExportFormat = query
+        .format
+        .as_deref()
+        .unwrap_or("jsonl")
+        .parse()
+        .map_err(|reason: String| tide::Error::from_str(400, reason))?;
+
+    // the records are written by a blocking task as the client reads them
+    let (sender, receiver) = channel::bounded(EXPORT_CHUNKS_AHEAD);
+    let store = req.state().store.clone();
+    async_std::task::spawn_blocking(move || {
+        let mut writer = io::BufWriter::with_capacity(EXPORT_CHUNK_BYTES, ChunkWriter(sender));
+        if let Err(e) = store
+            .export(format, &mut writer)
+            .and_then(|_| writer.flush())
+        {
+            // the client is gone or the export failed, the body is cut short
+            tide::log::warn!("Error: {}, streaming export", e);
+        }
+    });
+
+    let mut res = Response::new(200);
+    res.set_body(Body::from_reader(
+        receiver.map(Ok::<_, io::Error>).into_async_read(),
+        None,
+    ));
+    res.set_content_type(format.content_type());
+    Ok(res)
+}
+
+/// Sends every write to the export body as a chunk.
+struct ChunkWriter(Sender<Vec<u8>>);
+
+impl Write for ChunkWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0
+            .send_blocking(buf.to_vec())
+            .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn too_big_import() -> tide::Error {
+    tide::Error::from_str(
+        413,
+        format!("The import can't be over {} bytes", IMPORT_MAX_BYTES),
+    )
+}
+
+/// Import manual translations from the body, JSON Lines unless another `format` is given.
+#[utoipa::path(
+    post,
+    path = "/admin/overrides",
+    tag = "admin",
+    params(("format" = Option<String>, Query, description = "jsonl (default) or csv")),
+    request_body(content = String, description = "Manual translations", content_type = "text/plain"),
+    responses(
+        (status = 200, description = "Manual translations imported", body = ImportResponse),
+        (status = 400, description = "Unknown format or invalid row, nothing is imported", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid admin token", body = Problem, content_type = "application/problem+json"),
+        (status = 413, description = "Body over the size limit, nothing is imported", body = Problem, content_type = "application/problem+json"),
+        (status = 500, description = "Overrides file can't be written, nothing is imported", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("admin_token" = [])),
+)]
+pub async fn import(mut req: Request<State>) -> tide::Result {
+    let query: ImportQuery = req.query()?;
+    let format: ImportFormat = query
+        .format
+        .as_deref()
+        .unwrap_or("jsonl")
+        .parse()
+        .map_err(|reason: String| tide::Error::from_str(400, reason))?;
+
+    if req.len().is_some_and(|len| len as u64 > IMPORT_MAX_BYTES) {
+        return Err(too_big_import());
+    }
+    // without a length, the body is read up to one byte over the limit to tell if it's over
+    let mut content = Vec::new();
+    req.take_body()
+        .take(IMPORT_MAX_BYTES + 1)
+        .read_to_end(&mut content)
+        .await?;
+    if content.len() as u64 > IMPORT_MAX_BYTES {
+        return Err(too_big_import());
+    }
+
+    let overrides = &req.state().overrides;
+    let imported = overrides
+        .import(format, io::Cursor::new(content))
+        .await
+        .map_err(|e| match e {
+            StoreError::Write { .. } => tide::Error::from_str(500, e.to_string()),
+            _ => tide::Error::from_str(400, e.to_string()),
+        })?;
+
+    let mut res = Response::new(200);
+    res.set_body(Body::from_json(&ImportResponse {
+        imported,
+        total: overrides.len(),
+    })?);
+    Ok(res)
+}
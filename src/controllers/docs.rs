@@ -0,0 +1,168 @@
+//! OpenAPI document of the api, generated from the annotations of the handlers.
+
+use std::time::Duration;
+
+use tide::http::cache::{CacheControl, CacheDirective};
+use tide::http::mime;
+use tide::{Body, Request, Response};
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+
+use super::{admin, graphql, health_check, metrics, pokemon, translate, ui, ws};
+use crate::middlewares::api_key::API_KEY_HEADER;
+use crate::middlewares::json_error::Problem;
+use crate::server::State;
+use crate::warmup::{Progress, Usage};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "poke-spearify",
+        description = "Pokémon descriptions in Shakespeare's style."
+    ),
+    paths(
+        ui::index,
+        ui::search,
+        ui::pokemon,
+        ui::style,
+        docs,
+        docs_script,
+        openapi,
+        health_check::health_check,
+        metrics::metrics,
+        pokemon::get,
+        pokemon::batch,
+        pokemon::batch_stream,
+        pokemon::get_v2,
+        translate::post,
+        graphql::graphiql,
+        graphql::post,
+        ws::session,
+        admin::config,
+        admin::upstreams,
+        admin::clear_cache,
+        admin::clear_cache_entry,
+        admin::warmup,
+        admin::export,
+        admin::import,
+    ),
+    components(schemas(
+        pokemon::PokemonResponse,
+        pokemon::PokemonResponseV2,
+        pokemon::BatchRequest,
+        pokemon::BatchItem,
+        pokemon::BatchResponse,
+        pokemon::BatchProgress,
+        pokemon::BatchSummary,
+        translate::TranslateRequest,
+        translate::TranslateResponse,
+        admin::UpstreamsStatus,
+        admin::UpstreamStatus,
+        admin::ClearCacheResponse,
+        admin::WarmupStatus,
+        admin::ImportResponse,
+        Progress,
+        Usage,
+        Problem,
+    )),
+    modifiers(&SecuritySchemes, &VersionAliases)
+)]
+pub struct ApiDoc;
+
+/// Add the api key and admin token schemes referenced by the routes.
+struct SecuritySchemes;
+
+impl Modify for SecuritySchemes {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "api_key",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new(API_KEY_HEADER))),
+        );
+        components.add_security_scheme(
+            "admin_token",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+    }
+}
+
+/// The unversioned pokemon routes are aliases of v1, add them again under `/v1`.
+struct VersionAliases;
+
+impl Modify for VersionAliases {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let aliases: Vec<_> = openapi
+            .paths
+            .paths
+            .iter()
+            .filter(|(path, _)| path.starts_with("/pokemon/"))
+            .map(|(path, item)| (format!("/v1{}", path), item.clone()))
+            .collect();
+        openapi.paths.paths.extend(aliases);
+    }
+}
+
+/// Viewer of the OpenAPI document, its script is served with the api.
+const DOCS_PAGE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>poke-spearify api</title>
+<link rel="stylesheet" href="/static/style.css">
+</head>
+<body>
+<main data-spec-url="/openapi.json">Loading the documentation...</main>
+<script src="/static/docs.js"></script>
+</body>
+</html>
+"#;
+
+/// Script of the viewer, served at `/static/docs.js`.
+const DOCS_SCRIPT: &str = include_str!("../../static/docs.js");
+
+/// Freshness of the script of the viewer.
+const DOCS_SCRIPT_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[utoipa::path(
+    get,
+    path = "/openapi.json",
+    tag = "docs",
+    responses((status = 200, description = "This document", content_type = "application/json")),
+)]
+pub async fn openapi(_req: Request<State>) -> tide::Result {
+    let mut res = Response::new(200);
+    res.set_body(Body::from_json(&ApiDoc::openapi())?);
+    Ok(res)
+}
+
+#[utoipa::path(
+    get,
+    path = "/docs",
+    tag = "docs",
+    responses((status = 200, description = "Browsable documentation", content_type = "text/html")),
+)]
+pub async fn docs(_req: Request<State>) -> tide::Result {
+    let mut res = Response::new(200);
+    res.set_body(DOCS_PAGE);
+    res.set_content_type(mime::HTML);
+    Ok(res)
+}
+
+#[utoipa::path(
+    get,
+    path = "/static/docs.js",
+    tag = "docs",
+    responses((status = 200, description = "Script of the documentation viewer", content_type = "application/javascript")),
+)]
+pub async fn docs_script(_req: Request<State>) -> tide::Result {
+    let mut cache_control = CacheControl::new();
+    cache_control.push(CacheDirective::Public);
+    cache_control.push(CacheDirective::MaxAge(DOCS_SCRIPT_MAX_AGE));
+
+    let mut res = Response::new(200);
+    res.set_body(DOCS_SCRIPT);
+    res.set_content_type(mime::JAVASCRIPT);
+    cache_control.apply(&mut res);
+    Ok(res)
+}
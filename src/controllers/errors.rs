@@ -0,0 +1,50 @@
+use tide::StatusCode;
+
+use crate::wrappers::errors::WrapperError;
+
+/// How an error is exposed to the clients of our api.
+pub trait HttpError {
+    /// Status code of the response.
+    fn status(&self) -> StatusCode;
+
+    /// Client facing explanation of the error, without upstream details.
+    fn explanation(&self) -> &'static str;
+}
+
+impl HttpError for WrapperError {
+    fn status(&self) -> StatusCode {
+        match self {
+            WrapperError::NotFound { .. } => StatusCode::NotFound,
+            WrapperError::NoDescription => StatusCode::NotFound,
+            WrapperError::TooManyRequests { .. } => StatusCode::TooManyRequests,
+            WrapperError::ParsingError { .. } => StatusCode::BadGateway,
+            WrapperError::NetworkError { .. } => StatusCode::ServiceUnavailable,
+            WrapperError::Timeout { .. } => StatusCode::GatewayTimeout,
+            WrapperError::UpstreamError { status: 503, .. } => StatusCode::ServiceUnavailable,
+            WrapperError::UpstreamError { .. } => StatusCode::BadGateway,
+            WrapperError::UnexpectedError { .. } => StatusCode::InternalServerError,
+        }
+    }
+
+    fn explanation(&self) -> &'static str {
+        match self {
+            WrapperError::NotFound { .. } => "Not Found",
+            WrapperError::NoDescription => "The pokemon has no english description to translate",
+            WrapperError::TooManyRequests { .. } => "Too Many Requests",
+            WrapperError::ParsingError { .. } => "Invalid response from an upstream service",
+            WrapperError::NetworkError { .. } => "Upstream service unavailable",
+            WrapperError::Timeout { .. } => "Upstream service timed out",
+            WrapperError::UpstreamError { status: 503, .. } => "Upstream service unavailable",
+            WrapperError::UpstreamError { .. } => "Upstream service error",
+            WrapperError::UnexpectedError { .. } => "Unexpected Error",
+        }
+    }
+}
+
+/// Convert an error into a `tide::Error` with the mapped status.
+///
+/// The original error is kept as source so `JsonErrorMiddleware` can render its code. Note that
+/// `?` alone would also convert it, but always as an internal server error.
+pub fn to_tide_error(e: WrapperError) -> tide::Error {
+    tide::Error::new(e.status(), e)
+}
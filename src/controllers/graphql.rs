@@ -0,0 +1,42 @@
+use async_graphql::http::GraphiQLSource;
+use tide::http::mime;
+use tide::{Request, Response};
+
+use crate::graphql;
+use crate::middlewares::extension_types::Quotas;
+use crate::server::State;
+
+#[utoipa::path(
+    post,
+    path = "/graphql",
+    tag = "graphql",
+    request_body(content = String, description = "GraphQL request", content_type = "application/json"),
+    responses(
+        (status = 200, description = "GraphQL response, with the errors of each field", content_type = "application/json"),
+        (status = 400, description = "Invalid GraphQL request"),
+    ),
+    security((), ("api_key" = [])),
+)]
+pub async fn post(req: Request<State>) -> tide::Result {
+    let state = req.state().clone();
+    let quotas = req.ext::<Quotas>().cloned().unwrap_or_default();
+    let request = async_graphql_tide::receive_request(req).await?;
+    let response = graphql::schema()
+        .execute(graphql::with_loaders(request, &state, quotas))
+        .await;
+    async_graphql_tide::respond(response)
+}
+
+/// GraphiQL playground, to explore the schema during development.
+#[utoipa::path(
+    get,
+    path = "/graphql",
+    tag = "graphql",
+    responses((status = 200, description = "GraphiQL playground", content_type = "text/html")),
+)]
+pub async fn graphiql(_req: Request<State>) -> tide::Result {
+    let mut res = Response::new(200);
+    res.set_body(GraphiQLSource::build().endpoint("/graphql").finish());
+    res.set_content_type(mime::HTML);
+    Ok(res)
+}
@@ -1,6 +1,12 @@
 use crate::server::State;
 use tide::{Request, Response};
 
+#[utoipa::path(
+    get,
+    path = "/health_check",
+    tag = "health",
+    responses((status = 200, description = "The server is up")),
+)]
 pub async fn health_check(_req: Request<State>) -> tide::Result {
     Ok(Response::new(200))
 }
@@ -0,0 +1,161 @@
+use tide::{Request, Response};
+
+use crate::metrics::{Exposition, Histogram};
+use crate::server::State;
+use crate::wrappers::errors::{ErrorCounters, Upstream};
+
+/// Content type of the text exposition format.
+const EXPOSITION_CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Metrics of the server, in the Prometheus text format.
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "metrics",
+    responses((status = 200, description = "Prometheus metrics", content_type = "text/plain")),
+)]
+pub async fn metrics(req: Request<State>) -> tide::Result {
+    let state = req.state();
+    let mut exposition = Exposition::new();
+
+    let requests = state.metrics.requests();
+    exposition.family(
+        "http_requests_total",
+        "counter",
+        "Requests served, by route and status.",
+    );
+    for ((route, status), data) in &requests {
+        let status = status.to_string();
+        let labels = [("route", route.as_str()), ("status", status.as_str())];
+        exposition.sample("http_requests_total", &labels, data.count() as f64);
+    }
+    exposition.family(
+        "http_request_duration_seconds",
+        "histogram",
+        "Time to serve a request, by route and status.",
+    );
+    for ((route, status), data) in &requests {
+        let status = status.to_string();
+        let labels = [("route", route.as_str()), ("status", status.as_str())];
+        exposition.histogram("http_request_duration_seconds", &labels, data);
+    }
+
+    let upstreams: [(Upstream, &Histogram, &ErrorCounters); 2] = [
+        (
+            Upstream::Pokemon,
+            state.pokemon_wrapper.latency(),
+            state.pokemon_wrapper.errors(),
+        ),
+        (
+            Upstream::Shakespeare,
+            state.shakespeare_wrapper.latency(),
+            state.shakespeare_wrapper.errors(),
+        ),
+    ];
+    exposition.family(
+        "upstream_requests_total",
+        "counter",
+        "Calls to the upstream apis.",
+    );
+    for (upstream, latency, _) in &upstreams {
+        let labels = [("upstream", upstream.as_str())];
+        let calls = latency.snapshot().count();
+        exposition.sample("upstream_requests_total", &labels, calls as f64);
+    }
+    exposition.family(
+        "upstream_request_duration_seconds",
+        "histogram",
+        "Time to get the response of an upstream api.",
+    );
+    for (upstream, latency, _) in &upstreams {
+        let labels = [("upstream", upstream.as_str())];
+        exposition.histogram(
+            "upstream_request_duration_seconds",
+            &labels,
+            &latency.snapshot(),
+        );
+    }
+    exposition.family(
+        "upstream_errors_total",
+        "counter",
+        "Failed calls to the upstream apis, by error code.",
+    );
+    for (upstream, _, errors) in &upstreams {
+        for (code, count) in errors.counts() {
+            let labels = [("upstream", upstream.as_str()), ("code", code)];
+            exposition.sample("upstream_errors_total", &labels, count as f64);
+        }
+    }
+
+    let hits = state.cache.hits();
+    let misses = state.cache.misses();
+    let lookups = hits + misses;
+    exposition
+        .family(
+            "cache_hits_total",
+            "counter",
+            "Translation cache lookups with a valid entry.",
+        )
+        .sample("cache_hits_total", &[], hits as f64)
+        .family(
+            "cache_misses_total",
+            "counter",
+            "Translation cache lookups without a valid entry.",
+        )
+        .sample("cache_misses_total", &[], misses as f64)
+        .family(
+            "cache_hit_ratio",
+            "gauge",
+            "Share of the translation cache lookups with a valid entry.",
+        )
+        .sample(
+            "cache_hit_ratio",
+            &[],
+            if lookups > 0 {
+                hits as f64 / lookups as f64
+            } else {
+                0.0
+            },
+        )
+        .family(
+            "cache_entries",
+            "gauge",
+            "Translations in the cache, including the expired ones.",
+        )
+        .sample("cache_entries", &[], state.cache.len() as f64);
+
+    // only known once the translation api has answered
+    if let Some(remaining) = state.shakespeare_wrapper.quota_remaining().get() {
+        exposition
+            .family(
+                "translation_quota_remaining",
+                "gauge",
+                "Translations left in the current window of the translation api.",
+            )
+            .sample("translation_quota_remaining", &[], remaining);
+    }
+    if let Some(warmup) = &state.warmup {
+        let usage = warmup.progress().usage;
+        exposition
+            .family(
+                "warmup_budget_remaining",
+                "gauge",
+                "Translations the warm up can still spend, by window.",
+            )
+            .sample(
+                "warmup_budget_remaining",
+                &[("window", "hourly")],
+                f64::from(usage.remaining_hourly(warmup.config())),
+            )
+            .sample(
+                "warmup_budget_remaining",
+                &[("window", "daily")],
+                f64::from(usage.remaining_daily(warmup.config())),
+            );
+    }
+
+    let mut res = Response::new(200);
+    res.set_body(exposition.finish());
+    res.insert_header("Content-Type", EXPOSITION_CONTENT_TYPE);
+    Ok(res)
+}
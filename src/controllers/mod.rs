@@ -1,2 +1,11 @@
+pub mod admin;
+pub mod docs;
+pub mod errors;
+pub mod graphql;
 pub mod health_check;
+pub mod metrics;
+pub mod negotiation;
 pub mod pokemon;
+pub mod translate;
+pub mod ui;
+pub mod ws;
@@ -0,0 +1,167 @@
+//! Choice of the representation of a response, from the `format` query parameter or the
+//! `Accept` header.
+
+use std::str::FromStr;
+
+use serde::Deserialize;
+use tide::http::headers::ACCEPT;
+use tide::Request;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Json,
+    Text,
+    Html,
+    Xml,
+    Yaml,
+}
+
+/// Formats in order of preference, the first one is used for `*/*` or without `Accept`.
+const FORMATS: &[Format] = &[
+    Format::Json,
+    Format::Text,
+    Format::Html,
+    Format::Xml,
+    Format::Yaml,
+];
+
+#[derive(Deserialize)]
+struct FormatQuery {
+    format: Option<String>,
+}
+
+impl Format {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Format::Json => "application/json",
+            Format::Text => "text/plain;charset=utf-8",
+            Format::Html => "text/html;charset=utf-8",
+            Format::Xml => "application/xml",
+            Format::Yaml => "application/yaml",
+        }
+    }
+
+    /// Media types accepted for the format, the first one is the canonical.
+    fn media_types(&self) -> &'static [&'static str] {
+        match self {
+            Format::Json => &["application/json"],
+            Format::Text => &["text/plain"],
+            Format::Html => &["text/html"],
+            Format::Xml => &["application/xml", "text/xml"],
+            Format::Yaml => &["application/yaml", "application/x-yaml", "text/yaml"],
+        }
+    }
+
+    fn matches(&self, media_range: &str) -> bool {
+        self.media_types().iter().any(|media_type| {
+            media_range == "*/*"
+                || media_range == *media_type
+                || media_range
+                    .strip_suffix("/*")
+                    .is_some_and(|prefix| media_type.split('/').next() == Some(prefix))
+        })
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "json" => Ok(Format::Json),
+            "text" | "txt" => Ok(Format::Text),
+            "html" => Ok(Format::Html),
+            "xml" => Ok(Format::Xml),
+            "yaml" | "yml" => Ok(Format::Yaml),
+            _ => Err(format!(
+                "Unsupported format {}, use json, text, html, xml or yaml",
+                format
+            )),
+        }
+    }
+}
+
+/// Format of the response, the `format` query parameter overrides the `Accept` header.
+///
+/// Fails with 400 for an unknown `format` and with 406 if nothing in `Accept` is supported.
+pub fn negotiate<State>(req: &Request<State>) -> tide::Result<Format> {
+    let query: FormatQuery = req.query()?;
+    if let Some(format) = query.format {
+        return format
+            .parse()
+            .map_err(|reason: String| tide::Error::from_str(400, reason));
+    }
+
+    let accept = match req.header(ACCEPT) {
+        Some(values) => values
+            .iter()
+            .map(|value| value.as_str())
+            .collect::<Vec<_>>()
+            .join(","),
+        None => return Ok(Format::Json),
+    };
+
+    from_accept(&accept).ok_or_else(|| {
+        tide::Error::from_str(
+            406,
+            "Supported types are application/json, text/plain, text/html, application/xml and application/yaml",
+        )
+    })
+}
+
+/// Preferred format of an `Accept` header, by quality and then by order of the header.
+fn from_accept(accept: &str) -> Option<Format> {
+    let mut ranges: Vec<(String, f32)> = accept
+        .split(',')
+        .filter_map(|range| {
+            let mut parts = range.split(';').map(str::trim);
+            let media_range = parts.next().filter(|media_range| !media_range.is_empty())?;
+            let quality = parts
+                .filter_map(|param| param.strip_prefix("q="))
+                .find_map(|quality| quality.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            Some((media_range.to_lowercase(), quality))
+        })
+        .collect();
+    // stable, so the ranges of the same quality keep the order of the header
+    ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    let rejected = |format: &Format| {
+        ranges.iter().any(|(media_range, quality)| {
+            *quality <= 0.0 && format.media_types().contains(&media_range.as_str())
+        })
+    };
+
+    ranges
+        .iter()
+        .filter(|(_, quality)| *quality > 0.0)
+        .find_map(|(media_range, _)| {
+            FORMATS
+                .iter()
+                .find(|format| format.matches(media_range) && !rejected(format))
+                .copied()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accept_header() {
+        assert_eq!(Some(Format::Json), from_accept("*/*"));
+        assert_eq!(
+            Some(Format::Html),
+            from_accept("text/html,application/xhtml+xml,*/*;q=0.8")
+        );
+        assert_eq!(Some(Format::Text), from_accept("text/*"));
+        assert_eq!(
+            Some(Format::Yaml),
+            from_accept("text/plain;q=0.5, application/x-yaml")
+        );
+        assert_eq!(Some(Format::Text), from_accept("application/json;q=0, */*"));
+        assert_eq!(Some(Format::Xml), from_accept("text/xml"));
+        assert_eq!(None, from_accept("application/pdf"));
+        assert_eq!(None, from_accept("text/html;q=0"));
+    }
+}
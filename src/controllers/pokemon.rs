@@ -1,46 +1,808 @@
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, UNIX_EPOCH};
+
+use askama::Template;
+use futures::stream::{self, StreamExt};
 use serde::{Deserialize, Serialize};
+use tide::http::cache::{CacheControl, CacheDirective};
+use tide::http::conditional::{ETag, IfModifiedSince, IfNoneMatch, LastModified};
+use tide::http::headers::VARY;
+use tide::sse::Sender;
 use tide::{Body, Request, Response};
+use utoipa::ToSchema;
 
+use super::errors::{to_tide_error, HttpError};
+use super::negotiation::{self, Format};
+use crate::cache::now;
+use crate::correlation::{self, Correlation};
+use crate::middlewares::extension_types::Quotas;
 use crate::server::State;
+use crate::store::{Record, Source};
 use crate::wrappers::errors::WrapperError;
+use crate::wrappers::pokemon::{normalize_name, Species, DESCRIPTION_LANGUAGE};
+
+/// Max number of pokemons allowed in a single batch request.
+pub const BATCH_MAX_SIZE: usize = 20;
+
+/// Freshness of the responses that don't expire.
+const DEFAULT_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Style of the translations.
+const TRANSLATION_STYLE: &str = "shakespeare";
+
+/// Max number of concurrent upstream calls while resolving a batch.
+const BATCH_CONCURRENCY: usize = 4;
+
+/// Max number of pokemons of a streamed batch, enough for a whole generation.
+pub const BATCH_STREAM_MAX_SIZE: usize = 200;
+
+/// Longest wait of a streamed batch for the quotas of the client, the pokemon fails after it.
+const BATCH_STREAM_MAX_WAIT: Duration = Duration::from_secs(60);
 
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, ToSchema)]
 pub struct PokemonResponse {
     name: String,
     description: String,
 }
 
+impl PokemonResponse {
+    /// Body of the response in the given format.
+    fn render(&self, format: Format) -> tide::Result<Vec<u8>> {
+        let body = match format {
+            Format::Json => serde_json::to_vec(self)?,
+            Format::Text => self.description.clone().into_bytes(),
+            Format::Html => CardTemplate {
+                name: &self.name,
+                description: &self.description,
+            }
+            .render()?
+            .into_bytes(),
+            Format::Xml => format!(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n{}",
+                quick_xml::se::to_string_with_root("pokemon", self)?
+            )
+            .into_bytes(),
+            Format::Yaml => serde_yaml::to_string(self)?.into_bytes(),
+        };
+        Ok(body)
+    }
+}
+
+/// Card of the html rendering, a page of its own unlike the ones of the ui.
+#[derive(Template)]
+#[template(path = "card.html")]
+struct CardTemplate<'a> {
+    name: &'a str,
+    description: &'a str,
+}
+
+/// Shape of `/v2/pokemon/:pokemon_name`.
+#[derive(Deserialize, Serialize, ToSchema)]
+pub struct PokemonResponseV2 {
+    /// Pokédex number.
+    id: u32,
+    name: String,
+    description: String,
+    /// Language of the description.
+    language: String,
+    /// False when the translation api is unavailable (e.g. over its quota), the description is
+    /// then the original one.
+    translated: bool,
+    /// Style of the translation, only if translated.
+    style: Option<String>,
+}
+
+impl PokemonResponseV2 {
+    /// Body of the response in the given format.
+    fn render(&self, format: Format) -> tide::Result<Vec<u8>> {
+        let body = match format {
+            Format::Json => serde_json::to_vec(self)?,
+            Format::Text => self.description.clone().into_bytes(),
+            Format::Html => CardTemplate {
+                name: &self.name,
+                description: &self.description,
+            }
+            .render()?
+            .into_bytes(),
+            Format::Xml => format!(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n{}",
+                quick_xml::se::to_string_with_root("pokemon", self)?
+            )
+            .into_bytes(),
+            Format::Yaml => serde_yaml::to_string(self)?.into_bytes(),
+        };
+        Ok(body)
+    }
+}
+
+#[derive(Deserialize, Serialize, ToSchema)]
+pub struct BatchRequest {
+    pub names: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
+pub struct BatchItem {
+    name: String,
+    status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, ToSchema)]
+pub struct BatchResponse {
+    results: Vec<BatchItem>,
+}
+
+#[derive(Deserialize)]
+struct BatchStreamQuery {
+    /// Comma separated names of the pokemons.
+    names: String,
+}
+
+/// Data of the events of a streamed batch, only the fields of the event are present.
+#[derive(Deserialize, Serialize, Debug, Default, ToSchema)]
+pub struct BatchProgress {
+    name: String,
+    /// Position of the pokemon in the batch, on `queued`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    position: Option<usize>,
+    /// Original description, on `description`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    /// Description in Shakespeare's style, on `translated`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    translation: Option<String>,
+    /// Seconds until the quotas of the client allow the next pokemon, on `waiting`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    retry_after: Option<u64>,
+    /// Status, error and code of the failure, on `failed`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<String>,
+}
+
+/// Data of the `done` event, the last one of a streamed batch.
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
+pub struct BatchSummary {
+    translated: usize,
+    failed: usize,
+}
+
+/// Description of a pokemon in Shakespeare's style.
+#[utoipa::path(
+    get,
+    path = "/pokemon/{pokemon_name}",
+    tag = "pokemon",
+    params(
+        ("pokemon_name" = String, Path, description = "Name of the pokemon"),
+        ("format" = Option<String>, Query, description = "json, text, html, xml or yaml, instead of the Accept header"),
+    ),
+    responses(
+        (status = 200, description = "Translated description", content(
+            ("application/json" = PokemonResponse),
+            ("text/plain" = String),
+            ("text/html" = String),
+            ("application/xml" = String),
+            ("application/yaml" = String),
+        )),
+        (status = 304, description = "Not modified since the given ETag or date"),
+        (status = 400, description = "Unknown format", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown pokemon or without description", body = Problem, content_type = "application/problem+json"),
+        (status = 406, description = "None of the accepted formats is available", body = Problem, content_type = "application/problem+json"),
+        (status = 429, description = "Upstream rate limit reached", body = Problem, content_type = "application/problem+json"),
+        (status = 502, description = "Upstream error", body = Problem, content_type = "application/problem+json"),
+        (status = 503, description = "Upstream unavailable", body = Problem, content_type = "application/problem+json"),
+        (status = 504, description = "Upstream timeout", body = Problem, content_type = "application/problem+json"),
+    ),
+    security((), ("api_key" = [])),
+)]
 pub async fn get(req: Request<State>) -> tide::Result {
-    let pokemon_name = req.param("pokemon_name")?;
+    let pokemon_name = &normalize_name(req.param("pokemon_name")?);
+    let format = negotiation::negotiate(&req)?;
 
-    let pokemon_wrapper = req.state().pokemon_wrapper.clone();
-    let description = pokemon_wrapper
-        .get_description(pokemon_name)
-        .await
-        .map_err(|e| match e {
-            WrapperError::NotFound => tide::Error::from_str(404, "Not Found".to_string()),
-            WrapperError::TooManyRequests => {
-                tide::Error::from_str(429, "Too Many Requests".to_string())
-            }
-            _ => tide::Error::from_str(500, "Unexpected Error".to_string()),
-        })?;
-    let shakespeare_wrapper = req.state().shakespeare_wrapper.clone();
-    let translated_description = shakespeare_wrapper
-        .get_translation(&description)
+    let state = req.state();
+    let record = state
+        .description(pokemon_name)
         .await
-        .map_err(|e| match e {
-            WrapperError::TooManyRequests => {
-                tide::Error::from_str(429, "Too Many Requests".to_string())
-            }
-            _ => tide::Error::from_str(500, "Unexpected Error".to_string()),
-        })?;
+        .map_err(to_tide_error)?;
+    state.store.record(
+        pokemon_name,
+        record.id,
+        &record.description,
+        &record.translation,
+        record.source,
+    );
 
+    let freshness = max_age(state, &record);
     let pokemon = PokemonResponse {
         name: pokemon_name.to_string(),
-        description: translated_description,
+        description: record.translation,
     };
+    let body = pokemon.render(format)?;
+
+    Ok(cacheable(&req, format, body, record.recorded_at, freshness))
+}
+
+/// Freshness of a translation: manual ones don't expire, machine ones are valid until they
+/// leave the cache.
+fn max_age(state: &State, record: &Record) -> u64 {
+    let age = now().saturating_sub(record.recorded_at);
+    match (record.source, state.cache.ttl()) {
+        (Source::Machine, Some(ttl)) => ttl.as_secs().saturating_sub(age),
+        _ => DEFAULT_MAX_AGE.as_secs(),
+    }
+}
+
+/// Response with the validators and the freshness of the body, or `304` if the client already
+/// has it.
+fn cacheable(
+    req: &Request<State>,
+    format: Format,
+    body: Vec<u8>,
+    modified_at: u64,
+    max_age: u64,
+) -> Response {
+    let etag = etag(&body);
+    let last_modified = LastModified::new(UNIX_EPOCH + Duration::from_secs(modified_at));
+    let mut cache_control = CacheControl::new();
+    cache_control.push(CacheDirective::Public);
+    cache_control.push(CacheDirective::MaxAge(Duration::from_secs(max_age)));
+
+    let mut res = if is_not_modified(req, &etag, &last_modified) {
+        Response::new(304)
+    } else {
+        let mut res = Response::new(200);
+        res.set_body(body);
+        res.set_content_type(format.content_type());
+        res
+    };
+    res.insert_header(VARY, "Accept");
+    etag.apply(&mut res);
+    last_modified.apply(&mut res);
+    cache_control.apply(&mut res);
+    res
+}
+
+/// Description of a pokemon with its pokédex number, the original one if it can't be translated.
+#[utoipa::path(
+    get,
+    path = "/v2/pokemon/{pokemon_name}",
+    tag = "pokemon",
+    params(
+        ("pokemon_name" = String, Path, description = "Name of the pokemon"),
+        ("format" = Option<String>, Query, description = "json, text, html, xml or yaml, instead of the Accept header"),
+    ),
+    responses(
+        (status = 200, description = "Description, translated if possible", content(
+            ("application/json" = PokemonResponseV2),
+            ("text/plain" = String),
+            ("text/html" = String),
+            ("application/xml" = String),
+            ("application/yaml" = String),
+        )),
+        (status = 304, description = "Not modified since the given ETag or date"),
+        (status = 400, description = "Unknown format", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown pokemon or without description", body = Problem, content_type = "application/problem+json"),
+        (status = 406, description = "None of the accepted formats is available", body = Problem, content_type = "application/problem+json"),
+        (status = 429, description = "Upstream rate limit reached", body = Problem, content_type = "application/problem+json"),
+        (status = 502, description = "Upstream error", body = Problem, content_type = "application/problem+json"),
+        (status = 503, description = "Upstream unavailable", body = Problem, content_type = "application/problem+json"),
+        (status = 504, description = "Upstream timeout", body = Problem, content_type = "application/problem+json"),
+    ),
+    security((), ("api_key" = [])),
+)]
+pub async fn get_v2(req: Request<State>) -> tide::Result {
+    let pokemon_name = &normalize_name(req.param("pokemon_name")?);
+    let format = negotiation::negotiate(&req)?;
+    let state = req.state();
+
+    // the pokédex number is only known by the pokemon api, it's kept along the translations so
+    // only the pokemons not translated yet are asked for
+    let manual = state.overrides.get(pokemon_name);
+    let cached = state.cache.get(pokemon_name);
+    let known_id = manual
+        .as_ref()
+        .and_then(|record| record.id)
+        .or_else(|| cached.as_ref().and_then(|entry| entry.id));
+    let known = manual.or_else(|| cached.map(|entry| entry.into_record(pokemon_name)));
+
+    let (id, translated) = match (known_id, known) {
+        (Some(id), Some(record)) => (id, Ok(record)),
+        (_, known) => {
+            let species = state
+                .pokemon_wrapper
+                .get_species(pokemon_name)
+                .await
+                .map_err(to_tide_error)?;
+            let translated = match known {
+                Some(record) => Ok(record),
+                None => match state
+                    .shakespeare_wrapper
+                    .get_translation(&species.description)
+                    .await
+                {
+                    Ok(translation) => Ok(state
+                        .cache
+                        .insert_species(pokemon_name, &species, &translation)
+                        .into_record(pokemon_name)),
+                    Err(e) => {
+                        tide::log::warn!("Serving {} untranslated: {}", pokemon_name, e);
+                        Err(species.description)
+                    }
+                },
+            };
+            (species.id, translated)
+        }
+    };
+
+    match translated {
+        Ok(record) => {
+            state.store.record(
+                pokemon_name,
+                Some(id),
+                &record.description,
+                &record.translation,
+                record.source,
+            );
+            let pokemon = PokemonResponseV2 {
+                id,
+                name: pokemon_name.to_string(),
+                description: record.translation.clone(),
+                language: DESCRIPTION_LANGUAGE.to_string(),
+                translated: true,
+                style: Some(TRANSLATION_STYLE.to_string()),
+            };
+            let body = pokemon.render(format)?;
+            Ok(cacheable(
+                &req,
+                format,
+                body,
+                record.recorded_at,
+                max_age(state, &record),
+            ))
+        }
+        // the translation can be available on the next request, it's not kept
+        Err(description) => {
+            let pokemon = PokemonResponseV2 {
+                id,
+                name: pokemon_name.to_string(),
+                description,
+                language: DESCRIPTION_LANGUAGE.to_string(),
+                translated: false,
+                style: None,
+            };
+            let body = pokemon.render(format)?;
+            Ok(cacheable(&req, format, body, now(), 0))
+        }
+    }
+}
+
+/// Strong ETag of a response body, its 64 bits FNV-1a hash.
+fn etag(body: &[u8]) -> ETag {
+    ETag::new(format!("{:016x}", fnv1a(body)))
+}
+
+/// FNV-1a hash, the same in every build and instance unlike the `std` hashers.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+    bytes.iter().fold(OFFSET_BASIS, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+    })
+}
+
+/// Check the conditional headers of the request, `If-Modified-Since` is only used without
+/// `If-None-Match`. Invalid headers are ignored.
+fn is_not_modified(req: &Request<State>, etag: &ETag, last_modified: &LastModified) -> bool {
+    if let Ok(Some(if_none_match)) = IfNoneMatch::from_headers(req) {
+        // weak comparison, as required for If-None-Match
+        let tag = |etag: &ETag| match etag {
+            ETag::Strong(tag) | ETag::Weak(tag) => tag.clone(),
+        };
+        return if_none_match.wildcard()
+            || if_none_match.iter().any(|other| tag(other) == tag(etag));
+    }
+
+    match IfModifiedSince::from_headers(req) {
+        Ok(Some(if_modified_since)) => last_modified.modified() <= if_modified_since.modified(),
+        _ => false,
+    }
+}
+
+/// Descriptions of several pokemons, each item with its own status.
+#[utoipa::path(
+    post,
+    path = "/pokemon/batch",
+    tag = "pokemon",
+    request_body = BatchRequest,
+    responses(
+        (status = 200, description = "Result of each pokemon, in the same order", body = BatchResponse),
+        (status = 400, description = "Invalid or too big batch", body = Problem, content_type = "application/problem+json"),
+    ),
+    security((), ("api_key" = [])),
+)]
+pub async fn batch(mut req: Request<State>) -> tide::Result {
+    let batch: BatchRequest = req
+        .body_json()
+        .await
+        .map_err(|_| tide::Error::from_str(400, "Invalid batch request".to_string()))?;
+
+    if batch.names.is_empty() || batch.names.len() > BATCH_MAX_SIZE {
+        return Err(tide::Error::from_str(
+            400,
+            format!(
+                "A batch must contain between 1 and {} pokemons",
+                BATCH_MAX_SIZE
+            ),
+        ));
+    }
+
+    let state = req.state();
+
+    // The same pokemon can be requested more than once (e.g. a team with repeated members),
+    // we only ask the upstream apis once for each distinct name without a manual translation
+    // and not in the cache.
+    let names: Vec<String> = batch
+        .names
+        .iter()
+        .map(|name| normalize_name(name))
+        .collect();
+    let unique_names: HashSet<String> = names
+        .iter()
+        .filter(|name| !name.is_empty())
+        .cloned()
+        .collect();
+    let cached: HashMap<String, String> = unique_names
+        .iter()
+        .filter_map(|name| {
+            let translation = match state.overrides.get(name) {
+                Some(record) => record.translation,
+                None => state.cache.peek(name)?.translation,
+            };
+            Some((name.clone(), translation))
+        })
+        .collect();
+
+    // Each name reaching the upstream apis is charged to the quotas of the client, in the order
+    // of the batch, the ones refused fail without being looked up.
+    let quotas = req.ext::<Quotas>().cloned().unwrap_or_default();
+    let mut refused = HashSet::new();
+    let mut fetched = Vec::new();
+    for name in &names {
+        let looked_up = refused.contains(name) || fetched.contains(name);
+        if name.is_empty() || cached.contains_key(name) || looked_up {
+            continue;
+        }
+        match quotas.spend(1) {
+            Ok(()) => fetched.push(name.clone()),
+            Err(_) => {
+                refused.insert(name.clone());
+            }
+        }
+    }
+
+    let pokemon_wrapper = state.pokemon_wrapper.clone();
+    let species: HashMap<String, Result<Species, WrapperError>> = stream::iter(fetched)
+        .map(|name| {
+            let pokemon_wrapper = pokemon_wrapper.clone();
+            async move {
+                let species = pokemon_wrapper.get_species(&name).await;
+                (name, species)
+            }
+        })
+        .buffer_unordered(BATCH_CONCURRENCY)
+        .collect()
+        .await;
+
+    // Different pokemons can share the same description, translate each one only once.
+    let unique_descriptions: HashSet<String> = species
+        .values()
+        .filter_map(|species| {
+            species
+                .as_ref()
+                .ok()
+                .map(|species| species.description.clone())
+        })
+        .collect();
+
+    let shakespeare_wrapper = state.shakespeare_wrapper.clone();
+    let translations: HashMap<String, Result<String, WrapperError>> =
+        stream::iter(unique_descriptions)
+            .map(|description| {
+                let shakespeare_wrapper = shakespeare_wrapper.clone();
+                async move {
+                    let translation = shakespeare_wrapper.get_translation(&description).await;
+                    (description, translation)
+                }
+            })
+            .buffer_unordered(BATCH_CONCURRENCY)
+            .collect()
+            .await;
+
+    for (name, species) in &species {
+        if let Ok(species) = species {
+            if let Ok(translation) = &translations[&species.description] {
+                state.cache.insert_species(name, species, translation);
+            }
+        }
+    }
+
+    let results = names
+        .iter()
+        .map(|name| {
+            // a blank name would look up the list of species
+            if name.is_empty() {
+                return BatchItem {
+                    name: name.to_string(),
+                    status: 400,
+                    description: None,
+                    error: Some("A pokemon name can't be blank".to_string()),
+                    code: Some("invalid_name".to_string()),
+                };
+            }
+            if refused.contains(name) {
+                return BatchItem {
+                    name: name.to_string(),
+                    status: 429,
+                    description: None,
+                    error: Some("Too Many Requests".to_string()),
+                    code: Some("rate_limited".to_string()),
+                };
+            }
+
+            let translation = match cached.get(name) {
+                Some(translation) => Ok(translation),
+                None => species[name]
+                    .as_ref()
+                    .and_then(|species| translations[&species.description].as_ref()),
+            };
+
+            match translation {
+                Ok(description) => BatchItem {
+                    name: name.to_string(),
+                    status: 200,
+                    description: Some(description.to_string()),
+                    error: None,
+                    code: None,
+                },
+                Err(e) => BatchItem {
+                    name: name.to_string(),
+                    status: e.status() as u16,
+                    description: None,
+                    error: Some(e.explanation().to_string()),
+                    code: Some(e.code().to_string()),
+                },
+            }
+        })
+        .collect();
 
     let mut res = Response::new(200);
-    res.set_body(Body::from_json(&pokemon)?);
+    res.set_body(Body::from_json(&BatchResponse { results })?);
     Ok(res)
 }
+
+/// Translate several pokemons one after the other, streaming the progress as server-sent events.
+///
+/// Every pokemon is first `queued`, then gets its `description` and is `translated`, or
+/// `failed` with the code of the error. A `done` event with the totals ends the stream.
+///
+/// Each pokemon not cached yet is charged to the rate limit and the API key quota like a
+/// request, the stream sends a `waiting` event and pauses while they are exhausted.
+#[utoipa::path(
+    get,
+    path = "/pokemon/batch/stream",
+    tag = "pokemon",
+    params(("names" = String, Query, description = "Comma separated names of the pokemons")),
+    responses(
+        (status = 200, description = "Events of the batch, each one with a `BatchProgress` or, at the end, a `BatchSummary` as data", content_type = "text/event-stream", body = String),
+        (status = 400, description = "Invalid or too big batch", body = Problem, content_type = "application/problem+json"),
+    ),
+    security((), ("api_key" = [])),
+)]
+pub async fn batch_stream(req: Request<State>) -> tide::Result {
+    let query: BatchStreamQuery = req
+        .query()
+        .map_err(|_| tide::Error::from_str(400, "Missing names".to_string()))?;
+    let mut unique = HashSet::new();
+    let names: Vec<String> = query
+        .names
+        .split(',')
+        .map(normalize_name)
+        .filter(|name| !name.is_empty() && unique.insert(name.clone()))
+        .collect();
+
+    if names.is_empty() || names.len() > BATCH_STREAM_MAX_SIZE {
+        return Err(tide::Error::from_str(
+            400,
+            format!(
+                "A batch must contain between 1 and {} pokemons",
+                BATCH_STREAM_MAX_SIZE
+            ),
+        ));
+    }
+
+    // the stream runs in its own task, out of the scope of the request
+    let correlation = Correlation::current();
+    Ok(tide::sse::upgrade(req, move |req, sender| {
+        correlation::scoped(
+            correlation.clone(),
+            stream_batch(req, names.clone(), sender),
+        )
+    }))
+}
+
+/// Send an event of the batch, the ids are the position of the event in the stream.
+async fn send_event(
+    sender: &Sender,
+    event: &str,
+    id: &mut usize,
+    data: &impl Serialize,
+) -> tide::Result<()> {
+    sender
+        .send(event, serde_json::to_string(data)?, Some(&id.to_string()))
+        .await?;
+    *id += 1;
+    Ok(())
+}
+
+/// Spend a call of the client quotas, waiting for them with `waiting` events if they are
+/// available soon enough. Returns whether the call is allowed.
+async fn wait_for_quotas(
+    quotas: &Quotas,
+    name: &str,
+    sender: &Sender,
+    id: &mut usize,
+) -> tide::Result<bool> {
+    loop {
+        match quotas.spend(1) {
+            Ok(()) => return Ok(true),
+            Err(retry_after) if retry_after <= BATCH_STREAM_MAX_WAIT => {
+                let progress = BatchProgress {
+                    name: name.to_string(),
+                    retry_after: Some(retry_after.as_secs()),
+                    ..Default::default()
+                };
+                send_event(sender, "waiting", id, &progress).await?;
+                async_std::task::sleep(retry_after).await;
+            }
+            Err(_) => return Ok(false),
+        }
+    }
+}
+
+/// Resolve the pokemons in order, to stay within the quota of the translation api. Stops as soon
+/// as the client goes away.
+async fn stream_batch(req: Request<State>, names: Vec<String>, sender: Sender) -> tide::Result<()> {
+    let state = req.state();
+    let quotas = req.ext::<Quotas>().cloned().unwrap_or_default();
+    let mut id = 0;
+
+    for (position, name) in names.iter().enumerate() {
+        let queued = BatchProgress {
+            name: name.clone(),
+            position: Some(position),
+            ..Default::default()
+        };
+        send_event(&sender, "queued", &mut id, &queued).await?;
+    }
+
+    let mut summary = BatchSummary {
+        translated: 0,
+        failed: 0,
+    };
+    for name in &names {
+        let known = match state.overrides.get(name) {
+            Some(record) => Some((
+                record.id,
+                record.description,
+                record.translation,
+                record.source,
+            )),
+            None => state.cache.get(name).map(|entry| {
+                (
+                    entry.id,
+                    entry.description,
+                    entry.translation,
+                    Source::Machine,
+                )
+            }),
+        };
+
+        if known.is_none() && !wait_for_quotas(&quotas, name, &sender, &mut id).await? {
+            summary.failed += 1;
+            let progress = BatchProgress {
+                name: name.clone(),
+                status: Some(429),
+                error: Some("Too Many Requests".to_string()),
+                code: Some("rate_limited".to_string()),
+                ..Default::default()
+            };
+            send_event(&sender, "failed", &mut id, &progress).await?;
+            continue;
+        }
+
+        let translated = match known {
+            Some((pokedex_id, description, translation, source)) => {
+                let progress = BatchProgress {
+                    name: name.clone(),
+                    description: Some(description.clone()),
+                    ..Default::default()
+                };
+                send_event(&sender, "description", &mut id, &progress).await?;
+                Ok((pokedex_id, description, translation, source))
+            }
+            None => match state.pokemon_wrapper.get_species(name).await {
+                Ok(species) => {
+                    let progress = BatchProgress {
+                        name: name.clone(),
+                        description: Some(species.description.clone()),
+                        ..Default::default()
+                    };
+                    send_event(&sender, "description", &mut id, &progress).await?;
+                    state
+                        .shakespeare_wrapper
+                        .get_translation(&species.description)
+                        .await
+                        .map(|translation| {
+                            state.cache.insert_species(name, &species, &translation);
+                            (
+                                Some(species.id),
+                                species.description,
+                                translation,
+                                Source::Machine,
+                            )
+                        })
+                }
+                Err(e) => Err(e),
+            },
+        };
+
+        match translated {
+            Ok((pokedex_id, description, translation, source)) => {
+                state
+                    .store
+                    .record(name, pokedex_id, &description, &translation, source);
+                summary.translated += 1;
+                let progress = BatchProgress {
+                    name: name.clone(),
+                    translation: Some(translation),
+                    ..Default::default()
+                };
+                send_event(&sender, "translated", &mut id, &progress).await?;
+            }
+            Err(e) => {
+                summary.failed += 1;
+                let progress = BatchProgress {
+                    name: name.clone(),
+                    status: Some(e.status() as u16),
+                    error: Some(e.explanation().to_string()),
+                    code: Some(e.code().to_string()),
+                    ..Default::default()
+                };
+                send_event(&sender, "failed", &mut id, &progress).await?;
+            }
+        }
+    }
+
+    send_event(&sender, "done", &mut id, &summary).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stable_etags() {
+        assert_eq!(0xcbf2_9ce4_8422_2325, fnv1a(b""));
+        assert_eq!(0xaf63_dc4c_8601_ec8c, fnv1a(b"a"));
+        assert_eq!(0x8594_4171_f739_67e8, fnv1a(b"foobar"));
+        assert_eq!(ETag::new("af63dc4c8601ec8c".to_string()), etag(b"a"));
+    }
+}
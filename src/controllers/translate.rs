@@ -0,0 +1,121 @@
+use serde::{Deserialize, Serialize};
+use tide::{Body, Request, Response};
+use utoipa::ToSchema;
+
+use super::errors::to_tide_error;
+use crate::middlewares::extension_types::RequestId;
+use crate::server::State;
+
+/// Max number of characters allowed in a text to translate.
+pub const TRANSLATE_MAX_LENGTH: usize = 1000;
+
+pub(crate) const DEFAULT_STYLE: &str = "shakespeare";
+
+#[derive(Deserialize, Serialize, ToSchema)]
+pub struct TranslateRequest {
+    pub text: String,
+    pub style: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, ToSchema)]
+pub struct TranslateResponse {
+    translated: String,
+    style: String,
+    #[schema(value_type = Option<String>)]
+    request_id: Option<RequestId>,
+}
+
+/// Check the text is something the translation api can handle, returning the reason otherwise.
+pub(crate) fn validate_text(text: &str) -> Result<(), String> {
+    if text.trim().is_empty() {
+        return Err("Text must not be empty".to_string());
+    }
+
+    if text.chars().count() > TRANSLATE_MAX_LENGTH {
+        return Err(format!(
+            "Text must be at most {} characters long",
+            TRANSLATE_MAX_LENGTH
+        ));
+    }
+
+    // new lines and tabs are fine, any other control character is most likely an encoding issue.
+    if text
+        .chars()
+        .any(|c| c.is_control() && c != '\n' && c != '\t' && c != '\r')
+    {
+        return Err("Text must not contain control characters".to_string());
+    }
+
+    Ok(())
+}
+
+/// Translate any text in Shakespeare's style.
+#[utoipa::path(
+    post,
+    path = "/translate",
+    tag = "translate",
+    request_body = TranslateRequest,
+    responses(
+        (status = 200, description = "Translated text", body = TranslateResponse),
+        (status = 400, description = "Invalid text or unsupported style", body = Problem, content_type = "application/problem+json"),
+        (status = 429, description = "Upstream rate limit reached", body = Problem, content_type = "application/problem+json"),
+        (status = 502, description = "Upstream error", body = Problem, content_type = "application/problem+json"),
+        (status = 503, description = "Upstream unavailable", body = Problem, content_type = "application/problem+json"),
+        (status = 504, description = "Upstream timeout", body = Problem, content_type = "application/problem+json"),
+    ),
+    security((), ("api_key" = [])),
+)]
+pub async fn post(mut req: Request<State>) -> tide::Result {
+    let input: TranslateRequest = req
+        .body_json()
+        .await
+        .map_err(|_| tide::Error::from_str(400, "Invalid translate request".to_string()))?;
+
+    validate_text(&input.text).map_err(|reason| tide::Error::from_str(400, reason))?;
+
+    let style = input.style.unwrap_or_else(|| DEFAULT_STYLE.to_string());
+    if style != DEFAULT_STYLE {
+        return Err(tide::Error::from_str(
+            400,
+            format!("Unsupported style: {}", style),
+        ));
+    }
+
+    let shakespeare_wrapper = req.state().shakespeare_wrapper.clone();
+    let translated = shakespeare_wrapper
+        .get_translation(&input.text)
+        .await
+        .map_err(to_tide_error)?;
+
+    let translation = TranslateResponse {
+        translated,
+        style,
+        request_id: req.ext::<RequestId>().cloned(),
+    };
+
+    let mut res = Response::new(200);
+    res.set_body(Body::from_json(&translation)?);
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_empty_text() {
+        assert!(validate_text("   ").is_err());
+    }
+
+    #[test]
+    fn validate_too_long_text() {
+        let text = "a".repeat(TRANSLATE_MAX_LENGTH + 1);
+        assert!(validate_text(&text).is_err());
+    }
+
+    #[test]
+    fn validate_control_characters() {
+        assert!(validate_text("Hello\u{0}world").is_err());
+        assert!(validate_text("Hello\nworld").is_ok());
+    }
+}
@@ -0,0 +1,202 @@
+//! Server rendered web ui.
+//!
+//! The templates are compiled into the binary and the stylesheet is embedded, so the ui doesn't
+//! need any file next to it at runtime.
+
+use std::cmp::Reverse;
+use std::time::Duration;
+
+use askama::Template;
+use tide::http::cache::{CacheControl, CacheDirective};
+use tide::http::mime;
+use tide::{Redirect, Request, Response, StatusCode};
+
+use super::errors::HttpError;
+use crate::server::State;
+use crate::store::Source;
+use crate::wrappers::errors::WrapperError;
+use crate::wrappers::pokemon::normalize_name;
+
+/// Stylesheet of the ui, served at `/static/style.css`.
+const STYLE: &str = include_str!("../../static/style.css");
+
+/// Freshness of the stylesheet.
+const STYLE_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Number of recent translations listed in the index.
+const RECENT_MAX: usize = 12;
+
+/// Max number of results of a search.
+const SEARCH_MAX_RESULTS: usize = 50;
+
+#[derive(Template)]
+#[template(path = "index.html")]
+struct IndexTemplate {
+    query: String,
+    recent: Vec<String>,
+}
+
+#[derive(Template)]
+#[template(path = "search.html")]
+struct SearchTemplate {
+    query: String,
+    names: Vec<String>,
+}
+
+#[derive(Template)]
+#[template(path = "pokemon.html")]
+struct PokemonTemplate {
+    query: String,
+    name: String,
+    sprite: Option<String>,
+    description: String,
+    translation: String,
+    manual: bool,
+}
+
+#[derive(Template)]
+#[template(path = "error.html")]
+struct ErrorTemplate {
+    query: String,
+    status: u16,
+    message: String,
+}
+
+#[derive(serde::Deserialize)]
+struct SearchQuery {
+    #[serde(default)]
+    q: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/",
+    tag = "ui",
+    responses((status = 200, description = "Search page", content_type = "text/html")),
+)]
+pub async fn index(req: Request<State>) -> tide::Result {
+    let mut records = req.state().store.records();
+    records.sort_by_key(|record| Reverse(record.recorded_at));
+    let recent = records
+        .into_iter()
+        .take(RECENT_MAX)
+        .map(|record| record.name)
+        .collect();
+
+    html(
+        StatusCode::Ok,
+        &IndexTemplate {
+            query: String::new(),
+            recent,
+        },
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/search",
+    tag = "ui",
+    params(("q" = String, Query, description = "Part of the pokemon name")),
+    responses(
+        (status = 200, description = "Pokemons matching the search", content_type = "text/html"),
+        (status = 302, description = "Back to the search page when the query is empty"),
+    ),
+)]
+pub async fn search(req: Request<State>) -> tide::Result {
+    let SearchQuery { q } = req.query()?;
+    let query = normalize_name(&q);
+    if query.is_empty() {
+        return Ok(Redirect::new("/").into());
+    }
+
+    let species_names = match req.state().species_names().await {
+        Ok(species_names) => species_names,
+        Err(e) => return error_page(query, e),
+    };
+    let names = species_names
+        .iter()
+        .filter(|name| name.contains(&query))
+        .take(SEARCH_MAX_RESULTS)
+        .cloned()
+        .collect();
+
+    html(StatusCode::Ok, &SearchTemplate { query, names })
+}
+
+#[utoipa::path(
+    get,
+    path = "/pokedex/{pokemon_name}",
+    tag = "ui",
+    params(("pokemon_name" = String, Path, description = "Name of the pokemon")),
+    responses(
+        (status = 200, description = "Sprite, original and translated descriptions", content_type = "text/html"),
+        (status = 404, description = "Unknown pokemon or without description", content_type = "text/html"),
+    ),
+)]
+pub async fn pokemon(req: Request<State>) -> tide::Result {
+    let pokemon_name = normalize_name(req.param("pokemon_name")?);
+    let state = req.state();
+
+    let (record, sprite) = futures::join!(
+        state.description(&pokemon_name),
+        state.sprite(&pokemon_name)
+    );
+    let record = match record {
+        Ok(record) => record,
+        Err(e) => return error_page(String::new(), e),
+    };
+    // the page is still useful without the picture
+    let sprite = sprite.unwrap_or_else(|e| {
+        tide::log::warn!("Can't get the sprite of {}: {}", pokemon_name, e);
+        None
+    });
+
+    html(
+        StatusCode::Ok,
+        &PokemonTemplate {
+            query: String::new(),
+            name: pokemon_name,
+            sprite,
+            description: record.description,
+            translation: record.translation,
+            manual: record.source == Source::Manual,
+        },
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/static/style.css",
+    tag = "ui",
+    responses((status = 200, description = "Stylesheet of the ui", content_type = "text/css")),
+)]
+pub async fn style(_req: Request<State>) -> tide::Result {
+    let mut cache_control = CacheControl::new();
+    cache_control.push(CacheDirective::Public);
+    cache_control.push(CacheDirective::MaxAge(STYLE_MAX_AGE));
+
+    let mut res = Response::new(StatusCode::Ok);
+    res.set_body(STYLE);
+    res.set_content_type(mime::CSS);
+    cache_control.apply(&mut res);
+    Ok(res)
+}
+
+/// Render the upstream error as a page, with the same status as the api.
+fn error_page(query: String, e: WrapperError) -> tide::Result {
+    html(
+        e.status(),
+        &ErrorTemplate {
+            query,
+            status: e.status() as u16,
+            message: e.explanation().to_string(),
+        },
+    )
+}
+
+fn html(status: StatusCode, template: &impl Template) -> tide::Result {
+    let mut res = Response::new(status);
+    res.set_body(template.render()?);
+    res.set_content_type(mime::HTML);
+    Ok(res)
+}
@@ -0,0 +1,332 @@
+//! WebSocket sessions, for clients that want a persistent connection instead of a request per
+//! lookup.
+//!
+//! Every message is a JSON object with a `type` and an optional `request_id`, echoed in the
+//! replies so they can be matched with their request, as they are sent as soon as they
+//! complete, not in order.
+
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::StreamExt;
+use kv_log_macro::{info, warn};
+use serde::{Deserialize, Serialize};
+use tide::Request;
+use tide_websockets::{Message, WebSocketConnection};
+
+use super::errors::HttpError;
+use super::translate::{validate_text, DEFAULT_STYLE};
+use crate::cache::now;
+use crate::config::RequestIdPolicy;
+use crate::correlation::{local_trace, Correlation};
+use crate::middlewares::extension_types::{Quotas, RequestId};
+use crate::middlewares::rate_limit::Bucket;
+use crate::server::State;
+use crate::wrappers::errors::WrapperError;
+use crate::wrappers::pokemon::normalize_name;
+
+const DAY_SECS: u64 = 24 * 60 * 60;
+
+/// Max number of messages of a connection answered at the same time.
+const MAX_IN_FLIGHT: usize = 8;
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientMessage {
+    /// Description of a pokemon in Shakespeare's style.
+    Lookup { name: String },
+    /// Translation of any text.
+    Translate { text: String, style: Option<String> },
+    /// Pokemon of the day, now and then every day at midnight (UTC).
+    SubscribeDaily,
+}
+
+#[derive(Deserialize, Debug)]
+struct ClientEnvelope {
+    request_id: Option<String>,
+    #[serde(flatten)]
+    message: ClientMessage,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ServerMessage {
+    Pokemon {
+        name: String,
+        description: String,
+    },
+    Translation {
+        translated: String,
+        style: String,
+    },
+    Subscribed,
+    Daily {
+        name: String,
+        description: String,
+    },
+    Error {
+        status: u16,
+        code: String,
+        error: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        retry_after: Option<u64>,
+    },
+}
+
+impl ServerMessage {
+    fn error(status: u16, code: &str, error: &str) -> Self {
+        ServerMessage::Error {
+            status,
+            code: code.to_string(),
+            error: error.to_string(),
+            retry_after: None,
+        }
+    }
+
+    fn rate_limited(retry_after: u64) -> Self {
+        ServerMessage::Error {
+            status: 429,
+            code: "rate_limited".to_string(),
+            error: "Too Many Requests".to_string(),
+            retry_after: Some(retry_after),
+        }
+    }
+}
+
+impl From<WrapperError> for ServerMessage {
+    fn from(e: WrapperError) -> Self {
+        ServerMessage::error(e.status() as u16, e.code(), e.explanation())
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct ServerEnvelope {
+    request_id: RequestId,
+    #[serde(flatten)]
+    message: ServerMessage,
+}
+
+/// Id of a message, the one given by the client if the policy accepts it.
+fn request_id(given: Option<&str>, policy: RequestIdPolicy) -> RequestId {
+    match given.map(|id| RequestId::parse(id, policy)) {
+        Some(Ok(request_id)) => request_id,
+        Some(Err(e)) => {
+            log::warn!("Invalid request_id: \"{:?}\" - Error: {}", given, e);
+            RequestId::new()
+        }
+        None => RequestId::new(),
+    }
+}
+
+async fn reply(
+    conn: &WebSocketConnection,
+    request_id: RequestId,
+    message: ServerMessage,
+) -> tide::Result<()> {
+    conn.send_json(&ServerEnvelope {
+        request_id,
+        message,
+    })
+    .await
+}
+
+/// WebSocket session, each message is answered in its own task.
+///
+/// Clients send `lookup` (`name`), `translate` (`text`, `style`) or `subscribe_daily` messages,
+/// each with an optional `request_id`. The replies, `pokemon`, `translation`, `subscribed`,
+/// `daily` or `error` (`status`, `code`, `error`), carry the `request_id` of their message.
+///
+/// The messages of a connection share a token bucket with the settings of the http rate limit,
+/// even if it's disabled, and at most `MAX_IN_FLIGHT` of them are answered at the same time.
+/// Each lookup and translation is also charged to the rate limit and the API key quota of the
+/// client, like a request.
+#[utoipa::path(
+    get,
+    path = "/ws",
+    tag = "pokemon",
+    responses(
+        (status = 101, description = "Switched to the WebSocket protocol"),
+        (status = 426, description = "Not a WebSocket handshake"),
+    ),
+    security((), ("api_key" = [])),
+)]
+pub async fn session(req: Request<State>, conn: WebSocketConnection) -> tide::Result<()> {
+    let state = req.state().clone();
+    let quotas = req.ext::<Quotas>().cloned().unwrap_or_default();
+    let rate_limit = state.config.rate_limit.clone();
+    let mut bucket = Bucket::new(rate_limit.capacity);
+    let in_flight = Arc::new(AtomicUsize::new(0));
+    let mut daily: Option<RequestId> = None;
+    let mut messages = conn.clone();
+
+    loop {
+        // wait for the next message, or for the next pokemon of the day if subscribed
+        let next = match &daily {
+            Some(request_id) => {
+                let until_midnight = DAY_SECS - now() % DAY_SECS;
+                match async_std::future::timeout(
+                    Duration::from_secs(until_midnight),
+                    messages.next(),
+                )
+                .await
+                {
+                    Ok(next) => next,
+                    Err(_) => {
+                        let answer = pokemon_of_the_day(state.clone());
+                        spawn_reply(&conn, request_id.clone(), &in_flight, answer);
+                        continue;
+                    }
+                }
+            }
+            None => messages.next().await,
+        };
+
+        let text = match next {
+            Some(Ok(Message::Text(text))) => text,
+            Some(Ok(Message::Close(_))) | None => break,
+            Some(Ok(_)) => continue,
+            Some(Err(e)) => return Err(e.into()),
+        };
+
+        let envelope: ClientEnvelope = match serde_json::from_str(&text) {
+            Ok(envelope) => envelope,
+            Err(e) => {
+                let message = ServerMessage::error(400, "invalid_message", &e.to_string());
+                reply(&conn, RequestId::new(), message).await?;
+                continue;
+            }
+        };
+        let request_id = request_id(
+            envelope.request_id.as_deref(),
+            state.config.request_id.policy,
+        );
+        info!("WebSocket message", {
+            message: format!("{:?}", envelope.message),
+            request_id: request_id,
+        });
+
+        let decision = bucket.take(1, rate_limit.capacity, rate_limit.refill_per_sec);
+        if !decision.allowed {
+            warn!("Rate limit exceeded", { path: "/ws", request_id: request_id });
+            let message = ServerMessage::rate_limited(decision.retry_after);
+            reply(&conn, request_id, message).await?;
+            continue;
+        }
+
+        // answered right away, only the valid translations are charged
+        if let ClientMessage::Translate { text, style } = &envelope.message {
+            if let Err(message) = validate_translation(text, style.as_deref()) {
+                reply(&conn, request_id, message).await?;
+                continue;
+            }
+        }
+
+        // the pokemon of the day is shared by every client, only the others reach the upstreams
+        let charged = !matches!(envelope.message, ClientMessage::SubscribeDaily);
+        if charged && in_flight.load(Ordering::SeqCst) >= MAX_IN_FLIGHT {
+            let reason = format!("At most {} messages can be in flight", MAX_IN_FLIGHT);
+            let message = ServerMessage::error(429, "too_many_in_flight", &reason);
+            reply(&conn, request_id, message).await?;
+            continue;
+        }
+        let spent = if charged { quotas.spend(1) } else { Ok(()) };
+        if let Err(retry_after) = spent {
+            warn!("Quota exhausted", { path: "/ws", request_id: request_id });
+            let message = ServerMessage::rate_limited(retry_after.as_secs());
+            reply(&conn, request_id, message).await?;
+            continue;
+        }
+
+        match envelope.message {
+            ClientMessage::SubscribeDaily => {
+                reply(&conn, request_id.clone(), ServerMessage::Subscribed).await?;
+                let answer = pokemon_of_the_day(state.clone());
+                spawn_reply(&conn, request_id.clone(), &in_flight, answer);
+                daily = Some(request_id);
+            }
+            ClientMessage::Lookup { name } => {
+                let answer = lookup(state.clone(), normalize_name(&name));
+                spawn_reply(&conn, request_id, &in_flight, answer);
+            }
+            ClientMessage::Translate { text, style } => {
+                let answer = translate(state.clone(), text, style);
+                spawn_reply(&conn, request_id, &in_flight, answer);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Send the reply once it's ready, without blocking the next messages, counting it in flight
+/// until then. The upstream calls of each message carry its id, in a new trace.
+fn spawn_reply<F>(
+    conn: &WebSocketConnection,
+    request_id: RequestId,
+    in_flight: &Arc<AtomicUsize>,
+    answer: F,
+) where
+    F: Future<Output = ServerMessage> + Send + 'static,
+{
+    let conn = conn.clone();
+    let in_flight = in_flight.clone();
+    in_flight.fetch_add(1, Ordering::SeqCst);
+    let answer = Correlation::new(request_id.clone(), local_trace(None)).scope(answer);
+    async_std::task::spawn(async move {
+        if let Err(e) = reply(&conn, request_id, answer.await).await {
+            log::warn!("WebSocket reply not sent: {}", e);
+        }
+        in_flight.fetch_sub(1, Ordering::SeqCst);
+    });
+}
+
+async fn lookup(state: State, name: String) -> ServerMessage {
+    match state.description(&name).await {
+        Ok(record) => {
+            state.store.record(
+                &name,
+                record.id,
+                &record.description,
+                &record.translation,
+                record.source,
+            );
+            ServerMessage::Pokemon {
+                name,
+                description: record.translation,
+            }
+        }
+        Err(e) => e.into(),
+    }
+}
+
+/// Check the text and style of a translation, before it reaches the upstream.
+fn validate_translation(text: &str, style: Option<&str>) -> Result<(), ServerMessage> {
+    if let Err(reason) = validate_text(text) {
+        return Err(ServerMessage::error(400, "invalid_text", &reason));
+    }
+    match style {
+        Some(style) if style != DEFAULT_STYLE => {
+            let reason = format!("Unsupported style: {}", style);
+            Err(ServerMessage::error(400, "unsupported_style", &reason))
+        }
+        _ => Ok(()),
+    }
+}
+
+async fn translate(state: State, text: String, style: Option<String>) -> ServerMessage {
+    let style = style.unwrap_or_else(|| DEFAULT_STYLE.to_string());
+    match state.shakespeare_wrapper.get_translation(&text).await {
+        Ok(translated) => ServerMessage::Translation { translated, style },
+        Err(e) => e.into(),
+    }
+}
+
+/// Pokemon of the current day, shared by every client.
+async fn pokemon_of_the_day(state: State) -> ServerMessage {
+    match state.pokemon_of_the_day().await {
+        Ok((name, description)) => ServerMessage::Daily { name, description },
+        Err(e) => e.into(),
+    }
+}
@@ -0,0 +1,223 @@
+//! Correlation of a request with the calls it makes to the upstream apis.
+//!
+//! `RequestIdMiddleware` runs the rest of the request in the scope of its `Correlation`, and the
+//! wrappers send its request id and W3C trace context along with their calls.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context as TaskContext, Poll};
+
+use opentelemetry::propagation::TextMapPropagator;
+use opentelemetry::trace::{SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId, TraceState};
+use opentelemetry::Context;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use uuid::Uuid;
+
+use crate::middlewares::extension_types::RequestId;
+
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+pub const TRACEPARENT_HEADER: &str = "traceparent";
+pub const TRACESTATE_HEADER: &str = "tracestate";
+
+thread_local! {
+    static CURRENT: RefCell<Option<Correlation>> = const { RefCell::new(None) };
+}
+
+#[derive(Debug, Clone)]
+pub struct Correlation {
+    pub request_id: RequestId,
+    /// Trace context of the request, with the span id of this server.
+    pub trace: SpanContext,
+}
+
+impl Correlation {
+    pub fn new(request_id: RequestId, trace: SpanContext) -> Self {
+        Self { request_id, trace }
+    }
+
+    /// Correlation of the future being polled, if any.
+    pub fn current() -> Option<Correlation> {
+        CURRENT.with(|current| current.borrow().clone())
+    }
+
+    /// Poll the future in the scope of this correlation. Tasks spawned by the future aren't,
+    /// unless they are scoped too.
+    pub fn scope<F: Future>(self, future: F) -> Scoped<F> {
+        scoped(Some(self), future)
+    }
+
+    /// Headers of a call to an upstream api. The parent of the upstream is the current span if
+    /// traces are exported, this server otherwise.
+    pub fn headers(&self) -> Vec<(&'static str, String)> {
+        let span = tracing::Span::current()
+            .context()
+            .span()
+            .span_context()
+            .clone();
+        let trace = if span.is_valid() { &span } else { &self.trace };
+
+        let mut headers = vec![(REQUEST_ID_HEADER, self.request_id.as_str().to_string())];
+        headers.extend(trace_headers(trace));
+        headers
+    }
+}
+
+/// Poll the future in the scope of the correlation, if any.
+pub fn scoped<F: Future>(correlation: Option<Correlation>, future: F) -> Scoped<F> {
+    Scoped {
+        correlation,
+        future: Box::pin(future),
+    }
+}
+
+/// Keep the current correlation in a future polled elsewhere, e.g. in a new task.
+pub fn in_current<F: Future>(future: F) -> Scoped<F> {
+    scoped(Correlation::current(), future)
+}
+
+/// Headers of a call to an upstream api made in the scope of a correlation, none otherwise.
+pub fn outgoing_headers() -> Vec<(&'static str, String)> {
+    Correlation::current()
+        .map(|correlation| correlation.headers())
+        .unwrap_or_default()
+}
+
+/// Trace context given by the caller, if `traceparent` is valid.
+pub fn extract(traceparent: Option<&str>, tracestate: Option<&str>) -> Option<SpanContext> {
+    let mut carrier = HashMap::new();
+    if let Some(traceparent) = traceparent {
+        carrier.insert(TRACEPARENT_HEADER.to_string(), traceparent.to_string());
+    }
+    if let Some(tracestate) = tracestate {
+        carrier.insert(TRACESTATE_HEADER.to_string(), tracestate.to_string());
+    }
+
+    let context = TraceContextPropagator::new().extract(&carrier);
+    let parent = context.span().span_context().clone();
+    parent.is_valid().then_some(parent)
+}
+
+/// `traceparent` and `tracestate` (only if not empty) headers of a trace context.
+pub fn trace_headers(trace: &SpanContext) -> Vec<(&'static str, String)> {
+    let mut carrier = HashMap::new();
+    let context = Context::new().with_remote_span_context(trace.clone());
+    TraceContextPropagator::new().inject_context(&context, &mut carrier);
+
+    [TRACEPARENT_HEADER, TRACESTATE_HEADER]
+        .iter()
+        .filter_map(|&name| {
+            carrier
+                .remove(name)
+                .filter(|value| !value.is_empty())
+                .map(|value| (name, value))
+        })
+        .collect()
+}
+
+/// Trace context of a request when traces aren't exported: a new span of the trace of the
+/// caller, or of a new trace.
+pub fn local_trace(parent: Option<&SpanContext>) -> SpanContext {
+    let mut span_id = [0; 8];
+    span_id.copy_from_slice(&random_bytes()[..8]);
+    let span_id = SpanId::from_bytes(span_id);
+    match parent {
+        Some(parent) => SpanContext::new(
+            parent.trace_id(),
+            span_id,
+            parent.trace_flags(),
+            false,
+            parent.trace_state().clone(),
+        ),
+        None => SpanContext::new(
+            TraceId::from_bytes(random_bytes()),
+            span_id,
+            TraceFlags::default(),
+            false,
+            TraceState::default(),
+        ),
+    }
+}
+
+fn random_bytes() -> [u8; 16] {
+    *Uuid::new_v4().as_bytes()
+}
+
+/// Future polled in the scope of a correlation, see `Correlation::scope`.
+pub struct Scoped<F> {
+    correlation: Option<Correlation>,
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for Scoped<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let _guard = ScopeGuard::enter(this.correlation.clone());
+        this.future.as_mut().poll(cx)
+    }
+}
+
+/// Restore the previous correlation when dropped, even on panic.
+struct ScopeGuard {
+    previous: Option<Correlation>,
+}
+
+impl ScopeGuard {
+    fn enter(correlation: Option<Correlation>) -> Self {
+        let previous = CURRENT.with(|current| current.replace(correlation));
+        Self { previous }
+    }
+}
+
+impl Drop for ScopeGuard {
+    fn drop(&mut self) {
+        CURRENT.with(|current| *current.borrow_mut() = self.previous.take());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRACEPARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    #[test]
+    fn propagate_trace_context() {
+        let parent = extract(Some(TRACEPARENT), Some("vendor=value")).unwrap();
+        let trace = local_trace(Some(&parent));
+        let headers = trace_headers(&trace);
+
+        assert_eq!(TRACEPARENT_HEADER, headers[0].0);
+        assert!(headers[0]
+            .1
+            .starts_with("00-4bf92f3577b34da6a3ce929d0e0e4736-"));
+        assert!(headers[0].1.ends_with("-01"));
+        assert!(!headers[0].1.contains("00f067aa0ba902b7"));
+        assert_eq!((TRACESTATE_HEADER, "vendor=value".to_string()), headers[1]);
+
+        assert!(extract(
+            Some("00-00000000000000000000000000000000-00f067aa0ba902b7-01"),
+            None
+        )
+        .is_none());
+        assert!(extract(Some("not a traceparent"), None).is_none());
+        assert_eq!(1, trace_headers(&local_trace(None)).len());
+    }
+
+    #[async_std::test]
+    async fn scoped_future() {
+        let correlation = Correlation::new(RequestId::new(), local_trace(None));
+        let request_id = correlation.request_id.as_str().to_string();
+
+        let headers = correlation.scope(async { outgoing_headers() }).await;
+
+        assert_eq!((REQUEST_ID_HEADER, request_id), headers[0]);
+        assert_eq!(TRACEPARENT_HEADER, headers[1].0);
+        assert!(Correlation::current().is_none());
+        assert!(in_current(async { outgoing_headers() }).await.is_empty());
+    }
+}
@@ -0,0 +1,405 @@
+//! GraphQL schema over the pokemons and their translations.
+//!
+//! The upstream calls go through dataloaders, so a pokemon or a description requested several
+//! times in a query is only fetched once, and the lookups of the same level run concurrently.
+//! Every upstream call is charged to the quotas of the client, and a query can't make more than
+//! `LOADER_MAX_KEYS` calls of each kind.
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::future::Future;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use async_graphql::dataloader::{DataLoader, Loader};
+use async_graphql::{
+    Context, EmptyMutation, EmptySubscription, Enum, Error, ErrorExtensions, Object, Request,
+    Result, Schema, SimpleObject,
+};
+use futures::future::{BoxFuture, FutureExt, Shared};
+use futures::stream::{self, StreamExt};
+
+use crate::controllers::errors::HttpError;
+use crate::controllers::pokemon::BATCH_MAX_SIZE;
+use crate::controllers::translate::validate_text;
+use crate::correlation;
+use crate::middlewares::extension_types::Quotas;
+use crate::server::State;
+use crate::store::Source;
+use crate::wrappers::errors::WrapperError;
+use crate::wrappers::pokemon::{normalize_name, PokemonWrapper, Species};
+use crate::wrappers::shakespeare::ShakespeareWrapper;
+
+pub type PokeSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+/// Max depth of a query, the evolutions could be nested without end.
+const MAX_DEPTH: usize = 8;
+
+/// Max number of concurrent upstream calls of a loader.
+const LOAD_CONCURRENCY: usize = 4;
+
+/// Max number of distinct keys a loader fetches in a query, as many as a batch.
+const LOADER_MAX_KEYS: usize = BATCH_MAX_SIZE;
+
+/// Max complexity of a query, enough for a full batch with every field.
+const MAX_COMPLEXITY: usize = 600;
+
+/// Complexity of a field calling the pokemon api.
+const UPSTREAM_COST: usize = 5;
+
+/// Complexity of a field calling the translation api, the scarcest quota.
+const TRANSLATION_COST: usize = 10;
+
+/// Pokemons counted in an evolution chain, most chains have three.
+const EVOLUTIONS_COUNTED: usize = 3;
+
+/// Schema of the api, built on first use.
+pub fn schema() -> &'static PokeSchema {
+    static SCHEMA: OnceLock<PokeSchema> = OnceLock::new();
+    SCHEMA.get_or_init(|| {
+        Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+            .limit_depth(MAX_DEPTH)
+            .limit_complexity(MAX_COMPLEXITY)
+            .finish()
+    })
+}
+
+/// Add the state, the quotas of the client and a new set of loaders to the request, nothing is
+/// shared between requests.
+pub fn with_loaders(request: Request, state: &State, quotas: Quotas) -> Request {
+    let pokemon_wrapper = state.pokemon_wrapper.clone();
+    let shakespeare_wrapper = state.shakespeare_wrapper.clone();
+    request
+        .data(state.clone())
+        .data(quotas)
+        .data(RequestLoader::new(SpeciesLoader(pokemon_wrapper.clone())))
+        .data(RequestLoader::new(SpriteLoader(pokemon_wrapper.clone())))
+        .data(RequestLoader::new(EvolutionLoader(pokemon_wrapper)))
+        .data(RequestLoader::new(TranslationLoader(shakespeare_wrapper)))
+}
+
+/// Spawn a loader task, in the correlation of the request for its upstream calls.
+fn spawn<F>(future: F) -> async_std::task::JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    async_std::task::spawn(correlation::in_current(future))
+}
+
+/// Client facing error, with the same code and status as the rest api.
+fn to_graphql_error(e: &WrapperError) -> Error {
+    Error::new(e.explanation()).extend_with(|_, extensions| {
+        extensions.set("code", e.code());
+        extensions.set("status", e.status() as u16);
+    })
+}
+
+/// Result of a key, the failure of a key doesn't fail the rest of the batch.
+type Loaded<T> = std::result::Result<T, Arc<WrapperError>>;
+
+/// Call `load` for each key, concurrently.
+async fn load_each<T, F, Fut>(keys: &[String], load: F) -> HashMap<String, Loaded<T>>
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = std::result::Result<T, WrapperError>>,
+{
+    stream::iter(keys.iter().cloned())
+        .map(|key| {
+            let value = load(key.clone());
+            async move { (key, value.await.map_err(Arc::new)) }
+        })
+        .buffer_unordered(LOAD_CONCURRENCY)
+        .collect()
+        .await
+}
+
+/// Pokédex number and original description of a pokemon, by name.
+pub struct SpeciesLoader(PokemonWrapper);
+
+impl Loader<String> for SpeciesLoader {
+    type Value = Loaded<Species>;
+    type Error = Infallible;
+
+    async fn load(
+        &self,
+        names: &[String],
+    ) -> std::result::Result<HashMap<String, Self::Value>, Infallible> {
+        Ok(load_each(names, |name| {
+            let pokemon_wrapper = self.0.clone();
+            async move { pokemon_wrapper.get_species(&name).await }
+        })
+        .await)
+    }
+}
+
+/// Sprite of a pokemon, by name.
+pub struct SpriteLoader(PokemonWrapper);
+
+impl Loader<String> for SpriteLoader {
+    type Value = Loaded<Option<String>>;
+    type Error = Infallible;
+
+    async fn load(
+        &self,
+        names: &[String],
+    ) -> std::result::Result<HashMap<String, Self::Value>, Infallible> {
+        Ok(load_each(names, |name| {
+            let pokemon_wrapper = self.0.clone();
+            async move { pokemon_wrapper.get_sprite(&name).await }
+        })
+        .await)
+    }
+}
+
+/// Evolution chain of a pokemon, by name.
+pub struct EvolutionLoader(PokemonWrapper);
+
+impl Loader<String> for EvolutionLoader {
+    type Value = Loaded<Vec<String>>;
+    type Error = Infallible;
+
+    async fn load(
+        &self,
+        names: &[String],
+    ) -> std::result::Result<HashMap<String, Self::Value>, Infallible> {
+        Ok(load_each(names, |name| {
+            let pokemon_wrapper = self.0.clone();
+            async move { pokemon_wrapper.get_evolutions(&name).await }
+        })
+        .await)
+    }
+}
+
+/// Shakespeare's style translation, by original text.
+pub struct TranslationLoader(ShakespeareWrapper);
+
+impl Loader<String> for TranslationLoader {
+    type Value = Loaded<String>;
+    type Error = Infallible;
+
+    async fn load(
+        &self,
+        texts: &[String],
+    ) -> std::result::Result<HashMap<String, Self::Value>, Infallible> {
+        Ok(load_each(texts, |text| {
+            let shakespeare_wrapper = self.0.clone();
+            async move { shakespeare_wrapper.get_translation(&text).await }
+        })
+        .await)
+    }
+}
+
+/// Load of a key, shared by every field asking for it.
+type SharedLoad<T> = Shared<BoxFuture<'static, Option<Loaded<T>>>>;
+
+/// Loader of a request, loading each key once even if it's asked for again while in flight, and
+/// charging every key to the quotas of the client.
+pub struct RequestLoader<L: Loader<String, Value = Loaded<T>>, T: Clone> {
+    loader: Arc<DataLoader<L>>,
+    loads: Mutex<HashMap<String, SharedLoad<T>>>,
+}
+
+impl<L, T> RequestLoader<L, T>
+where
+    L: Loader<String, Value = Loaded<T>, Error = Infallible>,
+    T: Send + Sync + Clone + 'static,
+{
+    fn new(loader: L) -> Self {
+        Self {
+            loader: Arc::new(DataLoader::new(loader, spawn)),
+            loads: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn load(&self, key: &str, quotas: &Quotas) -> Result<SharedLoad<T>> {
+        let mut loads = self.loads.lock().expect("loads lock poisoned");
+        if let Some(load) = loads.get(key) {
+            return Ok(load.clone());
+        }
+        if loads.len() >= LOADER_MAX_KEYS {
+            return Err(Error::new(format!(
+                "At most {} pokemons or texts can be loaded by a query",
+                LOADER_MAX_KEYS
+            ))
+            .extend_with(|_, extensions| {
+                extensions.set("code", "too_many_keys");
+                extensions.set("status", 400);
+            }));
+        }
+        if let Err(retry_after) = quotas.spend(1) {
+            return Err(
+                Error::new("Too Many Requests").extend_with(|_, extensions| {
+                    extensions.set("code", "rate_limited");
+                    extensions.set("status", 429);
+                    extensions.set("retry_after", retry_after.as_secs());
+                }),
+            );
+        }
+
+        let loader = self.loader.clone();
+        let owned_key = key.to_string();
+        let load = async move {
+            match loader.load_one(owned_key).await {
+                Ok(loaded) => loaded,
+                Err(never) => match never {},
+            }
+        }
+        .boxed()
+        .shared();
+        loads.insert(key.to_string(), load.clone());
+        Ok(load)
+    }
+}
+
+/// Load a key with the loader of the request.
+async fn load<L, T>(ctx: &Context<'_>, key: &str) -> Result<T>
+where
+    L: Loader<String, Value = Loaded<T>, Error = Infallible>,
+    T: Send + Sync + Clone + 'static,
+{
+    let quotas = ctx.data_unchecked::<Quotas>();
+    let loaded = ctx
+        .data_unchecked::<RequestLoader<L, T>>()
+        .load(key, quotas)?
+        .await;
+    match loaded {
+        Some(Ok(value)) => Ok(value),
+        Some(Err(e)) => Err(to_graphql_error(&e)),
+        // every loader returns all of its keys
+        None => Err(Error::new("Unexpected Error")),
+    }
+}
+
+/// Translation styles.
+#[derive(Enum, Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum Style {
+    #[default]
+    Shakespeare,
+}
+
+#[derive(SimpleObject)]
+pub struct Translation {
+    style: Style,
+    text: String,
+    /// Curated by hand instead of translated by the api.
+    manual: bool,
+}
+
+pub struct Pokemon {
+    name: String,
+}
+
+impl Pokemon {
+    fn new(name: &str) -> Self {
+        Self {
+            name: normalize_name(name),
+        }
+    }
+}
+
+#[Object]
+impl Pokemon {
+    async fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// English description from the pokédex.
+    #[graphql(complexity = "UPSTREAM_COST + child_complexity")]
+    async fn description(&self, ctx: &Context<'_>) -> Result<String> {
+        let state = ctx.data_unchecked::<State>();
+        // the translation counts the lookup, a pokemon asked for both is counted once
+        match state.cache.peek(&self.name) {
+            Some(entry) => Ok(entry.description),
+            None => Ok(load::<SpeciesLoader, Species>(ctx, &self.name)
+                .await?
+                .description),
+        }
+    }
+
+    /// Description in the given style, the manual translation if any.
+    #[graphql(complexity = "UPSTREAM_COST + TRANSLATION_COST + child_complexity")]
+    async fn translation(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default)] style: Style,
+    ) -> Result<Translation> {
+        let state = ctx.data_unchecked::<State>();
+        if let Some(record) = state.overrides.get(&self.name) {
+            return Ok(Translation {
+                style,
+                text: record.translation,
+                manual: record.source == Source::Manual,
+            });
+        }
+
+        let translation = match state.cache.get(&self.name) {
+            Some(entry) => entry.translation,
+            None => {
+                let species = load::<SpeciesLoader, Species>(ctx, &self.name).await?;
+                let translation = load::<TranslationLoader, _>(ctx, &species.description).await?;
+                state
+                    .cache
+                    .insert_species(&self.name, &species, &translation);
+                translation
+            }
+        };
+        Ok(Translation {
+            style,
+            text: translation,
+            manual: false,
+        })
+    }
+
+    /// Front sprite of the default form.
+    #[graphql(complexity = "UPSTREAM_COST + child_complexity")]
+    async fn sprite(&self, ctx: &Context<'_>) -> Result<Option<String>> {
+        load::<SpriteLoader, _>(ctx, &self.name).await
+    }
+
+    /// Every pokemon of the evolution chain, including this one, in evolution order.
+    #[graphql(complexity = "UPSTREAM_COST + EVOLUTIONS_COUNTED * child_complexity")]
+    async fn evolutions(&self, ctx: &Context<'_>) -> Result<Vec<Pokemon>> {
+        let names = load::<EvolutionLoader, _>(ctx, &self.name).await?;
+        Ok(names.iter().map(|name| Pokemon::new(name)).collect())
+    }
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// A pokemon by name.
+    async fn pokemon(&self, name: String) -> Pokemon {
+        Pokemon::new(&name)
+    }
+
+    /// Several pokemons at once.
+    #[graphql(complexity = "names.len() * child_complexity")]
+    async fn pokemons(&self, names: Vec<String>) -> Result<Vec<Pokemon>> {
+        if names.is_empty() || names.len() > BATCH_MAX_SIZE {
+            return Err(Error::new(format!(
+                "Between 1 and {} pokemons can be requested at once",
+                BATCH_MAX_SIZE
+            )));
+        }
+        Ok(names.iter().map(|name| Pokemon::new(name)).collect())
+    }
+
+    /// Translate any text.
+    #[graphql(complexity = "TRANSLATION_COST + child_complexity")]
+    async fn translate(
+        &self,
+        ctx: &Context<'_>,
+        text: String,
+        #[graphql(default)] style: Style,
+    ) -> Result<Translation> {
+        validate_text(&text).map_err(Error::new)?;
+
+        let translation = load::<TranslationLoader, _>(ctx, &text).await?;
+        Ok(Translation {
+            style,
+            text: translation,
+            manual: false,
+        })
+    }
+}
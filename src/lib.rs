@@ -1,4 +1,13 @@
+pub mod cache;
+pub mod cli;
+pub mod config;
 pub mod controllers;
+pub mod correlation;
+pub mod graphql;
+pub mod metrics;
 pub mod middlewares;
 pub mod server;
+pub mod store;
+pub mod telemetry;
+pub mod warmup;
 pub mod wrappers;
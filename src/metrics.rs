@@ -0,0 +1,194 @@
+//! Prometheus metrics, rendered in the text exposition format.
+//!
+//! The counters are plain shared values updated by the middleware and the wrappers, the rest is
+//! read from the state when `/metrics` is scraped.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Upper bounds of the latency buckets, in seconds.
+pub const LATENCY_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Observations of a latency histogram.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HistogramData {
+    /// Observations of each bucket, not cumulative.
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl HistogramData {
+    fn observe(&mut self, elapsed: Duration) {
+        let secs = elapsed.as_secs_f64();
+        if let Some(bucket) = LATENCY_BUCKETS.iter().position(|bound| secs <= *bound) {
+            self.buckets[bucket] += 1;
+        }
+        self.sum += secs;
+        self.count += 1;
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+}
+
+/// Latency histogram. Clones share the same observations.
+#[derive(Clone, Debug, Default)]
+pub struct Histogram {
+    data: Arc<Mutex<HistogramData>>,
+}
+
+impl Histogram {
+    pub fn observe(&self, elapsed: Duration) {
+        self.data
+            .lock()
+            .expect("histogram lock poisoned")
+            .observe(elapsed);
+    }
+
+    pub fn snapshot(&self) -> HistogramData {
+        self.data.lock().expect("histogram lock poisoned").clone()
+    }
+}
+
+/// Last known value of something, unknown until first set. Clones share the same value.
+#[derive(Clone, Debug, Default)]
+pub struct Gauge {
+    value: Arc<Mutex<Option<f64>>>,
+}
+
+impl Gauge {
+    pub fn set(&self, value: f64) {
+        *self.value.lock().expect("gauge lock poisoned") = Some(value);
+    }
+
+    pub fn get(&self) -> Option<f64> {
+        *self.value.lock().expect("gauge lock poisoned")
+    }
+}
+
+/// Served requests, by route and status. Clones share the same observations.
+#[derive(Clone, Debug, Default)]
+pub struct Metrics {
+    requests: Arc<Mutex<BTreeMap<(String, u16), HistogramData>>>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn observe_request(&self, route: &str, status: u16, elapsed: Duration) {
+        self.requests
+            .lock()
+            .expect("metrics lock poisoned")
+            .entry((route.to_string(), status))
+            .or_default()
+            .observe(elapsed);
+    }
+
+    pub fn requests(&self) -> BTreeMap<(String, u16), HistogramData> {
+        self.requests.lock().expect("metrics lock poisoned").clone()
+    }
+}
+
+/// Writer of the text exposition format.
+#[derive(Debug, Default)]
+pub struct Exposition {
+    out: String,
+}
+
+impl Exposition {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start a metric family, its samples must follow.
+    pub fn family(&mut self, name: &str, kind: &str, help: &str) -> &mut Self {
+        let _ = writeln!(self.out, "# HELP {} {}", name, help);
+        let _ = writeln!(self.out, "# TYPE {} {}", name, kind);
+        self
+    }
+
+    pub fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: f64) -> &mut Self {
+        let _ = writeln!(self.out, "{}{} {}", name, format_labels(labels), value);
+        self
+    }
+
+    /// Samples of a histogram, with cumulative buckets.
+    pub fn histogram(
+        &mut self,
+        name: &str,
+        labels: &[(&str, &str)],
+        data: &HistogramData,
+    ) -> &mut Self {
+        let mut cumulative = 0;
+        for (bound, count) in LATENCY_BUCKETS.iter().zip(data.buckets.iter()) {
+            cumulative += count;
+            let le = bound.to_string();
+            let labels = [labels, &[("le", le.as_str())]].concat();
+            self.sample(&format!("{}_bucket", name), &labels, cumulative as f64);
+        }
+        let labels_inf = [labels, &[("le", "+Inf")]].concat();
+        self.sample(&format!("{}_bucket", name), &labels_inf, data.count as f64);
+        self.sample(&format!("{}_sum", name), labels, data.sum);
+        self.sample(&format!("{}_count", name), labels, data.count as f64)
+    }
+
+    pub fn finish(self) -> String {
+        self.out
+    }
+}
+
+fn format_labels(labels: &[(&str, &str)]) -> String {
+    if labels.is_empty() {
+        return String::new();
+    }
+    let labels: Vec<String> = labels
+        .iter()
+        .map(|(name, value)| {
+            let value = value
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n");
+            format!("{}=\"{}\"", name, value)
+        })
+        .collect();
+    format!("{{{}}}", labels.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_histogram() {
+        let histogram = Histogram::default();
+        histogram.observe(Duration::from_millis(20));
+        histogram.observe(Duration::from_secs(20));
+
+        let mut exposition = Exposition::new();
+        exposition
+            .family("latency_seconds", "histogram", "Latency.")
+            .histogram(
+                "latency_seconds",
+                &[("route", "/a\"b")],
+                &histogram.snapshot(),
+            );
+        let text = exposition.finish();
+
+        assert!(
+            text.starts_with("# HELP latency_seconds Latency.\n# TYPE latency_seconds histogram\n")
+        );
+        assert!(text.contains("latency_seconds_bucket{route=\"/a\\\"b\",le=\"0.01\"} 0\n"));
+        assert!(text.contains("latency_seconds_bucket{route=\"/a\\\"b\",le=\"0.025\"} 1\n"));
+        assert!(text.contains("latency_seconds_bucket{route=\"/a\\\"b\",le=\"10\"} 1\n"));
+        assert!(text.contains("latency_seconds_bucket{route=\"/a\\\"b\",le=\"+Inf\"} 2\n"));
+        assert!(text.contains("latency_seconds_count{route=\"/a\\\"b\"} 2\n"));
+    }
+}
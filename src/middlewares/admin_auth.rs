@@ -0,0 +1,83 @@
+use kv_log_macro::{info, warn};
+use tide::http::headers::{AUTHORIZATION, WWW_AUTHENTICATE};
+use tide::{Middleware, Next, Request, Response, Result};
+
+use super::extension_types::RequestId;
+
+/// Only let through requests with the admin bearer token, audit-logging every admin action.
+#[derive(Debug, Default, Clone)]
+pub struct AdminAuthMiddleware {
+    /// The admin api is disabled if there is no token.
+    token: Option<String>,
+}
+
+impl AdminAuthMiddleware {
+    /// Create a new instance of `AdminAuthMiddleware`.
+    #[must_use]
+    pub fn new(token: Option<String>) -> Self {
+        Self { token }
+    }
+
+    fn is_authorized<State>(&self, req: &Request<State>) -> bool {
+        let token = match &self.token {
+            Some(token) => token,
+            None => return false,
+        };
+
+        req.header(AUTHORIZATION)
+            .and_then(|header| header.last().as_str().strip_prefix("Bearer "))
+            .is_some_and(|bearer| constant_time_eq(bearer.as_bytes(), token.as_bytes()))
+    }
+
+    /// Check the token and log the admin action.
+    async fn handle<'a, State: Clone + Send + Sync + 'static>(
+        &'a self,
+        req: Request<State>,
+        next: Next<'a, State>,
+    ) -> Result {
+        let request_id = req
+            .ext::<RequestId>()
+            .map(|request_id| request_id.as_str().to_string())
+            .unwrap_or_else(|| "(no RequestId)".to_string());
+        let path = req.url().path().to_owned();
+        let method = req.method();
+        let ip = req.peer_addr().unwrap_or("(no Peer Address)").to_string();
+
+        if !self.is_authorized(&req) {
+            warn!("Unauthorized admin request", {
+                method: method.as_ref(),
+                path: path,
+                ip: ip,
+                request_id: request_id,
+            });
+
+            let mut res = Response::new(401);
+            res.insert_header(WWW_AUTHENTICATE, "Bearer");
+            return Ok(res);
+        }
+
+        let res = next.run(req).await;
+
+        info!("Admin action", {
+            method: method.as_ref(),
+            path: path,
+            ip: ip,
+            status: res.status() as u16,
+            request_id: request_id,
+        });
+
+        Ok(res)
+    }
+}
+
+#[tide::utils::async_trait]
+impl<State: Clone + Send + Sync + 'static> Middleware<State> for AdminAuthMiddleware {
+    async fn handle(&self, req: Request<State>, next: Next<'_, State>) -> Result {
+        self.handle(req, next).await
+    }
+}
+
+/// Compare without leaking how many bytes match through the elapsed time.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
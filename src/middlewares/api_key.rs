@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use kv_log_macro::warn;
+use tide::http::headers::RETRY_AFTER;
+use tide::{Middleware, Next, Request, Response, Result};
+
+use super::extension_types::{ClientId, Quota, Quotas, RequestId};
+use crate::config::ApiKey;
+
+/// Header with the API key, it can also be given in the `api_key` query parameter.
+pub const API_KEY_HEADER: &str = "X-Api-Key";
+
+/// Routes that don't need an API key, the admin api has its own token.
+const EXEMPT_PATHS: &[&str] = &[
+    "/health_check",
+    "/metrics",
+    "/admin",
+    "/static",
+    "/docs",
+    "/openapi.json",
+];
+
+/// Requests made by a client in the current quota window.
+#[derive(Debug)]
+struct Usage {
+    window_start: Instant,
+    count: u32,
+}
+
+/// Reject requests without a valid API key, attaching the `ClientId` of the key to the others
+/// and enforcing the key quota.
+#[derive(Debug, Clone)]
+pub struct ApiKeyMiddleware {
+    keys: Arc<HashMap<String, ApiKey>>,
+    window: Duration,
+    usage: Arc<Mutex<HashMap<String, Usage>>>,
+}
+
+impl ApiKeyMiddleware {
+    /// Create a new instance of `ApiKeyMiddleware`, counting the quotas in windows of `window`.
+    #[must_use]
+    pub fn new(keys: Vec<ApiKey>, window: Duration) -> Self {
+        let keys = keys.into_iter().map(|key| (key.key.clone(), key)).collect();
+        Self {
+            keys: Arc::new(keys),
+            window,
+            usage: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Count `units` requests of the client, returning the time left until the quota is
+    /// restored if it's exhausted.
+    fn spend(&self, api_key: &ApiKey, units: u32) -> Option<Duration> {
+        let quota = api_key.quota?;
+        let now = Instant::now();
+
+        let mut usage = self.usage.lock().expect("usage lock poisoned");
+        let usage = usage.entry(api_key.key.clone()).or_insert(Usage {
+            window_start: now,
+            count: 0,
+        });
+        if now.duration_since(usage.window_start) >= self.window {
+            usage.window_start = now;
+            usage.count = 0;
+        }
+
+        if usage.count.saturating_add(units) > quota {
+            return Some(self.window - now.duration_since(usage.window_start));
+        }
+        usage.count += units;
+        None
+    }
+
+    /// Check the API key and its quota.
+    async fn handle<'a, State: Clone + Send + Sync + 'static>(
+        &'a self,
+        mut req: Request<State>,
+        next: Next<'a, State>,
+    ) -> Result {
+        let path = req.url().path().to_owned();
+        if EXEMPT_PATHS
+            .iter()
+            .any(|exempt| path == *exempt || path.starts_with(&format!("{}/", exempt)))
+        {
+            return Ok(next.run(req).await);
+        }
+
+        let request_id = req
+            .ext::<RequestId>()
+            .map(|request_id| request_id.as_str().to_string())
+            .unwrap_or_else(|| "(no RequestId)".to_string());
+
+        let key = req
+            .header(API_KEY_HEADER)
+            .map(|header| header.last().as_str().to_string())
+            .or_else(|| {
+                req.url()
+                    .query_pairs()
+                    .find(|(name, _)| name == "api_key")
+                    .map(|(_, value)| value.into_owned())
+            });
+
+        let api_key = match key.as_ref().and_then(|key| self.keys.get(key)) {
+            Some(api_key) => api_key,
+            None => {
+                warn!("Invalid API key", {
+                    path: path,
+                    missing: key.is_none(),
+                    request_id: request_id,
+                });
+                return Ok(Response::new(401));
+            }
+        };
+
+        let client_id = ClientId::new(&api_key.client);
+        if let Some(retry_after) = self.spend(api_key, 1) {
+            warn!("API key quota exhausted", {
+                path: path,
+                client: client_id,
+                request_id: request_id,
+            });
+            let mut res = Response::new(429);
+            res.insert_header(RETRY_AFTER, retry_after.as_secs().max(1).to_string());
+            return Ok(res);
+        }
+
+        req.set_ext(client_id);
+        let mut quotas = req.ext::<Quotas>().cloned().unwrap_or_default();
+        quotas.add(Arc::new(KeyQuota {
+            middleware: self.clone(),
+            api_key: api_key.clone(),
+        }));
+        req.set_ext(quotas);
+        Ok(next.run(req).await)
+    }
+}
+
+/// Quota of the API key of a request.
+struct KeyQuota {
+    middleware: ApiKeyMiddleware,
+    api_key: ApiKey,
+}
+
+impl Quota for KeyQuota {
+    fn spend(&self, units: u32) -> std::result::Result<(), Duration> {
+        match self.middleware.spend(&self.api_key, units) {
+            Some(retry_after) => Err(retry_after.max(Duration::from_secs(1))),
+            None => Ok(()),
+        }
+    }
+}
+
+#[tide::utils::async_trait]
+impl<State: Clone + Send + Sync + 'static> Middleware<State> for ApiKeyMiddleware {
+    async fn handle(&self, req: Request<State>, next: Next<'_, State>) -> Result {
+        self.handle(req, next).await
+    }
+}
@@ -0,0 +1,68 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use tide::{Middleware, Next, Request, Result};
+
+use crate::config::VersionConfig;
+
+pub const DEPRECATION: &str = "Deprecation";
+pub const SUNSET: &str = "Sunset";
+const LINK: &str = "Link";
+
+/// Announce the deprecation (RFC 9745) and sunset (RFC 8594) of an api version in every
+/// response of its routes. Nothing is added if the version isn't deprecated.
+#[derive(Debug, Default, Clone)]
+pub struct DeprecationMiddleware {
+    deprecation: Option<String>,
+    sunset: Option<String>,
+    link: Option<String>,
+}
+
+impl DeprecationMiddleware {
+    /// Create a new instance of `DeprecationMiddleware` with the validated settings of a version.
+    #[must_use]
+    pub fn new(config: &VersionConfig) -> Self {
+        let unix_secs = |time: SystemTime| {
+            time.duration_since(UNIX_EPOCH)
+                .map(|since| since.as_secs())
+                .unwrap_or_default()
+        };
+
+        Self {
+            deprecation: config
+                .deprecation()
+                .map(|time| format!("@{}", unix_secs(time))),
+            sunset: config.sunset().map(httpdate::fmt_http_date),
+            link: config
+                .link
+                .as_ref()
+                .map(|url| format!("<{}>; rel=\"deprecation\"", url)),
+        }
+    }
+
+    /// Add the headers of the version to the response.
+    async fn announce<'a, State: Clone + Send + Sync + 'static>(
+        &'a self,
+        req: Request<State>,
+        next: Next<'a, State>,
+    ) -> Result {
+        let mut res = next.run(req).await;
+
+        if let Some(deprecation) = &self.deprecation {
+            res.insert_header(DEPRECATION, deprecation.as_str());
+        }
+        if let Some(sunset) = &self.sunset {
+            res.insert_header(SUNSET, sunset.as_str());
+        }
+        if let Some(link) = &self.link {
+            res.append_header(LINK, link.as_str());
+        }
+        Ok(res)
+    }
+}
+
+#[tide::utils::async_trait]
+impl<State: Clone + Send + Sync + 'static> Middleware<State> for DeprecationMiddleware {
+    async fn handle(&self, req: Request<State>, next: Next<'_, State>) -> Result {
+        self.announce(req, next).await
+    }
+}
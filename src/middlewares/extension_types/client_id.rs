@@ -0,0 +1,43 @@
+use std::fmt::{self, Display};
+
+use log::kv::{ToValue, Value};
+use serde::{Serialize, Serializer};
+
+/// Name of the client that made a request, given by its API key.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ClientId {
+    client: String,
+}
+
+impl ClientId {
+    pub fn new(client: &str) -> Self {
+        Self {
+            client: client.to_string(),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.client
+    }
+}
+
+impl Display for ClientId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.client)
+    }
+}
+
+impl Serialize for ClientId {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl ToValue for ClientId {
+    fn to_value(&self) -> Value<'_> {
+        Value::from(self.as_str())
+    }
+}
@@ -1,3 +1,7 @@
+mod client_id;
+mod quotas;
 mod request_id;
 
-pub use request_id::RequestId;
+pub use client_id::ClientId;
+pub use quotas::{Quota, Quotas};
+pub use request_id::{InvalidRequestId, RequestId};
@@ -0,0 +1,38 @@
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A limit of the client, spent by its requests.
+pub trait Quota: Send + Sync {
+    /// Spend `units` of the quota, or return how long to wait until they are available.
+    fn spend(&self, units: u32) -> Result<(), Duration>;
+}
+
+/// Limits of the client that made a request, added by the middlewares enforcing them.
+///
+/// A middleware only charges the request itself, the routes making several upstream calls
+/// for a single request (`POST /pokemon/batch`, GraphQL queries, batch streams, WebSocket
+/// messages) spend the extra calls here.
+#[derive(Clone, Default)]
+pub struct Quotas {
+    quotas: Vec<Arc<dyn Quota>>,
+}
+
+impl Quotas {
+    pub fn add(&mut self, quota: Arc<dyn Quota>) {
+        self.quotas.push(quota);
+    }
+
+    /// Spend `units` of every quota, or return how long to wait if one of them is exhausted.
+    pub fn spend(&self, units: u32) -> Result<(), Duration> {
+        self.quotas.iter().try_for_each(|quota| quota.spend(units))
+    }
+}
+
+impl fmt::Debug for Quotas {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Quotas")
+            .field("quotas", &self.quotas.len())
+            .finish()
+    }
+}
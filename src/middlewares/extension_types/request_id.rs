@@ -6,9 +6,31 @@ use serde::de::{Error as DeError, Unexpected, Visitor};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use uuid::Uuid;
 
+use crate::config::RequestIdPolicy;
+
+/// Max length of the ids accepted by the `Any` policy.
+pub const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// Alphabet of the ULIDs (Crockford's base32).
+const ULID_ALPHABET: &[u8] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+#[derive(thiserror::Error, Debug)]
+pub enum InvalidRequestId {
+    #[error("Not a UUID: {0}")]
+    NotUuid(#[from] uuid::Error),
+
+    #[error("Neither a UUID nor a ULID")]
+    NotUuidOrUlid,
+
+    #[error(
+        "Empty, longer than {} chars or with chars other than letters, digits, `-`, `_`, `.` and `:`",
+        MAX_REQUEST_ID_LEN
+    )]
+    Malformed,
+}
+
 #[derive(Debug, Clone)]
 pub struct RequestId {
-    id: Uuid,
     string_id: String,
 }
 
@@ -19,20 +41,54 @@ impl RequestId {
         Uuid::new_v4().into()
     }
 
+    /// Parse an id given by a client, if the policy accepts it.
+    pub fn parse(id: &str, policy: RequestIdPolicy) -> Result<Self, InvalidRequestId> {
+        match policy {
+            RequestIdPolicy::Uuid => Ok(id.parse()?),
+            RequestIdPolicy::UuidOrUlid if Uuid::parse_str(id).is_ok() || is_ulid(id) => Ok(Self {
+                string_id: id.to_string(),
+            }),
+            RequestIdPolicy::UuidOrUlid => Err(InvalidRequestId::NotUuidOrUlid),
+            RequestIdPolicy::Any if is_token(id) => Ok(Self {
+                string_id: id.to_string(),
+            }),
+            RequestIdPolicy::Any => Err(InvalidRequestId::Malformed),
+        }
+    }
+
     pub fn as_str(&self) -> &str {
         &self.string_id
     }
 
+    /// The id as a number, only if it's a UUID.
     #[cfg(feature = "honeycomb")]
     #[cfg_attr(feature = "docs", doc(cfg(feature = "honeycomb")))]
-    pub fn as_u128(&self) -> u128 {
-        self.id.as_u128()
+    pub fn as_u128(&self) -> Option<u128> {
+        Uuid::parse_str(&self.string_id).ok().map(|id| id.as_u128())
     }
 }
 
+fn is_ulid(id: &str) -> bool {
+    // 128 bits, so the first char is at most 7
+    id.len() == 26
+        && matches!(id.as_bytes()[0], b'0'..=b'7')
+        && id
+            .bytes()
+            .all(|c| ULID_ALPHABET.contains(&c.to_ascii_uppercase()))
+}
+
+/// Safe to put back in a header or a log line.
+fn is_token(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LEN
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'))
+}
+
 impl Display for RequestId {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.id)
+        write!(f, "{}", self.string_id)
     }
 }
 
@@ -41,7 +97,6 @@ impl From<Uuid> for RequestId {
         let buf = &mut [0; 36];
         let human_id = uuid.to_hyphenated().encode_lower(buf);
         Self {
-            id: uuid,
             string_id: human_id.to_string(),
         }
     }
@@ -51,8 +106,8 @@ impl FromStr for RequestId {
     type Err = uuid::Error;
 
     fn from_str(string: &str) -> Result<Self, uuid::Error> {
+        Uuid::parse_str(string)?;
         Ok(Self {
-            id: Uuid::parse_str(string)?,
             string_id: string.to_string(),
         })
     }
@@ -96,8 +151,46 @@ impl Serialize for RequestId {
     }
 }
 
-impl<'v> ToValue for RequestId {
+impl ToValue for RequestId {
     fn to_value(&self) -> Value<'_> {
         Value::from(self.as_str())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UUID: &str = "6e5b1c1a-3f5e-4c7b-9b1e-0c1f2a3b4c5d";
+    const ULID: &str = "01ARZ3NDEKTSV4RRFFQ69G5FAV";
+
+    #[test]
+    fn parse_with_policy() {
+        for policy in [
+            RequestIdPolicy::Uuid,
+            RequestIdPolicy::UuidOrUlid,
+            RequestIdPolicy::Any,
+        ] {
+            assert_eq!(UUID, RequestId::parse(UUID, policy).unwrap().as_str());
+        }
+
+        assert!(RequestId::parse(ULID, RequestIdPolicy::Uuid).is_err());
+        assert_eq!(
+            ULID,
+            RequestId::parse(ULID, RequestIdPolicy::UuidOrUlid)
+                .unwrap()
+                .as_str()
+        );
+        assert!(RequestId::parse("order-42", RequestIdPolicy::UuidOrUlid).is_err());
+        // out of the 128 bits range
+        assert!(
+            RequestId::parse("81ARZ3NDEKTSV4RRFFQ69G5FAV", RequestIdPolicy::UuidOrUlid).is_err()
+        );
+
+        assert!(RequestId::parse("order-42", RequestIdPolicy::Any).is_ok());
+        assert!(RequestId::parse("", RequestIdPolicy::Any).is_err());
+        assert!(RequestId::parse("order 42\r\n", RequestIdPolicy::Any).is_err());
+        let too_long = "a".repeat(MAX_REQUEST_ID_LEN + 1);
+        assert!(RequestId::parse(&too_long, RequestIdPolicy::Any).is_err());
+    }
+}
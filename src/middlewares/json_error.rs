@@ -0,0 +1,118 @@
+use kv_log_macro::error;
+use serde::{Deserialize, Serialize};
+use tide::http::Mime;
+use tide::{Body, Middleware, Next, Request, Result, StatusCode};
+use utoipa::ToSchema;
+
+use super::extension_types::RequestId;
+use crate::controllers::errors::HttpError;
+use crate::wrappers::errors::WrapperError;
+
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+const PROBLEM_TYPE_PREFIX: &str = "urn:poke-spearify:problem:";
+
+/// RFC 7807 problem details, as rendered by `JsonErrorMiddleware`.
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
+pub struct Problem {
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    pub title: String,
+    pub status: u16,
+    pub detail: String,
+    pub instance: String,
+    pub code: String,
+    #[schema(value_type = Option<String>)]
+    pub request_id: Option<RequestId>,
+}
+
+/// Render every error response as `application/problem+json`.
+#[derive(Debug, Default, Clone)]
+pub struct JsonErrorMiddleware {
+    _priv: (),
+}
+
+impl JsonErrorMiddleware {
+    /// Create a new instance of `JsonErrorMiddleware`.
+    #[must_use]
+    pub fn new() -> Self {
+        Self { _priv: () }
+    }
+
+    /// Replace the body of error responses with a problem document.
+    async fn render<'a, State: Clone + Send + Sync + 'static>(
+        &'a self,
+        req: Request<State>,
+        next: Next<'a, State>,
+    ) -> Result {
+        let request_id = req.ext::<RequestId>().cloned();
+        let instance = req.url().path().to_owned();
+
+        let mut res = next.run(req).await;
+        let status = res.status();
+
+        // Only errors without a body, we don't want to override a body set by the endpoint.
+        let has_body = res.len().map(|len| len > 0).unwrap_or(true);
+        if !(status.is_client_error() || status.is_server_error()) || has_body {
+            return Ok(res);
+        }
+
+        let (code, detail) = match res.error() {
+            Some(e) => match e.downcast_ref::<WrapperError>() {
+                Some(wrapper_error) => {
+                    if status.is_server_error() {
+                        error!("Upstream Error: {}", wrapper_error, {
+                            status: status as u16,
+                            path: instance,
+                            request_id: request_id,
+                        });
+                    }
+                    (
+                        wrapper_error.code().to_string(),
+                        wrapper_error.explanation().to_string(),
+                    )
+                }
+                // Internal errors could leak implementation details, only the status is exposed.
+                None if status.is_server_error() => {
+                    error!("Internal Error: {:?}", e, {
+                        status: status as u16,
+                        path: instance,
+                        request_id: request_id,
+                    });
+                    (status_code(status), status.canonical_reason().to_string())
+                }
+                None => (status_code(status), e.to_string()),
+            },
+            None => (status_code(status), status.canonical_reason().to_string()),
+        };
+
+        let problem = Problem {
+            problem_type: format!("{}{}", PROBLEM_TYPE_PREFIX, code),
+            title: status.canonical_reason().to_string(),
+            status: status as u16,
+            detail,
+            instance,
+            code,
+            request_id,
+        };
+
+        res.set_body(Body::from_json(&problem)?);
+        res.set_content_type(PROBLEM_JSON.parse::<Mime>()?);
+        Ok(res)
+    }
+}
+
+/// Machine-readable code for errors that don't come from a wrapper, e.g. `bad_request`.
+fn status_code(status: StatusCode) -> String {
+    status
+        .canonical_reason()
+        .to_lowercase()
+        .replace(|c: char| !c.is_ascii_alphanumeric(), "_")
+}
+
+#[tide::utils::async_trait]
+impl<State: Clone + Send + Sync + 'static> Middleware<State> for JsonErrorMiddleware {
+    async fn handle(&self, req: Request<State>, next: Next<'_, State>) -> Result {
+        self.render(req, next).await
+    }
+}
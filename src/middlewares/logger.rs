@@ -65,9 +65,18 @@ impl LogMiddleware {
         let status = res.status();
 
         if status.is_server_error() {
-            // Programmer error, always expect there to be JsonErrorMiddleware,
-            // which will catch internal server errors first and assign them a correlation id.
-            error!("Internal Error -- JsonErrorMiddleware must be installed after LogMiddleware");
+            // The error details are logged by JsonErrorMiddleware, which must be installed after
+            // LogMiddleware, here we only keep track of the request.
+            error!("Internal Error: {}", status.canonical_reason(), {
+                status: status as u16,
+                method: method.as_ref(),
+                path: path,
+                ip: ip,
+                referer: referer,
+                user_agent: user_agent,
+                request_id: request_id,
+                elapsed: format!("{:?}", start.elapsed()),
+            });
         } else if status.is_client_error() {
             if let Some(error) = res.error() {
                 warn!("Client Error: {}", status.canonical_reason(), {
@@ -0,0 +1,95 @@
+use std::time::Instant;
+
+use tide::{Middleware, Next, Request, Result};
+
+use crate::metrics::Metrics;
+use crate::server::ROUTES;
+
+/// Label of the requests without route, so unknown paths don't create new series.
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// Count the requests and their latency by route and status.
+#[derive(Debug, Clone)]
+pub struct MetricsMiddleware {
+    metrics: Metrics,
+}
+
+impl MetricsMiddleware {
+    /// Create a new instance of `MetricsMiddleware`, recording in `metrics`.
+    #[must_use]
+    pub fn new(metrics: Metrics) -> Self {
+        Self { metrics }
+    }
+
+    /// Template of the route of the path, e.g. `/pokemon/:pokemon_name`, `unmatched` if none.
+    ///
+    /// A literal segment is preferred to a parameter, as the router does: `/pokemon/batch` is
+    /// its own route, not a pokemon named `batch`.
+    fn route(path: &str) -> &'static str {
+        let segments: Vec<&str> = path.split('/').collect();
+        ROUTES
+            .iter()
+            .map(|(_, template)| *template)
+            .filter(|template| {
+                let parts: Vec<&str> = template.split('/').collect();
+                parts.len() == segments.len()
+                    && parts.iter().zip(&segments).all(|(part, segment)| {
+                        (part.starts_with(':') && !segment.is_empty()) || part == segment
+                    })
+            })
+            .min_by_key(|template| template.matches(':').count())
+            .unwrap_or(UNMATCHED_ROUTE)
+    }
+
+    /// Record a request and its response.
+    async fn record<'a, State: Clone + Send + Sync + 'static>(
+        &'a self,
+        req: Request<State>,
+        next: Next<'a, State>,
+    ) -> Result {
+        let route = Self::route(req.url().path());
+
+        let start = Instant::now();
+        let res = next.run(req).await;
+        self.metrics
+            .observe_request(route, res.status() as u16, start.elapsed());
+        Ok(res)
+    }
+}
+
+#[tide::utils::async_trait]
+impl<State: Clone + Send + Sync + 'static> Middleware<State> for MetricsMiddleware {
+    async fn handle(&self, req: Request<State>, next: Next<'_, State>) -> Result {
+        self.record(req, next).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn route_templates() {
+        assert_eq!(
+            "/pokemon/:pokemon_name",
+            MetricsMiddleware::route("/pokemon/charizard")
+        );
+        assert_eq!(
+            "/pokemon/:pokemon_name",
+            MetricsMiddleware::route("/pokemon/pokemon")
+        );
+        assert_eq!(
+            "/v1/pokemon/:pokemon_name",
+            MetricsMiddleware::route("/v1/pokemon/v1")
+        );
+        assert_eq!("/pokemon/batch", MetricsMiddleware::route("/pokemon/batch"));
+        assert_eq!(
+            "/admin/cache/:pokemon_name",
+            MetricsMiddleware::route("/admin/cache/admin")
+        );
+        assert_eq!("/", MetricsMiddleware::route("/"));
+        assert_eq!(UNMATCHED_ROUTE, MetricsMiddleware::route("/pokemon/"));
+        assert_eq!(UNMATCHED_ROUTE, MetricsMiddleware::route("/nothing/here"));
+        assert_eq!(UNMATCHED_ROUTE, MetricsMiddleware::route("/pokemon/a/b"));
+    }
+}
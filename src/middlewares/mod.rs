@@ -1,3 +1,9 @@
+pub mod admin_auth;
+pub mod api_key;
+pub mod deprecation;
 pub mod extension_types;
+pub mod json_error;
 pub mod logger;
+pub mod metrics;
+pub mod rate_limit;
 pub mod requestid;
@@ -0,0 +1,251 @@
+use std::net::{IpAddr, SocketAddr};
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use kv_log_macro::warn;
+use lru::LruCache;
+use tide::http::headers::RETRY_AFTER;
+use tide::{Middleware, Next, Request, Response, Result};
+
+use super::extension_types::{Quota, Quotas, RequestId};
+use crate::config::RateLimitConfig;
+
+/// Never limited: the probes and the scrapes must keep working under load, and the static
+/// content doesn't reach the upstream apis. Any other route is limited.
+const EXEMPT_PATHS: &[&str] = &[
+    "/health_check",
+    "/metrics",
+    "/static",
+    "/docs",
+    "/openapi.json",
+];
+
+/// Buckets kept before forgetting the least recently seen clients.
+const MAX_BUCKETS: usize = 10_000;
+
+/// Token bucket, refilled continuously up to its capacity.
+#[derive(Debug)]
+pub(crate) struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl Bucket {
+    /// A full bucket.
+    pub(crate) fn new(capacity: u32) -> Self {
+        Self {
+            tokens: f64::from(capacity),
+            updated_at: Instant::now(),
+        }
+    }
+
+    /// Tokens the bucket would have now.
+    fn refilled(&self, now: Instant, capacity: f64, refill_per_sec: f64) -> f64 {
+        let elapsed = now.duration_since(self.updated_at).as_secs_f64();
+        (self.tokens + elapsed * refill_per_sec).min(capacity)
+    }
+
+    /// Take `tokens` tokens, if there are enough.
+    pub(crate) fn take(&mut self, tokens: u32, capacity: u32, refill_per_sec: f64) -> Decision {
+        let tokens = f64::from(tokens);
+        let capacity = f64::from(capacity);
+        let now = Instant::now();
+        self.tokens = self.refilled(now, capacity, refill_per_sec);
+        self.updated_at = now;
+
+        let allowed = self.tokens >= tokens;
+        if allowed {
+            self.tokens -= tokens;
+        }
+
+        Decision {
+            allowed,
+            remaining: self.tokens.floor() as u32,
+            reset: ((capacity - self.tokens) / refill_per_sec).ceil() as u64,
+            retry_after: ((tokens - self.tokens).max(0.0) / refill_per_sec).ceil() as u64,
+        }
+    }
+}
+
+/// Outcome of taking a token from a bucket.
+pub(crate) struct Decision {
+    pub(crate) allowed: bool,
+    pub(crate) remaining: u32,
+    /// Seconds until the bucket is full again.
+    pub(crate) reset: u64,
+    /// Seconds until the next token, if the bucket is empty.
+    pub(crate) retry_after: u64,
+}
+
+/// Limit the requests of each client ip with a token bucket, adding `RateLimit-*` headers to
+/// the responses.
+#[derive(Debug, Clone)]
+pub struct RateLimitMiddleware {
+    capacity: u32,
+    refill_per_sec: f64,
+    trusted_proxies: Arc<Vec<IpAddr>>,
+    buckets: Arc<Mutex<LruCache<Option<IpAddr>, Bucket>>>,
+}
+
+impl RateLimitMiddleware {
+    /// Create a new instance of `RateLimitMiddleware`.
+    #[must_use]
+    pub fn new(config: &RateLimitConfig) -> Self {
+        Self {
+            capacity: config.capacity,
+            refill_per_sec: config.refill_per_sec,
+            trusted_proxies: Arc::new(config.trusted_proxies.clone()),
+            buckets: Arc::new(Mutex::new(LruCache::new(
+                NonZeroUsize::new(MAX_BUCKETS).expect("MAX_BUCKETS isn't zero"),
+            ))),
+        }
+    }
+
+    /// Ip of the client, taken from `X-Forwarded-For` only if the peer is a trusted proxy.
+    ///
+    /// The header is read from the right, the first address that isn't a trusted proxy is the
+    /// client, any address before it could be forged by the client itself.
+    fn client_ip<State>(&self, req: &Request<State>) -> Option<IpAddr> {
+        let peer_ip = req
+            .peer_addr()
+            .and_then(|peer| peer.parse::<SocketAddr>().ok())
+            .map(|peer| peer.ip());
+
+        let peer_ip = match peer_ip {
+            Some(ip) if self.trusted_proxies.contains(&ip) => ip,
+            _ => return peer_ip,
+        };
+
+        let forwarded: Vec<IpAddr> = req
+            .header("X-Forwarded-For")
+            .iter()
+            .flat_map(|values| values.iter())
+            .flat_map(|value| value.as_str().split(','))
+            .filter_map(|ip| ip.trim().parse().ok())
+            .collect();
+
+        let client_ip = forwarded
+            .iter()
+            .rev()
+            .find(|ip| !self.trusted_proxies.contains(ip))
+            .or_else(|| forwarded.first())
+            .copied();
+        Some(client_ip.unwrap_or(peer_ip))
+    }
+
+    /// Take `tokens` tokens of the client bucket, a new client evicts the least recently seen
+    /// one when there are `MAX_BUCKETS` buckets.
+    fn take(&self, client_ip: Option<IpAddr>, tokens: u32) -> Decision {
+        let mut buckets = self.buckets.lock().expect("buckets lock poisoned");
+        buckets
+            .get_or_insert_mut(client_ip, || Bucket::new(self.capacity))
+            .take(tokens, self.capacity, self.refill_per_sec)
+    }
+
+    /// Take a token of the client bucket, rejecting the request if it's empty.
+    async fn handle<'a, State: Clone + Send + Sync + 'static>(
+        &'a self,
+        mut req: Request<State>,
+        next: Next<'a, State>,
+    ) -> Result {
+        let path = req.url().path();
+        if EXEMPT_PATHS
+            .iter()
+            .any(|exempt| path == *exempt || path.starts_with(&format!("{}/", exempt)))
+        {
+            return Ok(next.run(req).await);
+        }
+
+        let client_ip = self.client_ip(&req);
+        let decision = self.take(client_ip, 1);
+
+        let mut res = if decision.allowed {
+            let mut quotas = req.ext::<Quotas>().cloned().unwrap_or_default();
+            quotas.add(Arc::new(ClientBucket {
+                middleware: self.clone(),
+                client_ip,
+            }));
+            req.set_ext(quotas);
+            next.run(req).await
+        } else {
+            let request_id = req
+                .ext::<RequestId>()
+                .map(|request_id| request_id.as_str().to_string())
+                .unwrap_or_else(|| "(no RequestId)".to_string());
+            warn!("Rate limit exceeded", {
+                path: req.url().path(),
+                ip: client_ip.map(|ip| ip.to_string()).unwrap_or_default(),
+                request_id: request_id,
+            });
+
+            let mut res = Response::new(429);
+            res.insert_header(RETRY_AFTER, decision.retry_after.to_string());
+            res
+        };
+
+        res.insert_header("RateLimit-Limit", self.capacity.to_string());
+        res.insert_header("RateLimit-Remaining", decision.remaining.to_string());
+        res.insert_header("RateLimit-Reset", decision.reset.to_string());
+        Ok(res)
+    }
+}
+
+/// Bucket of the client ip of a request.
+struct ClientBucket {
+    middleware: RateLimitMiddleware,
+    client_ip: Option<IpAddr>,
+}
+
+impl Quota for ClientBucket {
+    fn spend(&self, units: u32) -> std::result::Result<(), Duration> {
+        let decision = self.middleware.take(self.client_ip, units);
+        if decision.allowed {
+            Ok(())
+        } else {
+            Err(Duration::from_secs(decision.retry_after.max(1)))
+        }
+    }
+}
+
+#[tide::utils::async_trait]
+impl<State: Clone + Send + Sync + 'static> Middleware<State> for RateLimitMiddleware {
+    async fn handle(&self, req: Request<State>, next: Next<'_, State>) -> Result {
+        self.handle(req, next).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn forget_least_recently_seen() {
+        let middleware = RateLimitMiddleware::new(&RateLimitConfig {
+            capacity: 1,
+            refill_per_sec: 0.001,
+            ..Default::default()
+        });
+        let first = Some(IpAddr::from([10, 0, 0, 1]));
+        let last = Some(IpAddr::from([10, 0, 0, 2]));
+        assert!(middleware.take(first, 1).allowed);
+        assert!(middleware.take(last, 1).allowed);
+
+        for i in 0..MAX_BUCKETS as u32 - 1 {
+            middleware.take(Some(IpAddr::from(((i + 1) << 16).to_be_bytes())), 1);
+            // seen again, so never the least recent one
+            middleware.take(last, 1);
+        }
+
+        assert_eq!(
+            MAX_BUCKETS,
+            middleware
+                .buckets
+                .lock()
+                .expect("buckets lock poisoned")
+                .len()
+        );
+        assert!(middleware.take(first, 1).allowed);
+        assert!(!middleware.take(last, 1).allowed);
+    }
+}
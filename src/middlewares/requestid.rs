@@ -1,24 +1,37 @@
+use opentelemetry::trace::TraceContextExt;
+use opentelemetry::Context;
 use tide::{Middleware, Next, Request};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
 
 #[cfg(feature = "test")]
 use uuid::Uuid;
 
 use super::extension_types::RequestId;
+use crate::config::RequestIdPolicy;
+use crate::correlation::{
+    self, Correlation, REQUEST_ID_HEADER, TRACEPARENT_HEADER, TRACESTATE_HEADER,
+};
 
-/// Attach a RequestId UUID to every request.
+/// Attach a RequestId to every request, and continue the W3C trace context of the caller.
 #[derive(Debug, Default, Clone)]
 pub struct RequestIdMiddleware {
-    _priv: (),
+    policy: RequestIdPolicy,
 }
 
 impl RequestIdMiddleware {
-    /// Create a new instance of `RequestIdMiddleware`.
+    /// Create a new instance of `RequestIdMiddleware`, only accepting UUIDs from the clients.
     #[must_use]
     pub fn new() -> Self {
-        Self { _priv: () }
+        Self::default()
     }
 
-    /// Attach a UUID to every request.
+    /// Accept the ids of the clients allowed by the policy.
+    #[must_use]
+    pub fn with_policy(policy: RequestIdPolicy) -> Self {
+        Self { policy }
+    }
+
+    /// Attach an id to every request, and run it in the scope of its `Correlation`.
     async fn handle<'a, State: Clone + Send + Sync + 'static>(
         &'a self,
         mut req: Request<State>,
@@ -30,8 +43,8 @@ impl RequestIdMiddleware {
 
         let request_id: RequestId;
         #[cfg(not(feature = "test"))]
-        if let Some(header) = req.header("X-Request-Id") {
-            request_id = match header.last().as_str().parse() {
+        if let Some(header) = req.header(REQUEST_ID_HEADER) {
+            request_id = match RequestId::parse(header.last().as_str(), self.policy) {
                 Ok(id) => id,
                 Err(e) => {
                     log::warn!("Invalid X-Request-Id: \"{}\" - Error: {}", header, e);
@@ -46,11 +59,34 @@ impl RequestIdMiddleware {
             request_id = Uuid::nil().into()
         }
 
-        req.set_ext(request_id.clone());
+        let parent = correlation::extract(
+            req.header(TRACEPARENT_HEADER)
+                .map(|header| header.last().as_str()),
+            req.header(TRACESTATE_HEADER)
+                .map(|header| header.last().as_str()),
+        );
+        // the span of `TraceMiddleware`, if installed before
+        let span = tracing::Span::current();
+        if let Some(parent) = &parent {
+            // fails if traces aren't exported, the parent is kept in the correlation then
+            let _ = span.set_parent(Context::new().with_remote_span_context(parent.clone()));
+        }
+        span.set_attribute("request_id", request_id.as_str().to_string());
+        let exported = span.context().span().span_context().clone();
+        let trace = if exported.is_valid() {
+            exported
+        } else {
+            correlation::local_trace(parent.as_ref())
+        };
 
-        let mut res = next.run(req).await;
+        req.set_ext(request_id.clone());
+        let correlation = Correlation::new(request_id.clone(), trace);
+        let mut res = correlation.clone().scope(next.run(req)).await;
 
-        res.insert_header("X-Request-Id", request_id.as_str());
+        res.insert_header(REQUEST_ID_HEADER, request_id.as_str());
+        for (name, value) in correlation::trace_headers(&correlation.trace) {
+            res.insert_header(name, value);
+        }
 
         Ok(res)
     }
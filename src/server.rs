@@ -1,32 +1,360 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use async_std::sync::Mutex;
+use tide::http::Method;
 use tide::Server;
+use tide_websockets::WebSocket;
 
+use crate::cache::{now, CacheEntry, CacheError, TranslationCache};
+use crate::config::Config;
+use crate::controllers::admin;
+use crate::controllers::docs;
+use crate::controllers::graphql;
 use crate::controllers::health_check;
+use crate::controllers::metrics;
 use crate::controllers::pokemon;
-use crate::wrappers::pokemon::PokemonWrapper;
+use crate::controllers::translate;
+use crate::controllers::ui;
+use crate::controllers::ws;
+use crate::metrics::Metrics;
+use crate::middlewares::admin_auth::AdminAuthMiddleware;
+use crate::middlewares::deprecation::DeprecationMiddleware;
+use crate::store::{Record, StoreError, TranslationStore};
+use crate::warmup::{Warmup, WarmupError};
+use crate::wrappers::errors::{Upstream, WrapperError};
+use crate::wrappers::pokemon::{normalize_name, PokemonWrapper, POKEMON_SERVICE_PATH};
 use crate::wrappers::shakespeare::ShakespeareWrapper;
 
+/// Species listed by `State::species_names`, enough for the whole pokédex.
+const SPECIES_LIMIT: usize = 2000;
+
+/// How long the species names are kept, new species are rare.
+const SPECIES_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Names of every species, with when they were listed.
+type SpeciesNames = Option<(Instant, Arc<Vec<String>>)>;
+
+/// Name and translated description of the pokemon of the day, with the day.
+type DailyPokemon = Option<(u64, String, String)>;
+
+const DAY_SECS: u64 = 24 * 60 * 60;
+
+/// Every route of the server, as `(method, path template)`.
+///
+/// The unversioned pokemon routes are aliases of v1, and the admin ones are behind the admin
+/// token.
+pub const ROUTES: &[(Method, &str)] = &[
+    // web ui
+    (Method::Get, "/"),
+    (Method::Get, "/search"),
+    (Method::Get, "/pokedex/:pokemon_name"),
+    (Method::Get, "/static/style.css"),
+    // api documentation
+    (Method::Get, "/static/docs.js"),
+    (Method::Get, "/openapi.json"),
+    (Method::Get, "/docs"),
+    // health check
+    (Method::Get, "/health_check"),
+    // prometheus metrics
+    (Method::Get, "/metrics"),
+    // pokemon api routes
+    (Method::Get, "/pokemon/:pokemon_name"),
+    (Method::Post, "/pokemon/batch"),
+    (Method::Get, "/pokemon/batch/stream"),
+    (Method::Get, "/v1/pokemon/:pokemon_name"),
+    (Method::Post, "/v1/pokemon/batch"),
+    (Method::Get, "/v1/pokemon/batch/stream"),
+    (Method::Get, "/v2/pokemon/:pokemon_name"),
+    // free text translation route
+    (Method::Post, "/translate"),
+    // graphql api
+    (Method::Get, "/graphql"),
+    (Method::Post, "/graphql"),
+    // websocket sessions
+    (Method::Get, "/ws"),
+    // admin routes
+    (Method::Get, "/admin/config"),
+    (Method::Get, "/admin/upstreams"),
+    (Method::Delete, "/admin/cache"),
+    (Method::Delete, "/admin/cache/:pokemon_name"),
+    (Method::Get, "/admin/warmup"),
+    (Method::Get, "/admin/export"),
+    (Method::Post, "/admin/overrides"),
+];
+
+#[derive(thiserror::Error, Debug)]
+pub enum StateError {
+    #[error(transparent)]
+    Cache(#[from] CacheError),
+
+    #[error(transparent)]
+    Store(#[from] StoreError),
+
+    #[error(transparent)]
+    Warmup(#[from] WarmupError),
+}
+
 #[derive(Clone, Debug)]
 pub struct State {
+    /// Effective settings, the default ones if not created from a config.
+    pub config: Arc<Config>,
     pub shakespeare_wrapper: ShakespeareWrapper,
     pub pokemon_wrapper: PokemonWrapper,
+    pub cache: TranslationCache,
+    /// Translations served, for exports.
+    pub store: TranslationStore,
+    /// Manual translations, served instead of the machine ones.
+    pub overrides: TranslationStore,
+    /// Background pre-translation, only if enabled.
+    pub warmup: Option<Warmup>,
+    /// Served requests, recorded by `MetricsMiddleware`.
+    pub metrics: Metrics,
+    /// Names of every species, fetched on the first search.
+    species_names: Arc<Mutex<SpeciesNames>>,
+    /// Pokemon of the day, found once for every client.
+    daily_pokemon: Arc<Mutex<DailyPokemon>>,
+    /// Sprite urls of the pokemons already shown, they don't change.
+    sprites: Arc<RwLock<HashMap<String, Option<String>>>>,
+}
+
+impl State {
+    /// Create a state with an in-memory cache and store.
+    pub fn new(shakespeare_wrapper: ShakespeareWrapper, pokemon_wrapper: PokemonWrapper) -> Self {
+        Self {
+            config: Arc::new(Config::default()),
+            shakespeare_wrapper,
+            pokemon_wrapper,
+            cache: TranslationCache::new(),
+            store: TranslationStore::new(),
+            overrides: TranslationStore::new(),
+            warmup: None,
+            metrics: Metrics::new(),
+            species_names: Arc::default(),
+            daily_pokemon: Arc::default(),
+            sprites: Arc::default(),
+        }
+    }
+
+    /// Create the state described by the config.
+    pub fn from_config(config: &Config) -> Result<Self, StateError> {
+        let mut cache = match &config.cache.path {
+            Some(path) => TranslationCache::load(path)?,
+            None => TranslationCache::new(),
+        };
+        cache.set_ttl(config.cache.ttl_secs.map(Duration::from_secs));
+        let store = match &config.store.path {
+            Some(path) => TranslationStore::load(path)?,
+            None => TranslationStore::new(),
+        };
+        let overrides = match &config.overrides.path {
+            Some(path) => TranslationStore::load(path)?,
+            None => TranslationStore::new(),
+        };
+        let shakespeare_wrapper = config.shakespeare_wrapper();
+        let pokemon_wrapper = config.pokemon_wrapper();
+
+        let warmup = if config.warmup.enabled {
+            Some(Warmup::new(
+                &config.warmup,
+                pokemon_wrapper.clone(),
+                shakespeare_wrapper.clone(),
+                cache.clone(),
+            )?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            config: Arc::new(config.clone()),
+            shakespeare_wrapper,
+            pokemon_wrapper,
+            cache,
+            store,
+            overrides,
+            warmup,
+            metrics: Metrics::new(),
+            species_names: Arc::default(),
+            daily_pokemon: Arc::default(),
+            sprites: Arc::default(),
+        })
+    }
+
+    /// Get the name and translated description of the pokemon of the current day (UTC), the
+    /// same for every client, going through the pokédex in order.
+    pub async fn pokemon_of_the_day(&self) -> Result<(String, String), WrapperError> {
+        let day = now() / DAY_SECS;
+        // held while looking for it, so the clients waiting for a new day share the calls
+        let mut daily_pokemon = self.daily_pokemon.lock().await;
+        if let Some((found_day, name, description)) = &*daily_pokemon {
+            if *found_day == day {
+                return Ok((name.clone(), description.clone()));
+            }
+        }
+
+        let count = self.pokemon_wrapper.list_species(0, 1).await?.count;
+        let name = if count > 0 {
+            self.pokemon_wrapper
+                .list_species(day as usize % count, 1)
+                .await?
+                .results
+                .into_iter()
+                .next()
+                .map(|species| species.name)
+        } else {
+            None
+        };
+        let name = name.ok_or_else(|| WrapperError::NotFound {
+            upstream: Upstream::Pokemon,
+            url: POKEMON_SERVICE_PATH.to_string(),
+        })?;
+
+        let record = self.description(&name).await?;
+        *daily_pokemon = Some((day, name.clone(), record.translation.clone()));
+        Ok((name, record.translation))
+    }
+
+    /// Get the names of every species, listed once a day at most.
+    pub async fn species_names(&self) -> Result<Arc<Vec<String>>, WrapperError> {
+        // held while listing, so concurrent searches wait for the same call
+        let mut species_names = self.species_names.lock().await;
+        if let Some((listed_at, names)) = &*species_names {
+            if listed_at.elapsed() < SPECIES_TTL {
+                return Ok(names.clone());
+            }
+        }
+
+        let page = self.pokemon_wrapper.list_species(0, SPECIES_LIMIT).await?;
+        let names: Arc<Vec<String>> = Arc::new(
+            page.results
+                .into_iter()
+                .map(|species| species.name)
+                .collect(),
+        );
+        *species_names = Some((Instant::now(), names.clone()));
+        Ok(names)
+    }
+
+    /// Get the url of the pokemon sprite, asking the pokemon api only the first time. Only the
+    /// existing pokemons are kept, so there are as many sprites as pokemons at most.
+    pub async fn sprite(&self, pokemon_name: &str) -> Result<Option<String>, WrapperError> {
+        let pokemon_name = normalize_name(pokemon_name);
+        if let Some(sprite) = self
+            .sprites
+            .read()
+            .expect("sprites lock poisoned")
+            .get(&pokemon_name)
+        {
+            return Ok(sprite.clone());
+        }
+
+        let sprite = self.pokemon_wrapper.get_sprite(&pokemon_name).await?;
+        self.sprites
+            .write()
+            .expect("sprites lock poisoned")
+            .insert(pokemon_name, sprite.clone());
+        Ok(sprite)
+    }
+
+    /// Get the description of the pokemon in Shakespeare's style, from the cache if possible.
+    pub async fn translated_description(
+        &self,
+        pokemon_name: &str,
+    ) -> Result<CacheEntry, WrapperError> {
+        let pokemon_name = &normalize_name(pokemon_name);
+        if let Some(entry) = self.cache.get(pokemon_name) {
+            return Ok(entry);
+        }
+
+        let species = self.pokemon_wrapper.get_species(pokemon_name).await?;
+        let translation = self
+            .shakespeare_wrapper
+            .get_translation(&species.description)
+            .await?;
+
+        Ok(self
+            .cache
+            .insert_species(pokemon_name, &species, &translation))
+    }
+
+    /// Get the manual translation of the pokemon if any, otherwise the machine one.
+    pub async fn description(&self, pokemon_name: &str) -> Result<Record, WrapperError> {
+        let pokemon_name = &normalize_name(pokemon_name);
+        if let Some(record) = self.overrides.get(pokemon_name) {
+            return Ok(record);
+        }
+
+        let entry = self.translated_description(pokemon_name).await?;
+        Ok(entry.into_record(pokemon_name))
+    }
 }
 
 pub async fn build(
     shakespeare_wrapper: ShakespeareWrapper,
     pokemon_wrapper: PokemonWrapper,
 ) -> Server<State> {
-    let state = State {
-        shakespeare_wrapper,
-        pokemon_wrapper,
-    };
+    build_with_state(State::new(shakespeare_wrapper, pokemon_wrapper)).await
+}
 
+pub async fn build_with_state(state: State) -> Server<State> {
+    let admin_auth = AdminAuthMiddleware::new(state.config.admin.token.clone());
+    let cors = state.config.cors.clone();
+    let v1_deprecation = DeprecationMiddleware::new(&state.config.versions.v1);
+    let v2_deprecation = DeprecationMiddleware::new(&state.config.versions.v2);
     let mut app = tide::with_state(state);
 
-    // health check
-    app.at("/health_check").get(health_check::health_check);
+    // The preflight requests are answered before routing, so every route supports them.
+    if cors.enabled {
+        app.with(cors.middleware());
+    }
+
+    for &(method, path) in ROUTES {
+        let mut route = app.at(path);
+        if path.starts_with("/admin/") {
+            route.with(admin_auth.clone());
+        } else if path.starts_with("/v2/") {
+            route.with(v2_deprecation.clone());
+        } else if path.starts_with("/v1/") || path.starts_with("/pokemon/") {
+            route.with(v1_deprecation.clone());
+        }
 
-    // pokemon api route
-    app.at("/pokemon/:pokemon_name").get(pokemon::get);
+        match (method, path) {
+            (Method::Get, "/") => route.method(method, ui::index),
+            (Method::Get, "/search") => route.method(method, ui::search),
+            (Method::Get, "/pokedex/:pokemon_name") => route.method(method, ui::pokemon),
+            (Method::Get, "/static/style.css") => route.method(method, ui::style),
+            (Method::Get, "/static/docs.js") => route.method(method, docs::docs_script),
+            (Method::Get, "/openapi.json") => route.method(method, docs::openapi),
+            (Method::Get, "/docs") => route.method(method, docs::docs),
+            (Method::Get, "/health_check") => route.method(method, health_check::health_check),
+            (Method::Get, "/metrics") => route.method(method, metrics::metrics),
+            (Method::Get, "/pokemon/:pokemon_name" | "/v1/pokemon/:pokemon_name") => {
+                route.method(method, pokemon::get)
+            }
+            (Method::Post, "/pokemon/batch" | "/v1/pokemon/batch") => {
+                route.method(method, pokemon::batch)
+            }
+            (Method::Get, "/pokemon/batch/stream" | "/v1/pokemon/batch/stream") => {
+                route.method(method, pokemon::batch_stream)
+            }
+            (Method::Get, "/v2/pokemon/:pokemon_name") => route.method(method, pokemon::get_v2),
+            (Method::Post, "/translate") => route.method(method, translate::post),
+            (Method::Get, "/graphql") => route.method(method, graphql::graphiql),
+            (Method::Post, "/graphql") => route.method(method, graphql::post),
+            (Method::Get, "/ws") => route.method(method, WebSocket::new(ws::session)),
+            (Method::Get, "/admin/config") => route.method(method, admin::config),
+            (Method::Get, "/admin/upstreams") => route.method(method, admin::upstreams),
+            (Method::Delete, "/admin/cache") => route.method(method, admin::clear_cache),
+            (Method::Delete, "/admin/cache/:pokemon_name") => {
+                route.method(method, admin::clear_cache_entry)
+            }
+            (Method::Get, "/admin/warmup") => route.method(method, admin::warmup),
+            (Method::Get, "/admin/export") => route.method(method, admin::export),
+            (Method::Post, "/admin/overrides") => route.method(method, admin::import),
+            _ => unreachable!("no handler for {} {}", method, path),
+        };
+    }
 
     app
 }
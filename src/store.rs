@@ -0,0 +1,637 @@
+//! Record of every translation served, to be exported without calling the upstream apis.
+//!
+//! The same store keeps the manual translations imported by the content team, which are served
+//! instead of the machine ones.
+
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+
+use async_std::channel::{self, Receiver, Sender};
+use async_std::sync::Mutex as AsyncMutex;
+use serde::{Deserialize, Serialize};
+
+use crate::cache::now;
+use crate::wrappers::pokemon::normalize_name;
+
+#[derive(thiserror::Error, Debug)]
+pub enum StoreError {
+    #[error("Can't read store file {path}: {source}")]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("Invalid record in store file {path} line {line}: {source}")]
+    Parse {
+        path: PathBuf,
+        line: usize,
+        source: serde_json::Error,
+    },
+
+    #[error("Invalid import row {line}: {reason}")]
+    Import { line: usize, reason: String },
+
+    #[error("Can't write store file {path}: {source}")]
+    Write {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+}
+
+/// Where a translation comes from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    /// Translated by the funtranslations api.
+    #[default]
+    Machine,
+    /// Curated by hand and imported.
+    Manual,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Record {
+    pub name: String,
+    /// Original description, as returned by the pokemon api, can be empty for manual ones.
+    #[serde(default)]
+    pub description: String,
+    /// Description in Shakespeare's style.
+    pub translation: String,
+    #[serde(default)]
+    pub source: Source,
+    /// Unix timestamp (seconds) of when the translation was recorded.
+    pub recorded_at: u64,
+    /// Pokédex number, if known when recorded.
+    #[serde(default)]
+    pub id: Option<u32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExportFormat {
+    Jsonl,
+    Csv,
+    Markdown,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Jsonl => "application/x-ndjson",
+            ExportFormat::Csv => "text/csv",
+            ExportFormat::Markdown => "text/markdown",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImportFormat {
+    Jsonl,
+    Csv,
+}
+
+impl ImportFormat {
+    /// Guess the format from the file extension, JSON Lines unless it's `.csv`.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("csv") => ImportFormat::Csv,
+            _ => ImportFormat::Jsonl,
+        }
+    }
+}
+
+impl FromStr for ImportFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "jsonl" => Ok(ImportFormat::Jsonl),
+            "csv" => Ok(ImportFormat::Csv),
+            _ => Err(format!("Unsupported format {}, use jsonl or csv", format)),
+        }
+    }
+}
+
+/// Row of an import file, the original description is optional.
+#[derive(Deserialize)]
+struct ImportRow {
+    name: String,
+    translation: String,
+    #[serde(default)]
+    description: String,
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "jsonl" => Ok(ExportFormat::Jsonl),
+            "csv" => Ok(ExportFormat::Csv),
+            "markdown" | "md" => Ok(ExportFormat::Markdown),
+            _ => Err(format!(
+                "Unsupported format {}, use jsonl, csv or markdown",
+                format
+            )),
+        }
+    }
+}
+
+/// Latest translation of each pokemon, by name.
+///
+/// Clones share the same records. When created with `load` every new record is appended to the
+/// file as a JSON line, the last line of a pokemon wins when the file is loaded again.
+#[derive(Clone, Debug, Default)]
+pub struct TranslationStore {
+    records: Arc<RwLock<BTreeMap<String, Record>>>,
+    file: Option<StoreFile>,
+    /// Held while importing, so the imports reach the file and the records in the same order.
+    importing: Arc<AsyncMutex<()>>,
+}
+
+/// File of a persisted store.
+#[derive(Clone, Debug)]
+struct StoreFile {
+    path: PathBuf,
+    /// Feeds the writing task, in the order the records changed in memory.
+    appends: Sender<Append>,
+}
+
+/// Records to append to the file, with where to report once they are written.
+#[derive(Debug)]
+struct Append {
+    records: Vec<Record>,
+    written: Option<Sender<io::Result<()>>>,
+}
+
+impl TranslationStore {
+    /// Create an in-memory store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a store persisted in `path`, loading the records already in the file.
+    pub fn load(path: &Path) -> Result<Self, StoreError> {
+        let mut records = BTreeMap::new();
+
+        if path.exists() {
+            let read_error = |source| StoreError::Read {
+                path: path.to_path_buf(),
+                source,
+            };
+            let file = std::fs::File::open(path).map_err(read_error)?;
+
+            for (index, line) in BufReader::new(file).lines().enumerate() {
+                let line = line.map_err(read_error)?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+
+                let record: Record =
+                    serde_json::from_str(&line).map_err(|source| StoreError::Parse {
+                        path: path.to_path_buf(),
+                        line: index + 1,
+                        source,
+                    })?;
+                records.insert(record.name.clone(), record);
+            }
+        }
+
+        let (appends, pending) = channel::unbounded();
+        // the task holds no sender, it ends with the last clone of the store
+        async_std::task::spawn(write_appends(path.to_path_buf(), pending));
+
+        Ok(Self {
+            records: Arc::new(RwLock::new(records)),
+            file: Some(StoreFile {
+                path: path.to_path_buf(),
+                appends,
+            }),
+            importing: Arc::default(),
+        })
+    }
+
+    /// Record a translation, unless it's the one already recorded for the pokemon.
+    pub fn record(
+        &self,
+        name: &str,
+        id: Option<u32>,
+        description: &str,
+        translation: &str,
+        source: Source,
+    ) {
+        self.insert(Record {
+            name: normalize_name(name),
+            description: description.to_string(),
+            translation: translation.to_string(),
+            source,
+            recorded_at: now(),
+            id,
+        });
+    }
+
+    pub fn get(&self, name: &str) -> Option<Record> {
+        self.records
+            .read()
+            .expect("store lock poisoned")
+            .get(&normalize_name(name))
+            .cloned()
+    }
+
+    /// Record the manual translations of a JSON Lines or CSV file with `name` and `translation`
+    /// fields (and optionally `description`), returning how many rows were read.
+    ///
+    /// Nothing is recorded if any row is invalid, the rows are written to the file at once before
+    /// being served.
+    pub async fn import<R>(&self, format: ImportFormat, reader: R) -> Result<usize, StoreError>
+    where
+        R: Read + Send + 'static,
+    {
+        let rows = async_std::task::spawn_blocking(move || match format {
+            ImportFormat::Jsonl => import_jsonl(reader),
+            ImportFormat::Csv => import_csv(reader),
+        })
+        .await?;
+        let imported = rows.len();
+
+        let _importing = self.importing.lock().await;
+        let changed: Vec<Record> = {
+            let records = self.records.read().expect("store lock poisoned");
+            rows.into_iter()
+                .map(|row| Record {
+                    name: normalize_name(&row.name),
+                    description: row.description,
+                    translation: row.translation,
+                    source: Source::Manual,
+                    recorded_at: now(),
+                    id: None,
+                })
+                .filter(|record| !is_recorded(&records, record))
+                .collect()
+        };
+
+        if let Some(file) = &self.file {
+            let (written, done) = channel::bounded(1);
+            let append = Append {
+                records: changed.clone(),
+                written: Some(written),
+            };
+            let write_error = |source| StoreError::Write {
+                path: file.path.clone(),
+                source,
+            };
+            let closed = || io::Error::new(io::ErrorKind::BrokenPipe, "store writer stopped");
+            file.appends
+                .send(append)
+                .await
+                .map_err(|_| write_error(closed()))?;
+            done.recv()
+                .await
+                .unwrap_or_else(|_| Err(closed()))
+                .map_err(write_error)?;
+        }
+
+        let mut records = self.records.write().expect("store lock poisoned");
+        for record in changed {
+            records.insert(record.name.clone(), record);
+        }
+        Ok(imported)
+    }
+
+    pub fn records(&self) -> Vec<Record> {
+        self.records
+            .read()
+            .expect("store lock poisoned")
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.read().expect("store lock poisoned").len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Write every record in the given format, sorted by name.
+    pub fn export<W: Write>(&self, format: ExportFormat, writer: W) -> io::Result<()> {
+        let records = self.records();
+        match format {
+            ExportFormat::Jsonl => export_jsonl(&records, writer),
+            ExportFormat::Csv => export_csv(&records, writer),
+            ExportFormat::Markdown => export_markdown(&records, writer),
+        }
+    }
+
+    /// Wait until the records already changed are written to the file, e.g. before exiting.
+    pub async fn flush(&self) {
+        let file = match &self.file {
+            Some(file) => file,
+            None => return,
+        };
+
+        // the appends are written in order, so this one is written after the pending ones
+        let (written, done) = channel::bounded(1);
+        let append = Append {
+            records: Vec::new(),
+            written: Some(written),
+        };
+        if file.appends.send(append).await.is_ok() {
+            let _ = done.recv().await;
+        }
+    }
+
+    fn insert(&self, record: Record) {
+        let mut records = self.records.write().expect("store lock poisoned");
+        if is_recorded(&records, &record) {
+            return;
+        }
+        records.insert(record.name.clone(), record.clone());
+
+        // queued while holding the lock, so the file gets the changes in the same order
+        if let Some(file) = &self.file {
+            let append = Append {
+                records: vec![record],
+                written: None,
+            };
+            let _ = file.appends.try_send(append);
+        }
+    }
+}
+
+/// Whether the record is the one already recorded for the pokemon, but for the time.
+fn is_recorded(records: &BTreeMap<String, Record>, record: &Record) -> bool {
+    records.get(&record.name).is_some_and(|recorded| {
+        recorded.description == record.description
+            && recorded.translation == record.translation
+            && recorded.source == record.source
+            && recorded.id == record.id
+    })
+}
+
+/// Append the queued records to the file, out of the executor threads.
+async fn write_appends(path: PathBuf, pending: Receiver<Append>) {
+    while let Ok(Append { records, written }) = pending.recv().await {
+        let path = path.clone();
+        let result = async_std::task::spawn_blocking(move || append(&path, &records)).await;
+
+        match written {
+            Some(written) => {
+                let _ = written.send(result).await;
+            }
+            None => {
+                if let Err(e) = result {
+                    tide::log::error!("Error: {}, saving translation record", e);
+                }
+            }
+        }
+    }
+}
+
+/// Append the records to the file as JSON lines, in a single write when they fit the buffer.
+fn append(path: &Path, records: &[Record]) -> io::Result<()> {
+    if records.is_empty() {
+        return Ok(());
+    }
+
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let mut writer = BufWriter::new(file);
+    for record in records {
+        serde_json::to_writer(&mut writer, record)?;
+        writeln!(writer)?;
+    }
+    writer.flush()
+}
+
+fn import_jsonl<R: Read>(reader: R) -> Result<Vec<ImportRow>, StoreError> {
+    let mut rows = Vec::new();
+    for (index, line) in BufReader::new(reader).lines().enumerate() {
+        let import_error = |reason: String| StoreError::Import {
+            line: index + 1,
+            reason,
+        };
+
+        let line = line.map_err(|e| import_error(e.to_string()))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let row = serde_json::from_str(&line).map_err(|e| import_error(e.to_string()))?;
+        rows.push(check_row(row, index + 1)?);
+    }
+    Ok(rows)
+}
+
+fn import_csv<R: Read>(reader: R) -> Result<Vec<ImportRow>, StoreError> {
+    csv::Reader::from_reader(reader)
+        .deserialize()
+        .enumerate()
+        .map(|(index, row)| {
+            // the header is the first line
+            let line = index + 2;
+            let row = row.map_err(|e| StoreError::Import {
+                line,
+                reason: e.to_string(),
+            })?;
+            check_row(row, line)
+        })
+        .collect()
+}
+
+fn check_row(row: ImportRow, line: usize) -> Result<ImportRow, StoreError> {
+    if row.name.trim().is_empty() || row.translation.trim().is_empty() {
+        return Err(StoreError::Import {
+            line,
+            reason: "name and translation can't be empty".to_string(),
+        });
+    }
+    Ok(row)
+}
+
+fn export_jsonl<W: Write>(records: &[Record], mut writer: W) -> io::Result<()> {
+    for record in records {
+        serde_json::to_writer(&mut writer, record)?;
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+fn export_csv<W: Write>(records: &[Record], writer: W) -> io::Result<()> {
+    let mut csv_writer = csv::Writer::from_writer(writer);
+    for record in records {
+        csv_writer.serialize(record).map_err(io::Error::other)?;
+    }
+    csv_writer.flush()
+}
+
+fn export_markdown<W: Write>(records: &[Record], mut writer: W) -> io::Result<()> {
+    // Pipes would break the table and new lines the row.
+    let escape = |text: &str| text.replace('|', "\\|").replace(['\n', '\r'], " ");
+
+    writeln!(
+        writer,
+        "| Name | Description | Translation | Source | Recorded at |"
+    )?;
+    writeln!(writer, "| --- | --- | --- | --- | --- |")?;
+    for record in records {
+        let source = match record.source {
+            Source::Machine => "machine",
+            Source::Manual => "manual",
+        };
+        writeln!(
+            writer,
+            "| {} | {} | {} | {} | {} |",
+            escape(&record.name),
+            escape(&record.description),
+            escape(&record.translation),
+            source,
+            record.recorded_at
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store() -> TranslationStore {
+        let store = TranslationStore::new();
+        store.record(
+            "charizard",
+            Some(6),
+            "Spits fire",
+            "Spits fire, forsooth",
+            Source::Machine,
+        );
+        store.record(
+            "ditto",
+            Some(132),
+            "Can copy | transform",
+            "Can copy, \"transform\"",
+            Source::Machine,
+        );
+        store
+    }
+
+    fn export(store: &TranslationStore, format: ExportFormat) -> String {
+        let mut buffer = Vec::new();
+        store.export(format, &mut buffer).unwrap();
+        String::from_utf8(buffer).unwrap()
+    }
+
+    #[test]
+    fn record_only_changes() {
+        let store = store();
+        let recorded_at = store.records()[0].recorded_at;
+
+        store.record(
+            "charizard",
+            Some(6),
+            "Spits fire",
+            "Spits fire, forsooth",
+            Source::Machine,
+        );
+
+        assert_eq!(2, store.len());
+        assert_eq!(recorded_at, store.records()[0].recorded_at);
+    }
+
+    #[test]
+    fn export_jsonl() {
+        let exported = export(&store(), ExportFormat::Jsonl);
+        let records: Vec<Record> = exported
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        assert_eq!(store().records(), records);
+    }
+
+    #[test]
+    fn export_csv() {
+        let exported = export(&store(), ExportFormat::Csv);
+        let mut lines = exported.lines();
+
+        assert_eq!(
+            Some("name,description,translation,source,recorded_at,id"),
+            lines.next()
+        );
+        assert!(lines
+            .nth(1)
+            .unwrap()
+            .starts_with("ditto,Can copy | transform,\"Can copy, \"\"transform\"\"\",machine,"));
+    }
+
+    #[test]
+    fn export_markdown() {
+        let exported = export(&store(), ExportFormat::Markdown);
+        let lines: Vec<&str> = exported.lines().collect();
+
+        assert_eq!(4, lines.len());
+        assert!(lines[3].starts_with("| ditto | Can copy \\| transform |"));
+    }
+
+    #[async_std::test]
+    async fn persist_records() {
+        let path =
+            std::env::temp_dir().join(format!("poke-spearify-store-{}.jsonl", std::process::id()));
+
+        let store = TranslationStore::load(&path).unwrap();
+        store.record(
+            "charizard",
+            Some(6),
+            "Spits fire",
+            "Spits fire, forsooth",
+            Source::Machine,
+        );
+        store
+            .import(
+                ImportFormat::Jsonl,
+                r#"{"name": "charizard", "translation": "Spits fire, verily"}"#.as_bytes(),
+            )
+            .await
+            .unwrap();
+
+        let reloaded = TranslationStore::load(&path).unwrap();
+        assert_eq!(1, reloaded.len());
+        assert_eq!("Spits fire, verily", reloaded.records()[0].translation);
+        assert_eq!(Source::Manual, reloaded.records()[0].source);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[async_std::test]
+    async fn import_csv() {
+        let store = TranslationStore::new();
+        let content = "name,translation\nCharizard,\"Spits fire, forsooth\"\nditto,Can copy\n";
+
+        assert_eq!(
+            2,
+            store
+                .import(ImportFormat::Csv, content.as_bytes())
+                .await
+                .unwrap()
+        );
+
+        let charizard = store.get("charizard").unwrap();
+        assert_eq!("Spits fire, forsooth", charizard.translation);
+        assert_eq!("", charizard.description);
+        assert_eq!(Source::Manual, charizard.source);
+    }
+
+    #[async_std::test]
+    async fn import_invalid_row() {
+        let store = TranslationStore::new();
+        let content = "name,translation\ncharizard,Spits fire\nditto,\n";
+
+        let error = store.import(ImportFormat::Csv, content.as_bytes()).await;
+
+        assert!(matches!(error, Err(StoreError::Import { line: 3, .. })));
+        assert!(store.is_empty());
+    }
+}
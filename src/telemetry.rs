@@ -0,0 +1,136 @@
+//! OpenTelemetry traces.
+//!
+//! The `tracing` spans (the ones of `TraceMiddleware` and of the upstream calls) are exported
+//! with OTLP over HTTP. The exporter sends the batches with surf, so no other runtime is needed.
+
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_http::{Bytes, HttpClient, HttpError, Request, Response};
+use opentelemetry_otlp::{ExporterBuildError, Protocol, WithExportConfig, WithHttpConfig};
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+use tracing::field::Empty;
+use tracing::{Level, Span, Subscriber};
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::layer::{Layer, SubscriberExt};
+
+use crate::config::{OtlpProtocol, TracingConfig};
+use crate::wrappers::errors::{Upstream, WrapperError};
+
+/// Name of the instrumentation scope of the spans.
+const TRACER_NAME: &str = "poke-spearify";
+
+#[derive(thiserror::Error, Debug)]
+pub enum TelemetryError {
+    #[error("Can't build the OTLP exporter: {0}")]
+    Exporter(#[from] ExporterBuildError),
+
+    #[error("Can't install the tracing subscriber: {0}")]
+    Subscriber(#[from] tracing::subscriber::SetGlobalDefaultError),
+}
+
+/// Provider exporting the spans to the collector of the config, in batches.
+pub fn tracer_provider(config: &TracingConfig) -> Result<SdkTracerProvider, TelemetryError> {
+    let protocol = match config.protocol {
+        OtlpProtocol::HttpProtobuf => Protocol::HttpBinary,
+        OtlpProtocol::HttpJson => Protocol::HttpJson,
+    };
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .with_http_client(SurfClient)
+        .with_endpoint(&config.endpoint)
+        .with_protocol(protocol)
+        .build()?;
+
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            Resource::builder()
+                .with_service_name(config.service_name.clone())
+                .build(),
+        )
+        .build())
+}
+
+/// Export the spans as set in the config, if enabled. The provider must be kept until the
+/// server stops, and shut down to flush the last spans.
+pub fn init(config: &TracingConfig) -> Result<Option<SdkTracerProvider>, TelemetryError> {
+    if !config.enabled {
+        return Ok(None);
+    }
+
+    let provider = tracer_provider(config)?;
+    tracing::subscriber::set_global_default(subscriber(&provider))?;
+    Ok(Some(provider))
+}
+
+/// Subscriber sending the spans of the server to the provider.
+///
+/// The spans of the dependencies are left out: the HTTP client ones would be exported as well,
+/// and its agent thread span would keep the upstream span that started it open forever.
+pub fn subscriber(provider: &SdkTracerProvider) -> impl Subscriber + Send + Sync {
+    let targets = Targets::new()
+        .with_target(env!("CARGO_CRATE_NAME"), Level::INFO)
+        .with_target("tide_tracing", Level::INFO);
+    // without activation the request span isn't started when entered, so `RequestIdMiddleware`
+    // can still make it a child of the span of the caller
+    let layer = tracing_opentelemetry::layer()
+        .with_tracer(provider.tracer(TRACER_NAME))
+        .with_context_activation(false)
+        .with_filter(targets);
+    tracing_subscriber::registry().with(layer)
+}
+
+/// Client span of a call to an upstream api, with the HTTP attributes known before the call.
+pub(crate) fn upstream_span(name: &str, upstream: Upstream, method: &str, url: &str) -> Span {
+    tracing::info_span!(
+        "upstream call",
+        otel.name = name,
+        otel.kind = "client",
+        otel.status_code = Empty,
+        peer.service = upstream.as_str(),
+        http.request.method = method,
+        url.full = url,
+        http.response.status_code = Empty,
+        error.type = Empty,
+    )
+}
+
+/// Record the status code of the response in the current upstream span.
+pub(crate) fn record_status(status: u16) {
+    Span::current().record("http.response.status_code", status);
+}
+
+/// Mark the upstream span as failed, with the code of the error.
+pub(crate) fn record_result<T>(span: &Span, result: &Result<T, WrapperError>) {
+    if let Err(e) = result {
+        span.record("otel.status_code", "ERROR");
+        span.record("error.type", e.code());
+    }
+}
+
+/// OTLP transport over surf.
+#[derive(Debug, Clone, Copy)]
+struct SurfClient;
+
+#[tide::utils::async_trait]
+impl HttpClient for SurfClient {
+    async fn send_bytes(&self, request: Request<Bytes>) -> Result<Response<Bytes>, HttpError> {
+        let (parts, body) = request.into_parts();
+        let method: surf::http::Method = parts
+            .method
+            .as_str()
+            .parse()
+            .map_err(|e: surf::Error| e.into_inner())?;
+        let mut req = surf::Request::new(method, surf::Url::parse(&parts.uri.to_string())?);
+        req.set_body(body.to_vec());
+        for (name, value) in parts.headers.iter() {
+            req.insert_header(name.as_str(), value.to_str()?);
+        }
+
+        let mut res = surf::client().send(req).await.map_err(|e| e.into_inner())?;
+        let body = res.body_bytes().await.map_err(|e| e.into_inner())?;
+        Ok(Response::builder()
+            .status(u16::from(res.status()))
+            .body(Bytes::from(body))?)
+    }
+}
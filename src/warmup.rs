@@ -0,0 +1,391 @@
+//! Gradual pre-translation of the whole pokédex.
+//!
+//! The translation api has a tiny quota, so the scheduler walks the species list translating one
+//! pokemon at a time and never spends more than the hourly/daily budget. The progress is persisted
+//! after every step to resume where it was left after a restart.
+
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_std::sync::Mutex as AsyncMutex;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::cache::{now, write_atomic, TranslationCache};
+use crate::config::WarmupConfig;
+use crate::wrappers::errors::{ErrorKind, WrapperError};
+use crate::wrappers::pokemon::PokemonWrapper;
+use crate::wrappers::shakespeare::ShakespeareWrapper;
+
+/// Number of species fetched at once from the pokemon api.
+const PAGE_SIZE: usize = 50;
+
+const HOUR: u64 = 60 * 60;
+const DAY: u64 = 24 * HOUR;
+
+#[derive(thiserror::Error, Debug)]
+pub enum WarmupError {
+    #[error("Can't read warmup progress file {path}: {source}")]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("Invalid warmup progress file {path}: {source}")]
+    Parse {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+}
+
+/// Translations spent in the current hour and day.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct Usage {
+    pub hour_start: u64,
+    pub hour_count: u32,
+    pub day_start: u64,
+    pub day_count: u32,
+}
+
+impl Usage {
+    /// Start new windows if `now` is past the current ones.
+    fn roll(&mut self, now: u64) {
+        let hour_start = now - now % HOUR;
+        if hour_start != self.hour_start {
+            self.hour_start = hour_start;
+            self.hour_count = 0;
+        }
+
+        let day_start = now - now % DAY;
+        if day_start != self.day_start {
+            self.day_start = day_start;
+            self.day_count = 0;
+        }
+    }
+
+    fn allows(&self, config: &WarmupConfig) -> bool {
+        self.hour_count < config.hourly_budget && self.day_count < config.daily_budget
+    }
+
+    fn record(&mut self) {
+        self.hour_count += 1;
+        self.day_count += 1;
+    }
+
+    pub fn remaining_hourly(&self, config: &WarmupConfig) -> u32 {
+        config.hourly_budget.saturating_sub(self.hour_count)
+    }
+
+    pub fn remaining_daily(&self, config: &WarmupConfig) -> u32 {
+        config.daily_budget.saturating_sub(self.day_count)
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct Progress {
+    /// Position in the species list of the next pokemon to translate.
+    pub offset: usize,
+    /// Number of species, known once the first page is fetched.
+    pub total: Option<usize>,
+    pub translated: usize,
+    /// Pokemons already in the cache.
+    pub skipped: usize,
+    /// Pokemons that can't be translated, e.g. without description.
+    pub failed: usize,
+    pub finished: bool,
+    pub last_error: Option<String>,
+    pub updated_at: u64,
+    pub usage: Usage,
+}
+
+/// Outcome of a scheduler step.
+#[derive(Debug, PartialEq)]
+pub enum Step {
+    Translated(String),
+    Failed(String),
+    /// The budget is spent, nothing is done until the next window.
+    BudgetExhausted,
+    /// The quota left of the api is kept for the requests.
+    QuotaReserved,
+    /// An upstream api is failing, the same pokemon is tried in the next step.
+    Retry,
+    Finished,
+}
+
+#[derive(Clone, Debug)]
+pub struct Warmup {
+    config: WarmupConfig,
+    pokemon_wrapper: PokemonWrapper,
+    shakespeare_wrapper: ShakespeareWrapper,
+    cache: TranslationCache,
+    progress: Arc<Mutex<Progress>>,
+    /// Names of the current page not processed yet, the first one is at `progress.offset`.
+    pending: Arc<Mutex<VecDeque<String>>>,
+    /// Held while saving the progress, so the last update is the one saved.
+    saving: Arc<AsyncMutex<()>>,
+    /// Start of the hour the quota reserve was reached in.
+    reserved_hour: Arc<Mutex<Option<u64>>>,
+}
+
+impl Warmup {
+    /// Create a scheduler, resuming the progress saved in `config.progress_path` if any.
+    pub fn new(
+        config: &WarmupConfig,
+        pokemon_wrapper: PokemonWrapper,
+        shakespeare_wrapper: ShakespeareWrapper,
+        cache: TranslationCache,
+    ) -> Result<Self, WarmupError> {
+        let progress = match &config.progress_path {
+            Some(path) if path.exists() => load_progress(path)?,
+            _ => Progress::default(),
+        };
+
+        Ok(Self {
+            config: config.clone(),
+            pokemon_wrapper,
+            shakespeare_wrapper,
+            cache,
+            progress: Arc::new(Mutex::new(progress)),
+            pending: Arc::new(Mutex::new(VecDeque::new())),
+            saving: Arc::default(),
+            reserved_hour: Arc::default(),
+        })
+    }
+
+    pub fn config(&self) -> &WarmupConfig {
+        &self.config
+    }
+
+    pub fn progress(&self) -> Progress {
+        let mut progress = self
+            .progress
+            .lock()
+            .expect("progress lock poisoned")
+            .clone();
+        progress.usage.roll(now());
+        progress
+    }
+
+    /// Run the scheduler in the background until the whole pokédex is translated.
+    pub fn spawn(self) -> async_std::task::JoinHandle<()> {
+        async_std::task::spawn(async move {
+            let interval = Duration::from_secs(self.config.interval_secs);
+            loop {
+                let step = self.step().await;
+                tide::log::debug!("Warmup step: {:?}", step);
+                if step == Step::Finished {
+                    tide::log::info!("Warmup finished");
+                    break;
+                }
+                async_std::task::sleep(interval).await;
+            }
+        })
+    }
+
+    /// Translate the next pokemon not in the cache, if the budget allows it.
+    pub async fn step(&self) -> Step {
+        loop {
+            if self.progress().finished {
+                return Step::Finished;
+            }
+
+            let name = match self.next_pending().await {
+                Ok(Some(name)) => name,
+                Ok(None) => {
+                    self.update(|progress| progress.finished = true).await;
+                    return Step::Finished;
+                }
+                Err(e) => {
+                    self.update(|progress| progress.last_error = Some(e.to_string()))
+                        .await;
+                    return Step::Retry;
+                }
+            };
+
+            if self.cache.peek(&name).is_some() {
+                self.advance(|progress| progress.skipped += 1).await;
+                continue;
+            }
+
+            let mut allowed = false;
+            self.update(|progress| {
+                progress.usage.roll(now());
+                allowed = progress.usage.allows(&self.config);
+            })
+            .await;
+            if !allowed {
+                return Step::BudgetExhausted;
+            }
+
+            // only known after a translation, from the headers of the api
+            let quota_remaining = self.shakespeare_wrapper.quota_remaining().get();
+            if quota_remaining
+                .is_some_and(|remaining| remaining <= f64::from(self.config.quota_reserve))
+            {
+                let now = now();
+                let hour_start = now - now % HOUR;
+                let mut reserved_hour = self.reserved_hour.lock().expect("reserved lock poisoned");
+                match *reserved_hour {
+                    // one translation a hour refreshes the quota, it may be reset since
+                    Some(hour) if hour != hour_start => *reserved_hour = None,
+                    _ => {
+                        *reserved_hour = Some(hour_start);
+                        return Step::QuotaReserved;
+                    }
+                }
+            }
+
+            let species = match self.pokemon_wrapper.get_species(&name).await {
+                Ok(species) => species,
+                Err(e) => return self.failure(name, e).await,
+            };
+
+            self.update(|progress| progress.usage.record()).await;
+            return match self
+                .shakespeare_wrapper
+                .get_translation(&species.description)
+                .await
+            {
+                Ok(translation) => {
+                    self.cache.insert_species(&name, &species, &translation);
+                    self.advance(|progress| progress.translated += 1).await;
+                    Step::Translated(name)
+                }
+                Err(e) if e.kind() == ErrorKind::TooManyRequests => {
+                    // Our usage is out of sync with the api, give up until the next hour.
+                    let hourly_budget = self.config.hourly_budget;
+                    self.update(|progress| {
+                        progress.usage.hour_count = hourly_budget;
+                        progress.last_error = Some(e.to_string());
+                    })
+                    .await;
+                    Step::BudgetExhausted
+                }
+                Err(e) => self.failure(name, e).await,
+            };
+        }
+    }
+
+    /// First pending name, fetching the page at the current offset if needed.
+    async fn next_pending(&self) -> Result<Option<String>, WrapperError> {
+        if let Some(name) = self.pending.lock().expect("pending lock poisoned").front() {
+            return Ok(Some(name.clone()));
+        }
+
+        let offset = self.progress().offset;
+        let page = self.pokemon_wrapper.list_species(offset, PAGE_SIZE).await?;
+        self.update(|progress| progress.total = Some(page.count))
+            .await;
+
+        let mut pending = self.pending.lock().expect("pending lock poisoned");
+        pending.extend(page.results.into_iter().map(|species| species.name));
+        Ok(pending.front().cloned())
+    }
+
+    async fn failure(&self, name: String, e: WrapperError) -> Step {
+        let last_error = Some(format!("{}: {}", name, e));
+        match e.kind() {
+            ErrorKind::NetworkError
+            | ErrorKind::Timeout
+            | ErrorKind::UpstreamError
+            | ErrorKind::TooManyRequests => {
+                self.update(|progress| progress.last_error = last_error)
+                    .await;
+                Step::Retry
+            }
+            _ => {
+                self.advance(|progress| {
+                    progress.failed += 1;
+                    progress.last_error = last_error;
+                })
+                .await;
+                Step::Failed(name)
+            }
+        }
+    }
+
+    /// Move to the next pokemon.
+    async fn advance<F: FnOnce(&mut Progress)>(&self, f: F) {
+        self.pending
+            .lock()
+            .expect("pending lock poisoned")
+            .pop_front();
+        self.update(|progress| {
+            progress.offset += 1;
+            f(progress);
+        })
+        .await;
+    }
+
+    /// Change the progress and persist it, a failure to save it is only logged.
+    async fn update<F: FnOnce(&mut Progress)>(&self, f: F) {
+        {
+            let mut progress = self.progress.lock().expect("progress lock poisoned");
+            f(&mut progress);
+            progress.updated_at = now();
+        }
+
+        let path = match &self.config.progress_path {
+            Some(path) => path.clone(),
+            None => return,
+        };
+        // the progress is copied once saving, so a later update is never overwritten
+        let _saving = self.saving.lock().await;
+        let content = {
+            let progress = self.progress.lock().expect("progress lock poisoned");
+            serde_json::to_string(&*progress).expect("progress is serializable")
+        };
+        let saved =
+            async_std::task::spawn_blocking(move || write_atomic(&path, content.as_bytes())).await;
+        if let Err(e) = saved {
+            tide::log::error!("Error: {}, saving warmup progress", e);
+        }
+    }
+}
+
+fn load_progress(path: &Path) -> Result<Progress, WarmupError> {
+    let content = std::fs::read_to_string(path).map_err(|source| WarmupError::Read {
+        path: path.to_path_buf(),
+        source,
+    })?;
+
+    serde_json::from_str(&content).map_err(|source| WarmupError::Parse {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn usage_windows() {
+        let config = WarmupConfig {
+            hourly_budget: 2,
+            daily_budget: 3,
+            ..WarmupConfig::default()
+        };
+        let mut usage = Usage::default();
+
+        usage.roll(DAY);
+        usage.record();
+        usage.record();
+        assert!(!usage.allows(&config));
+
+        // next hour, only the daily budget is left
+        usage.roll(DAY + HOUR);
+        assert!(usage.allows(&config));
+        usage.record();
+        assert!(!usage.allows(&config));
+        assert_eq!(1, usage.remaining_hourly(&config));
+        assert_eq!(0, usage.remaining_daily(&config));
+
+        // next day
+        usage.roll(2 * DAY);
+        assert_eq!(2, usage.remaining_hourly(&config));
+        assert_eq!(3, usage.remaining_daily(&config));
+    }
+}
@@ -1,17 +1,181 @@
-#[derive(thiserror::Error, Debug, PartialEq)]
-pub enum WrapperError {
-    #[error("Not Found")]
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+use std::sync::{Arc, Mutex};
+
+pub type BoxError = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+/// Third party api called by a wrapper.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Upstream {
+    Pokemon,
+    Shakespeare,
+}
+
+impl Upstream {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Upstream::Pokemon => "pokeapi",
+            Upstream::Shakespeare => "funtranslations",
+        }
+    }
+}
+
+impl Display for Upstream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Kind of a `WrapperError`, without any of the context it carries.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
     NotFound,
+    NoDescription,
+    ParsingError,
+    TooManyRequests,
+    NetworkError,
+    Timeout,
+    UpstreamError,
+    UnexpectedError,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum WrapperError {
+    #[error("Not Found in {upstream} ({url})")]
+    NotFound { upstream: Upstream, url: String },
 
     #[error("Pokemon without en description")]
     NoDescription,
 
-    #[error("Parsing error")]
-    ParsingError,
+    #[error("Parsing error of {upstream} response ({url})")]
+    ParsingError {
+        upstream: Upstream,
+        url: String,
+        #[source]
+        source: BoxError,
+    },
 
-    #[error("Too many requests")]
-    TooManyRequests,
+    #[error("Too many requests to {upstream} ({url})")]
+    TooManyRequests { upstream: Upstream, url: String },
 
-    #[error("Unexpected API Error")]
-    UnexpectedError,
+    #[error("Network error calling {upstream} ({url})")]
+    NetworkError {
+        upstream: Upstream,
+        url: String,
+        #[source]
+        source: BoxError,
+    },
+
+    #[error("Timeout calling {upstream} ({url})")]
+    Timeout { upstream: Upstream, url: String },
+
+    #[error("{upstream} responded with status {status} ({url})")]
+    UpstreamError {
+        upstream: Upstream,
+        url: String,
+        status: u16,
+    },
+
+    #[error("Unexpected {upstream} API Error ({url})")]
+    UnexpectedError { upstream: Upstream, url: String },
+}
+
+impl WrapperError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            WrapperError::NotFound { .. } => ErrorKind::NotFound,
+            WrapperError::NoDescription => ErrorKind::NoDescription,
+            WrapperError::ParsingError { .. } => ErrorKind::ParsingError,
+            WrapperError::TooManyRequests { .. } => ErrorKind::TooManyRequests,
+            WrapperError::NetworkError { .. } => ErrorKind::NetworkError,
+            WrapperError::Timeout { .. } => ErrorKind::Timeout,
+            WrapperError::UpstreamError { .. } => ErrorKind::UpstreamError,
+            WrapperError::UnexpectedError { .. } => ErrorKind::UnexpectedError,
+        }
+    }
+
+    /// The api that failed, if the error comes from a call to a third party.
+    pub fn upstream(&self) -> Option<Upstream> {
+        match self {
+            WrapperError::NotFound { upstream, .. }
+            | WrapperError::ParsingError { upstream, .. }
+            | WrapperError::TooManyRequests { upstream, .. }
+            | WrapperError::NetworkError { upstream, .. }
+            | WrapperError::Timeout { upstream, .. }
+            | WrapperError::UpstreamError { upstream, .. }
+            | WrapperError::UnexpectedError { upstream, .. } => Some(*upstream),
+            WrapperError::NoDescription => None,
+        }
+    }
+
+    /// The url of the failed call, if the error comes from a call to a third party.
+    pub fn url(&self) -> Option<&str> {
+        match self {
+            WrapperError::NotFound { url, .. }
+            | WrapperError::ParsingError { url, .. }
+            | WrapperError::TooManyRequests { url, .. }
+            | WrapperError::NetworkError { url, .. }
+            | WrapperError::Timeout { url, .. }
+            | WrapperError::UpstreamError { url, .. }
+            | WrapperError::UnexpectedError { url, .. } => Some(url),
+            WrapperError::NoDescription => None,
+        }
+    }
+
+    /// Stable machine-readable code for the error, safe to expose to clients.
+    pub fn code(&self) -> &'static str {
+        match self.kind() {
+            ErrorKind::NotFound => "not_found",
+            ErrorKind::NoDescription => "no_description",
+            ErrorKind::ParsingError => "parsing_error",
+            ErrorKind::TooManyRequests => "too_many_requests",
+            ErrorKind::NetworkError => "network_error",
+            ErrorKind::Timeout => "timeout",
+            ErrorKind::UpstreamError => "upstream_error",
+            ErrorKind::UnexpectedError => "unexpected_error",
+        }
+    }
+}
+
+/// Source errors can't be compared, two errors are equal if they share kind and context.
+impl PartialEq for WrapperError {
+    fn eq(&self, other: &Self) -> bool {
+        let status = |e: &WrapperError| match e {
+            WrapperError::UpstreamError { status, .. } => Some(*status),
+            _ => None,
+        };
+
+        self.kind() == other.kind()
+            && self.upstream() == other.upstream()
+            && self.url() == other.url()
+            && status(self) == status(other)
+    }
+}
+
+/// Number of errors returned by a wrapper, by error code. Clones share the same counts.
+#[derive(Clone, Debug, Default)]
+pub struct ErrorCounters {
+    counts: Arc<Mutex<BTreeMap<&'static str, u64>>>,
+}
+
+impl ErrorCounters {
+    /// Count the error of `result`, if any, and return it untouched.
+    pub fn track<T>(&self, result: Result<T, WrapperError>) -> Result<T, WrapperError> {
+        if let Err(e) = &result {
+            *self
+                .counts
+                .lock()
+                .expect("error counters lock poisoned")
+                .entry(e.code())
+                .or_insert(0) += 1;
+        }
+        result
+    }
+
+    pub fn counts(&self) -> BTreeMap<&'static str, u64> {
+        self.counts
+            .lock()
+            .expect("error counters lock poisoned")
+            .clone()
+    }
 }
@@ -1,3 +1,8 @@
+use std::time::Duration;
+
 pub mod errors;
 pub mod pokemon;
 pub mod shakespeare;
+
+/// Max time to wait for a third party api before giving up.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
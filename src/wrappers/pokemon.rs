@@ -1,11 +1,63 @@
-use crate::wrappers::errors::WrapperError;
+use crate::correlation;
+use crate::metrics::Histogram;
+use crate::telemetry;
+use crate::wrappers::errors::{ErrorCounters, Upstream, WrapperError};
+use crate::wrappers::DEFAULT_TIMEOUT;
+use serde::de::DeserializeOwned;
 use serde::Deserialize;
+use std::time::{Duration, Instant};
+use tracing::Instrument;
 
-const POKEMON_SERVICE_URI: &str = "https://pokeapi.co";
+pub const POKEMON_SERVICE_URI: &str = "https://pokeapi.co";
 pub const POKEMON_SERVICE_PATH: &str = "/api/v2/pokemon-species/";
+pub const POKEMON_FORMS_PATH: &str = "/api/v2/pokemon/";
+pub const EVOLUTION_CHAIN_PATH: &str = "/api/v2/evolution-chain/";
+/// Language of the descriptions.
+pub const DESCRIPTION_LANGUAGE: &str = "en";
 #[derive(Deserialize, Debug)]
 struct Pokemon {
+    id: u32,
     flavor_text_entries: Vec<FlavorText>,
+    #[serde(default)]
+    evolution_chain: Option<ApiResource>,
+}
+
+/// Link to another resource of the api.
+#[derive(Deserialize, Debug)]
+struct ApiResource {
+    url: String,
+}
+
+impl ApiResource {
+    /// Id of the resource, the last segment of its url.
+    fn id(&self) -> &str {
+        self.url
+            .trim_end_matches('/')
+            .rsplit('/')
+            .next()
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct EvolutionChain {
+    chain: ChainLink,
+}
+
+#[derive(Deserialize, Debug)]
+struct ChainLink {
+    species: NamedResource,
+    evolves_to: Vec<ChainLink>,
+}
+
+impl ChainLink {
+    /// Species of the chain, each one before its evolutions.
+    fn names(self, names: &mut Vec<String>) {
+        names.push(self.species.name);
+        for link in self.evolves_to {
+            link.names(names);
+        }
+    }
 }
 
 #[derive(Deserialize, Debug)]
@@ -19,29 +71,209 @@ struct Language {
     name: String,
 }
 
+/// Default form of a pokemon, only the sprites are used.
+#[derive(Deserialize, Debug)]
+struct PokemonForm {
+    sprites: Sprites,
+}
+
+#[derive(Deserialize, Debug)]
+struct Sprites {
+    front_default: Option<String>,
+}
+
+/// Species of a pokemon, with its pokédex number.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Species {
+    pub id: u32,
+    pub description: String,
+}
+
+/// A page of the species list.
+#[derive(Deserialize, Debug)]
+pub struct SpeciesPage {
+    /// Total number of species.
+    pub count: usize,
+    pub results: Vec<NamedResource>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct NamedResource {
+    pub name: String,
+}
+
+/// Name of a pokemon as used in the lookups and as key of the cache and the stores: the api
+/// only knows the lowercase names.
+pub fn normalize_name(pokemon_name: &str) -> String {
+    pokemon_name.trim().to_lowercase()
+}
+
 #[derive(Clone, Debug)]
 pub struct PokemonWrapper {
     base_url: String,
+    timeout: Duration,
+    errors: ErrorCounters,
+    latency: Histogram,
 }
 
 impl PokemonWrapper {
     pub fn new() -> Self {
         Self {
             base_url: POKEMON_SERVICE_URI.to_string(),
+            timeout: DEFAULT_TIMEOUT,
+            errors: ErrorCounters::default(),
+            latency: Histogram::default(),
         }
     }
 
     pub fn with_base_url(base_url: &str) -> Self {
         Self {
             base_url: base_url.to_string(),
+            timeout: DEFAULT_TIMEOUT,
+            errors: ErrorCounters::default(),
+            latency: Histogram::default(),
+        }
+    }
+
+    /// Set the max time to wait for the pokemon api.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Errors returned so far, shared by all the clones of the wrapper.
+    pub fn errors(&self) -> &ErrorCounters {
+        &self.errors
+    }
+
+    /// Latency of the calls so far, shared by all the clones of the wrapper.
+    pub fn latency(&self) -> &Histogram {
+        &self.latency
+    }
+
+    /// Url of the pokemon under `path`. Pokemon names only have letters, digits and dashes, any
+    /// other name isn't found without calling the api, so it can't change the path or the query.
+    fn pokemon_url(&self, path: &str, pokemon_name: &str) -> Result<String, WrapperError> {
+        let pokemon_name = normalize_name(pokemon_name);
+        let url = format!("{}{}{}", self.base_url, path, pokemon_name);
+        let valid = !pokemon_name.is_empty()
+            && pokemon_name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+        if valid {
+            Ok(url)
+        } else {
+            Err(WrapperError::NotFound {
+                upstream: Upstream::Pokemon,
+                url,
+            })
         }
     }
 
+    /// Record the latency and the error, if any, of a call started at `started`.
+    fn track<T>(
+        &self,
+        started: Instant,
+        result: Result<T, WrapperError>,
+    ) -> Result<T, WrapperError> {
+        self.latency.observe(started.elapsed());
+        self.errors.track(result)
+    }
+
     pub async fn get_description(&self, pokemon_name: &str) -> Result<String, WrapperError> {
-        let pokemon_url = format!("{}{}{}", self.base_url, POKEMON_SERVICE_PATH, pokemon_name);
-        let pokemon = fetch_pokemon(&pokemon_url).await?;
-        let description = pokemon.get_description()?;
-        Ok(description)
+        self.get_species(pokemon_name)
+            .await
+            .map(|species| species.description)
+    }
+
+    /// Pokédex number and description of the pokemon.
+    pub async fn get_species(&self, pokemon_name: &str) -> Result<Species, WrapperError> {
+        let started = Instant::now();
+        let pokemon_url = self.pokemon_url(POKEMON_SERVICE_PATH, pokemon_name)?;
+        let species = async {
+            let span =
+                telemetry::upstream_span("fetch_pokemon", Upstream::Pokemon, "GET", &pokemon_url);
+            let pokemon = async_std::future::timeout(self.timeout, fetch_pokemon(&pokemon_url))
+                .instrument(span.clone())
+                .await
+                .map_err(|_| {
+                    tide::log::error!("Timeout getting response from Pokemon API");
+                    WrapperError::Timeout {
+                        upstream: Upstream::Pokemon,
+                        url: pokemon_url.clone(),
+                    }
+                })
+                .and_then(|pokemon| pokemon);
+            telemetry::record_result(&span, &pokemon);
+            let pokemon = pokemon?;
+            Ok(Species {
+                id: pokemon.id,
+                description: pokemon.get_description()?,
+            })
+        };
+        self.track(started, species.await)
+    }
+
+    /// Url of the front sprite of the pokemon default form, if it has one.
+    pub async fn get_sprite(&self, pokemon_name: &str) -> Result<Option<String>, WrapperError> {
+        let started = Instant::now();
+        let form_url = self.pokemon_url(POKEMON_FORMS_PATH, pokemon_name)?;
+        let form = self.fetch::<PokemonForm>(&form_url).await;
+        self.track(started, form)
+            .map(|form| form.sprites.front_default)
+    }
+
+    /// Species of the evolution chain of the pokemon, including itself, in evolution order.
+    pub async fn get_evolutions(&self, pokemon_name: &str) -> Result<Vec<String>, WrapperError> {
+        let started = Instant::now();
+        let pokemon_url = self.pokemon_url(POKEMON_SERVICE_PATH, pokemon_name)?;
+        let pokemon_name = normalize_name(pokemon_name);
+        let evolutions = async {
+            let pokemon: Pokemon = self.fetch(&pokemon_url).await?;
+            let chain = match pokemon.evolution_chain {
+                Some(chain) => chain,
+                None => return Ok(vec![pokemon_name.clone()]),
+            };
+
+            // the chain is fetched from our base url, not the one in the response
+            let chain_url = format!("{}{}{}/", self.base_url, EVOLUTION_CHAIN_PATH, chain.id());
+            let chain: EvolutionChain = self.fetch(&chain_url).await?;
+            let mut names = Vec::new();
+            chain.chain.names(&mut names);
+            Ok(names)
+        };
+        self.track(started, evolutions.await)
+    }
+
+    /// List `limit` species starting at `offset`, in pokédex order.
+    pub async fn list_species(
+        &self,
+        offset: usize,
+        limit: usize,
+    ) -> Result<SpeciesPage, WrapperError> {
+        let started = Instant::now();
+        let species_url = format!(
+            "{}{}?offset={}&limit={}",
+            self.base_url, POKEMON_SERVICE_PATH, offset, limit
+        );
+        let page = self.fetch(&species_url).await;
+        self.track(started, page)
+    }
+
+    /// Get a resource of the api, failing after the timeout.
+    async fn fetch<T: DeserializeOwned>(&self, url: &str) -> Result<T, WrapperError> {
+        async_std::future::timeout(self.timeout, fetch_json(url))
+            .await
+            .map_err(|_| {
+                tide::log::error!("Timeout getting response from Pokemon API");
+                WrapperError::Timeout {
+                    upstream: Upstream::Pokemon,
+                    url: url.to_string(),
+                }
+            })?
     }
 }
 
@@ -56,7 +288,7 @@ impl Pokemon {
         let description = self
             .flavor_text_entries
             .iter()
-            .find(|desc| desc.language.name == "en")
+            .find(|desc| desc.language.name == DESCRIPTION_LANGUAGE)
             .ok_or(WrapperError::NoDescription)?;
 
         // The api description is multiline with `\n` and also contains `\u{c}` sequence
@@ -72,42 +304,74 @@ impl Pokemon {
 }
 
 async fn fetch_pokemon(pokemon_url: &str) -> Result<Pokemon, WrapperError> {
-    let mut res = surf::get(pokemon_url).await.map_err(|e| {
+    fetch_json(pokemon_url).await
+}
+
+async fn fetch_json<T: DeserializeOwned>(url: &str) -> Result<T, WrapperError> {
+    let mut req = surf::get(url);
+    for (name, value) in correlation::outgoing_headers() {
+        req = req.header(name, value);
+    }
+    let mut res = req.await.map_err(|e| {
         tide::log::error!("Error: {}, getting response from Pokemon API", e);
-        WrapperError::UnexpectedError
+        WrapperError::NetworkError {
+            upstream: Upstream::Pokemon,
+            url: url.to_string(),
+            source: e.into_inner().into(),
+        }
     })?;
 
     let status: u16 = res.status().into();
+    telemetry::record_status(status);
     match status {
         200 => {
-            let pokemon: Pokemon = res.body_json().await.map_err(|e| {
-                tide::log::error!("Error: {}, deserializing response to Pokemon", e);
-                WrapperError::ParsingError
+            let value: T = res.body_json().await.map_err(|e| {
+                tide::log::error!(
+                    "Error: {}, deserializing response to {}",
+                    e,
+                    std::any::type_name::<T>()
+                );
+                WrapperError::ParsingError {
+                    upstream: Upstream::Pokemon,
+                    url: url.to_string(),
+                    source: e.into_inner().into(),
+                }
             })?;
-            Ok(pokemon)
+            Ok(value)
         }
-        404 => Err(WrapperError::NotFound),
-        _ => Err(WrapperError::UnexpectedError),
+        404 => Err(WrapperError::NotFound {
+            upstream: Upstream::Pokemon,
+            url: url.to_string(),
+        }),
+        429 => Err(WrapperError::TooManyRequests {
+            upstream: Upstream::Pokemon,
+            url: url.to_string(),
+        }),
+        _ => Err(WrapperError::UpstreamError {
+            upstream: Upstream::Pokemon,
+            url: url.to_string(),
+            status,
+        }),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::wrappers::errors::ErrorKind;
 
-    use wiremock::matchers::{method, path};
+    use wiremock::matchers::{method, path, query_param};
     use wiremock::{Mock, MockServer, ResponseTemplate};
 
     #[async_std::test]
     async fn fetch_pokemon_description() -> std::result::Result<(), tide::Error> {
         let mock_server = MockServer::start().await;
         let existing_pokemon = "charizard";
-        let mock_path = format!("{}{}", POKEMON_SERVICE_PATH, existing_pokemon.to_string());
+        let mock_path = format!("{}{}", POKEMON_SERVICE_PATH, existing_pokemon);
 
         const CHARIZARD_CONTENT: &str = include_str!("../../samples/charizard.json");
 
-        let charizard_as_json: serde_json::Value =
-            serde_json::from_str(&CHARIZARD_CONTENT).unwrap();
+        let charizard_as_json: serde_json::Value = serde_json::from_str(CHARIZARD_CONTENT).unwrap();
         let response = ResponseTemplate::new(200).set_body_json(charizard_as_json);
 
         Mock::given(method("GET"))
@@ -129,11 +393,7 @@ mod tests {
     async fn fetch_non_existing_pokemon_description() -> std::result::Result<(), tide::Error> {
         let mock_server = MockServer::start().await;
         let non_existing_pokemon = "nocharizard";
-        let mock_path = format!(
-            "{}{}",
-            POKEMON_SERVICE_PATH,
-            non_existing_pokemon.to_string()
-        );
+        let mock_path = format!("{}{}", POKEMON_SERVICE_PATH, non_existing_pokemon);
 
         Mock::given(method("GET"))
             .and(path(&mock_path))
@@ -144,9 +404,15 @@ mod tests {
         let pokemon_url = format!("{}{}", &mock_server.uri(), &mock_path);
         let pokemon = fetch_pokemon(&pokemon_url).await;
 
-        assert_eq!(true, pokemon.is_err());
+        assert!(pokemon.is_err());
 
-        assert_eq!(WrapperError::NotFound, pokemon.err().unwrap());
+        assert_eq!(
+            WrapperError::NotFound {
+                upstream: Upstream::Pokemon,
+                url: pokemon_url
+            },
+            pokemon.err().unwrap()
+        );
 
         Ok(())
     }
@@ -155,12 +421,11 @@ mod tests {
     async fn fetch_pokemon_without_description() -> std::result::Result<(), tide::Error> {
         let mock_server = MockServer::start().await;
         let existing_pokemon = "charizard";
-        let mock_path = format!("{}{}", POKEMON_SERVICE_PATH, existing_pokemon.to_string());
+        let mock_path = format!("{}{}", POKEMON_SERVICE_PATH, existing_pokemon);
 
         const CHARIZARD_CONTENT: &str = include_str!("../../samples/charizard_without_desc.json");
 
-        let charizard_as_json: serde_json::Value =
-            serde_json::from_str(&CHARIZARD_CONTENT).unwrap();
+        let charizard_as_json: serde_json::Value = serde_json::from_str(CHARIZARD_CONTENT).unwrap();
         let response = ResponseTemplate::new(200).set_body_json(charizard_as_json);
 
         Mock::given(method("GET"))
@@ -174,7 +439,7 @@ mod tests {
 
         let description = pokemon.get_description();
 
-        assert_eq!(true, description.is_err());
+        assert!(description.is_err());
 
         assert_eq!(WrapperError::NoDescription, description.err().unwrap());
 
@@ -185,7 +450,7 @@ mod tests {
     async fn fetch_pokemon_parse_error() -> std::result::Result<(), tide::Error> {
         let mock_server = MockServer::start().await;
         let existing_pokemon = "charizard";
-        let mock_path = format!("{}{}", POKEMON_SERVICE_PATH, existing_pokemon.to_string());
+        let mock_path = format!("{}{}", POKEMON_SERVICE_PATH, existing_pokemon);
 
         const CHARIZARD_CONTENT: &str = include_str!("../../samples/charizard_bad.json");
 
@@ -200,9 +465,171 @@ mod tests {
         let pokemon_url = format!("{}{}", &mock_server.uri(), &mock_path);
         let pokemon_result = fetch_pokemon(&pokemon_url).await;
 
-        assert_eq!(true, pokemon_result.is_err());
+        assert!(pokemon_result.is_err());
+
+        let error = pokemon_result.err().unwrap();
+        assert_eq!(ErrorKind::ParsingError, error.kind());
+        assert!(std::error::Error::source(&error).is_some());
+
+        Ok(())
+    }
 
-        assert_eq!(WrapperError::ParsingError, pokemon_result.err().unwrap());
+    #[async_std::test]
+    async fn fetch_pokemon_upstream_error() -> std::result::Result<(), tide::Error> {
+        let mock_server = MockServer::start().await;
+        let existing_pokemon = "charizard";
+        let mock_path = format!("{}{}", POKEMON_SERVICE_PATH, existing_pokemon);
+
+        Mock::given(method("GET"))
+            .and(path(&mock_path))
+            .respond_with(ResponseTemplate::new(503))
+            .mount(&mock_server)
+            .await;
+
+        let pokemon_url = format!("{}{}", &mock_server.uri(), &mock_path);
+        let pokemon_result = fetch_pokemon(&pokemon_url).await;
+
+        assert_eq!(
+            WrapperError::UpstreamError {
+                upstream: Upstream::Pokemon,
+                url: pokemon_url,
+                status: 503
+            },
+            pokemon_result.err().unwrap()
+        );
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn fetch_pokemon_network_error() -> std::result::Result<(), tide::Error> {
+        // nothing listens on the discard port.
+        let pokemon_url = format!("http://127.0.0.1:9{}charizard", POKEMON_SERVICE_PATH);
+        let pokemon_result = fetch_pokemon(&pokemon_url).await;
+
+        let error = pokemon_result.err().unwrap();
+        assert_eq!(ErrorKind::NetworkError, error.kind());
+        assert!(std::error::Error::source(&error).is_some());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn list_species_page() -> std::result::Result<(), tide::Error> {
+        let mock_server = MockServer::start().await;
+
+        let response = ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "count": 1025,
+            "next": "https://pokeapi.co/api/v2/pokemon-species/?offset=2&limit=2",
+            "previous": null,
+            "results": [
+                { "name": "bulbasaur", "url": "https://pokeapi.co/api/v2/pokemon-species/1/" },
+                { "name": "ivysaur", "url": "https://pokeapi.co/api/v2/pokemon-species/2/" }
+            ]
+        }));
+
+        Mock::given(method("GET"))
+            .and(path(POKEMON_SERVICE_PATH))
+            .and(query_param("offset", "0"))
+            .and(query_param("limit", "2"))
+            .respond_with(response)
+            .mount(&mock_server)
+            .await;
+
+        let pokemon_wrapper = PokemonWrapper::with_base_url(&mock_server.uri());
+        let page = pokemon_wrapper.list_species(0, 2).await?;
+
+        assert_eq!(1025, page.count);
+        let names: Vec<&str> = page.results.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(vec!["bulbasaur", "ivysaur"], names);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn get_sprite() -> std::result::Result<(), tide::Error> {
+        let mock_server = MockServer::start().await;
+
+        let response = ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "id": 6,
+            "name": "charizard",
+            "sprites": {
+                "front_default": "https://raw.githubusercontent.com/PokeAPI/sprites/master/sprites/pokemon/6.png",
+                "back_default": null
+            }
+        }));
+
+        Mock::given(method("GET"))
+            .and(path(format!("{}charizard", POKEMON_FORMS_PATH)))
+            .respond_with(response)
+            .mount(&mock_server)
+            .await;
+
+        let pokemon_wrapper = PokemonWrapper::with_base_url(&mock_server.uri());
+        let sprite = pokemon_wrapper.get_sprite("charizard").await?;
+
+        assert_eq!(
+            Some("https://raw.githubusercontent.com/PokeAPI/sprites/master/sprites/pokemon/6.png"),
+            sprite.as_deref()
+        );
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn get_evolutions() -> std::result::Result<(), tide::Error> {
+        let mock_server = MockServer::start().await;
+
+        const CHARIZARD_CONTENT: &str = include_str!("../../samples/charizard.json");
+        let charizard_as_json: serde_json::Value = serde_json::from_str(CHARIZARD_CONTENT).unwrap();
+        Mock::given(method("GET"))
+            .and(path(format!("{}charizard", POKEMON_SERVICE_PATH)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(charizard_as_json))
+            .mount(&mock_server)
+            .await;
+
+        let chain = serde_json::json!({
+            "id": 2,
+            "chain": {
+                "species": { "name": "charmander", "url": "https://pokeapi.co/api/v2/pokemon-species/4/" },
+                "evolves_to": [{
+                    "species": { "name": "charmeleon", "url": "https://pokeapi.co/api/v2/pokemon-species/5/" },
+                    "evolves_to": [{
+                        "species": { "name": "charizard", "url": "https://pokeapi.co/api/v2/pokemon-species/6/" },
+                        "evolves_to": []
+                    }]
+                }]
+            }
+        });
+        Mock::given(method("GET"))
+            .and(path(format!("{}2/", EVOLUTION_CHAIN_PATH)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(chain))
+            .mount(&mock_server)
+            .await;
+
+        let pokemon_wrapper = PokemonWrapper::with_base_url(&mock_server.uri());
+        let evolutions = pokemon_wrapper.get_evolutions("charizard").await?;
+
+        assert_eq!(vec!["charmander", "charmeleon", "charizard"], evolutions);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn invalid_names_not_found() -> std::result::Result<(), tide::Error> {
+        // arrenge, any call would fail with a network error
+        let pokemon_wrapper = PokemonWrapper::with_base_url("http://127.0.0.1:9");
+
+        // act
+        let species = pokemon_wrapper.get_species("../pokemon/1").await;
+        let sprite = pokemon_wrapper.get_sprite("a?b").await;
+        let evolutions = pokemon_wrapper.get_evolutions("  ").await;
+
+        // assert
+        assert_eq!(ErrorKind::NotFound, species.unwrap_err().kind());
+        assert_eq!(ErrorKind::NotFound, sprite.unwrap_err().kind());
+        assert_eq!(ErrorKind::NotFound, evolutions.unwrap_err().kind());
+        assert!(pokemon_wrapper.errors().counts().is_empty());
 
         Ok(())
     }
@@ -1,9 +1,17 @@
-use crate::wrappers::errors::WrapperError;
+use crate::correlation;
+use crate::metrics::{Gauge, Histogram};
+use crate::telemetry;
+use crate::wrappers::errors::{ErrorCounters, Upstream, WrapperError};
+use crate::wrappers::DEFAULT_TIMEOUT;
 use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tracing::Instrument;
 
-const TRANSLATION_SERVICE_URI: &str = "https://api.funtranslations.com";
+pub const TRANSLATION_SERVICE_URI: &str = "https://api.funtranslations.com";
 pub const TRANSLATION_SHAKESPEARE_PATH: &str = "/translate/shakespeare.json";
 const TRANSLATION_API_KEY_HEADER: &str = "X-FunTranslations-Api-Secret";
+/// Translations left in the current window of the api key, or of the client ip without key.
+const RATE_LIMIT_REMAINING_HEADER: &str = "X-RateLimit-Remaining";
 
 #[derive(Deserialize, Debug)]
 struct Translation {
@@ -30,6 +38,10 @@ struct InputText {
 pub struct ShakespeareWrapper {
     base_url: String,
     api_key: Option<String>,
+    timeout: Duration,
+    errors: ErrorCounters,
+    latency: Histogram,
+    quota_remaining: Gauge,
 }
 
 impl ShakespeareWrapper {
@@ -37,6 +49,10 @@ impl ShakespeareWrapper {
         Self {
             base_url: TRANSLATION_SERVICE_URI.to_string(),
             api_key: None,
+            timeout: DEFAULT_TIMEOUT,
+            errors: ErrorCounters::default(),
+            latency: Histogram::default(),
+            quota_remaining: Gauge::default(),
         }
     }
 
@@ -44,6 +60,10 @@ impl ShakespeareWrapper {
         Self {
             base_url: base_url.to_string(),
             api_key: None,
+            timeout: DEFAULT_TIMEOUT,
+            errors: ErrorCounters::default(),
+            latency: Histogram::default(),
+            quota_remaining: Gauge::default(),
         }
     }
 
@@ -52,11 +72,61 @@ impl ShakespeareWrapper {
         self.api_key = api_key;
     }
 
+    /// Set the max time to wait for the translation api.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Errors returned so far, shared by all the clones of the wrapper.
+    pub fn errors(&self) -> &ErrorCounters {
+        &self.errors
+    }
+
+    /// Latency of the calls so far, shared by all the clones of the wrapper.
+    pub fn latency(&self) -> &Histogram {
+        &self.latency
+    }
+
+    /// Translations left, as reported by the last response of the api.
+    pub fn quota_remaining(&self) -> &Gauge {
+        &self.quota_remaining
+    }
+
     pub async fn get_translation(&self, translation_input: &str) -> Result<String, WrapperError> {
+        let started = Instant::now();
         let tranlation_request_url = format!("{}{}", self.base_url, TRANSLATION_SHAKESPEARE_PATH);
-        let translated_text =
-            fetch_translation(&tranlation_request_url, translation_input, &self.api_key).await?;
-        Ok(translated_text)
+        let span = telemetry::upstream_span(
+            "fetch_translation",
+            Upstream::Shakespeare,
+            "POST",
+            &tranlation_request_url,
+        );
+        let translated_text = async_std::future::timeout(
+            self.timeout,
+            fetch_translation(
+                &tranlation_request_url,
+                translation_input,
+                &self.api_key,
+                &self.quota_remaining,
+            ),
+        )
+        .instrument(span.clone())
+        .await
+        .map_err(|_| {
+            tide::log::error!("Timeout getting response from translation service");
+            WrapperError::Timeout {
+                upstream: Upstream::Shakespeare,
+                url: tranlation_request_url.clone(),
+            }
+        })
+        .and_then(|translated_text| translated_text);
+        telemetry::record_result(&span, &translated_text);
+        self.latency.observe(started.elapsed());
+        self.errors.track(translated_text)
     }
 }
 
@@ -70,52 +140,86 @@ async fn fetch_translation(
     translation_url: &str,
     translation_input: &str,
     api_key: &Option<String>,
+    quota_remaining: &Gauge,
 ) -> Result<String, WrapperError> {
     let text = InputText {
         text: translation_input.to_string(),
     };
 
     let mut req = surf::post(translation_url)
-        .body(surf::Body::from_json(&text).map_err(|_| {
-            tide::log::error!("Error encoding request");
-            WrapperError::UnexpectedError
+        .body(surf::Body::from_json(&text).map_err(|e| {
+            tide::log::error!("Error: {}, encoding request", e);
+            WrapperError::UnexpectedError {
+                upstream: Upstream::Shakespeare,
+                url: translation_url.to_string(),
+            }
         })?)
         .build();
 
     if let Some(api_key) = api_key {
         req.set_header(TRANSLATION_API_KEY_HEADER, api_key.to_string());
     }
+    for (name, value) in correlation::outgoing_headers() {
+        req.set_header(name, value);
+    }
 
     let client = surf::client();
-    let mut res = client.send(req).await.map_err(|_| {
-        tide::log::error!("Error returned by translation service");
-        WrapperError::UnexpectedError
+    let mut res = client.send(req).await.map_err(|e| {
+        tide::log::error!("Error: {}, getting response from translation service", e);
+        WrapperError::NetworkError {
+            upstream: Upstream::Shakespeare,
+            url: translation_url.to_string(),
+            source: e.into_inner().into(),
+        }
     })?;
 
+    if let Some(remaining) = res
+        .header(RATE_LIMIT_REMAINING_HEADER)
+        .and_then(|remaining| remaining.last().as_str().trim().parse::<u32>().ok())
+    {
+        quota_remaining.set(f64::from(remaining));
+    }
+
     let status: u16 = res.status().into();
+    telemetry::record_status(status);
     match status {
         200 => {
             let translation: Translation = res.body_json().await.map_err(|e| {
                 tide::log::error!("Error: {}, deserializing response to Translation", e);
-                WrapperError::UnexpectedError
+                WrapperError::ParsingError {
+                    upstream: Upstream::Shakespeare,
+                    url: translation_url.to_string(),
+                    source: e.into_inner().into(),
+                }
             })?;
 
             match translation.success.total {
                 1 => Ok(translation.contents.translated),
                 _ => {
                     tide::log::error!("Error returned by translation service");
-                    Err(WrapperError::UnexpectedError)
+                    Err(WrapperError::UnexpectedError {
+                        upstream: Upstream::Shakespeare,
+                        url: translation_url.to_string(),
+                    })
                 }
             }
         }
-        429 => Err(WrapperError::TooManyRequests),
-        _ => Err(WrapperError::UnexpectedError),
+        429 => Err(WrapperError::TooManyRequests {
+            upstream: Upstream::Shakespeare,
+            url: translation_url.to_string(),
+        }),
+        _ => Err(WrapperError::UpstreamError {
+            upstream: Upstream::Shakespeare,
+            url: translation_url.to_string(),
+            status,
+        }),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::wrappers::errors::ErrorKind;
 
     use wiremock::matchers::{method, path};
     use wiremock::{Mock, MockServer, ResponseTemplate};
@@ -126,7 +230,7 @@ mod tests {
 
         const TRASLATION_CONTENT: &str = include_str!("../../samples/shakespeare_translation.json");
         let translation_as_json: serde_json::Value =
-            serde_json::from_str(&TRASLATION_CONTENT).unwrap();
+            serde_json::from_str(TRASLATION_CONTENT).unwrap();
         let response = ResponseTemplate::new(200).set_body_json(&translation_as_json);
 
         Mock::given(method("POST"))
@@ -140,6 +244,7 @@ mod tests {
             &request_url,
             "Rust, a language empowering everyone to build reliable and efficient software.",
             &None,
+            &Gauge::default(),
         )
         .await?;
 
@@ -166,13 +271,18 @@ mod tests {
             &request_url,
             "Rust, a language empowering everyone to build reliable and efficient software.",
             &None,
+            &Gauge::default(),
         )
         .await;
 
-        assert_eq!(true, translation_response.is_err());
+        assert!(translation_response.is_err());
 
         assert_eq!(
-            WrapperError::UnexpectedError,
+            WrapperError::UpstreamError {
+                upstream: Upstream::Shakespeare,
+                url: request_url,
+                status: 500
+            },
             translation_response.err().unwrap()
         );
 
@@ -198,15 +308,15 @@ mod tests {
             &request_url,
             "Rust, a language empowering everyone to build reliable and efficient software.",
             &None,
+            &Gauge::default(),
         )
         .await;
 
-        assert_eq!(true, translation_response.is_err());
+        assert!(translation_response.is_err());
 
-        assert_eq!(
-            WrapperError::UnexpectedError,
-            translation_response.err().unwrap()
-        );
+        let error = translation_response.err().unwrap();
+        assert_eq!(ErrorKind::ParsingError, error.kind());
+        assert!(std::error::Error::source(&error).is_some());
 
         Ok(())
     }
@@ -0,0 +1,116 @@
+// Viewer of the OpenAPI document, served with the api so /docs works without any CDN.
+(function () {
+  "use strict";
+
+  var METHODS = ["get", "post", "put", "patch", "delete"];
+
+  function element(name, className, text) {
+    var node = document.createElement(name);
+    if (className) {
+      node.className = className;
+    }
+    if (text !== undefined) {
+      node.textContent = text;
+    }
+    return node;
+  }
+
+  function schemaName(schema) {
+    if (!schema) {
+      return "";
+    }
+    if (schema.$ref) {
+      return schema.$ref.split("/").pop();
+    }
+    if (schema.type === "array") {
+      return schemaName(schema.items) + "[]";
+    }
+    return schema.type || "";
+  }
+
+  function operation(path, method, op) {
+    var section = element("section", "operation");
+    var title = element("h3");
+    title.appendChild(element("span", "method " + method, method.toUpperCase()));
+    title.appendChild(element("code", null, " " + path));
+    section.appendChild(title);
+    if (op.summary) {
+      section.appendChild(element("p", null, op.summary));
+    }
+    if (op.description) {
+      section.appendChild(element("p", null, op.description));
+    }
+
+    var params = op.parameters || [];
+    if (params.length) {
+      var list = element("ul", "parameters");
+      params.forEach(function (param) {
+        var item = element("li");
+        item.appendChild(element("code", null, param.name));
+        item.appendChild(
+          document.createTextNode(
+            " (" + param.in + (param.required ? ", required" : "") + ") " +
+              (param.description || "")
+          )
+        );
+        list.appendChild(item);
+      });
+      section.appendChild(list);
+    }
+
+    var body = op.requestBody && op.requestBody.content;
+    if (body) {
+      Object.keys(body).forEach(function (type) {
+        section.appendChild(
+          element("p", null, "Body: " + type + " " + schemaName(body[type].schema))
+        );
+      });
+    }
+
+    var responses = element("ul", "responses");
+    Object.keys(op.responses || {}).forEach(function (status) {
+      var response = op.responses[status];
+      var types = Object.keys(response.content || {});
+      var schemas = types.map(function (type) {
+        return type + " " + schemaName(response.content[type].schema);
+      });
+      responses.appendChild(
+        element(
+          "li",
+          null,
+          status + " " + (response.description || "") +
+            (schemas.length ? " — " + schemas.join(", ") : "")
+        )
+      );
+    });
+    section.appendChild(responses);
+    return section;
+  }
+
+  function render(spec) {
+    var main = document.querySelector("main");
+    main.textContent = "";
+    main.appendChild(element("h1", null, spec.info.title + " " + spec.info.version));
+    if (spec.info.description) {
+      main.appendChild(element("p", null, spec.info.description));
+    }
+    Object.keys(spec.paths).forEach(function (path) {
+      METHODS.forEach(function (method) {
+        var op = spec.paths[path][method];
+        if (op) {
+          main.appendChild(operation(path, method, op));
+        }
+      });
+    });
+  }
+
+  var main = document.querySelector("main");
+  fetch(main.getAttribute("data-spec-url"))
+    .then(function (res) {
+      return res.json();
+    })
+    .then(render)
+    .catch(function (e) {
+      main.textContent = "Can't load the OpenAPI document: " + e;
+    });
+})();
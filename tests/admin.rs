@@ -0,0 +1,157 @@
+use std::sync::Arc;
+
+use poke_spearify::config::Config;
+use poke_spearify::controllers::admin::{ClearCacheResponse, UpstreamsStatus};
+use poke_spearify::server::{self, State};
+use poke_spearify::wrappers::pokemon;
+use poke_spearify::wrappers::shakespeare;
+
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// State with the admin api enabled, and unreachable upstreams.
+fn admin_state(token: Option<&str>) -> State {
+    let shakespeare_wrapper = shakespeare::ShakespeareWrapper::with_base_url("http://127.0.0.1:9");
+    let pokemon_wrapper = pokemon::PokemonWrapper::with_base_url("http://127.0.0.1:9");
+    let mut state = State::new(shakespeare_wrapper, pokemon_wrapper);
+
+    let mut config = Config::default();
+    config.admin.token = token.map(str::to_string);
+    state.config = Arc::new(config);
+    state
+}
+
+#[async_std::test]
+async fn unauthorized_admin_requests() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge
+    let client =
+        surf::Client::with_http_client(server::build_with_state(admin_state(Some("secret"))).await);
+    let disabled =
+        surf::Client::with_http_client(server::build_with_state(admin_state(None)).await);
+
+    // act
+    let without_token = client.get("https://example.com/admin/config").await?;
+    let wrong_token = client
+        .get("https://example.com/admin/config")
+        .header("Authorization", "Bearer wrong")
+        .await?;
+    let admin_disabled = disabled
+        .get("https://example.com/admin/config")
+        .header("Authorization", "Bearer secret")
+        .await?;
+
+    // assert
+    assert_eq!(401, without_token.status());
+    assert_eq!(
+        Some("Bearer"),
+        without_token
+            .header("WWW-Authenticate")
+            .map(|header| header.as_str())
+    );
+    assert_eq!(401, wrong_token.status());
+    assert_eq!(401, admin_disabled.status());
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn effective_config() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge
+    let app = server::build_with_state(admin_state(Some("secret"))).await;
+
+    // act
+    let mut res = surf::Client::with_http_client(app)
+        .get("https://example.com/admin/config")
+        .header("Authorization", "Bearer secret")
+        .await?;
+
+    // assert
+    assert_eq!(200, res.status());
+    let config: serde_json::Value = res.body_json().await?;
+    assert_eq!(5000, config["server"]["port"]);
+    assert!(config["admin"].get("token").is_none());
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn upstream_error_counters() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge wrappers mocks
+    let mock_pokemon_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&mock_pokemon_server)
+        .await;
+
+    let shakespeare_wrapper = shakespeare::ShakespeareWrapper::with_base_url("http://127.0.0.1:9");
+    let pokemon_wrapper = pokemon::PokemonWrapper::with_base_url(&mock_pokemon_server.uri());
+    let mut state = State::new(shakespeare_wrapper, pokemon_wrapper);
+    state.config = admin_state(Some("secret")).config;
+    let client = surf::Client::with_http_client(server::build_with_state(state).await);
+
+    // act
+    for _ in 0..2 {
+        let res = client
+            .get("https://example.com/pokemon/nocharizard")
+            .await?;
+        assert_eq!(404, res.status());
+    }
+    let mut res = client
+        .get("https://example.com/admin/upstreams")
+        .header("Authorization", "Bearer secret")
+        .await?;
+
+    // assert
+    assert_eq!(200, res.status());
+    let status: UpstreamsStatus = res.body_json().await?;
+    assert_eq!(mock_pokemon_server.uri(), status.pokemon.base_url);
+    assert_eq!(Some(&2), status.pokemon.errors.get("not_found"));
+    assert_eq!("http://127.0.0.1:9", status.translation.base_url);
+    assert!(status.translation.errors.is_empty());
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn clear_cache() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge
+    let state = admin_state(Some("secret"));
+    state
+        .cache
+        .insert("charizard", "Spits fire", "Spits fire, forsooth");
+    state.cache.insert("ditto", "Can copy", "Can copy, verily");
+    state.cache.insert("mew", "Rare", "Rare, forsooth");
+    let client = surf::Client::with_http_client(server::build_with_state(state.clone()).await);
+
+    // act
+    let removed = client
+        .delete("https://example.com/admin/cache/charizard")
+        .header("Authorization", "Bearer secret")
+        .await?;
+    let not_cached = client
+        .delete("https://example.com/admin/cache/charizard")
+        .header("Authorization", "Bearer secret")
+        .await?;
+    let mut cleared = client
+        .delete("https://example.com/admin/cache")
+        .header("Authorization", "Bearer secret")
+        .await?;
+
+    // assert
+    assert_eq!(204, removed.status());
+    assert_eq!(404, not_cached.status());
+    assert_eq!(200, cleared.status());
+    let cleared: ClearCacheResponse = cleared.body_json().await?;
+    assert_eq!(2, cleared.removed);
+    assert!(state.cache.is_empty());
+
+    Ok(())
+}
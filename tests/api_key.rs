@@ -0,0 +1,124 @@
+use std::time::Duration;
+
+use poke_spearify::config::ApiKey;
+use poke_spearify::middlewares::api_key::ApiKeyMiddleware;
+use poke_spearify::middlewares::extension_types::ClientId;
+use poke_spearify::server;
+use poke_spearify::wrappers::pokemon;
+use poke_spearify::wrappers::shakespeare;
+use tide::{Request, Server};
+
+fn api_key(key: &str, client: &str, quota: Option<u32>) -> ApiKey {
+    ApiKey {
+        key: key.to_string(),
+        client: client.to_string(),
+        quota,
+    }
+}
+
+/// App with the api keys `team-key` (unlimited) and `scraper-key` (2 requests per hour), and a
+/// `/whoami` route returning the client of the key.
+async fn app() -> Server<server::State> {
+    let shakespeare_wrapper = shakespeare::ShakespeareWrapper::with_base_url("http://127.0.0.1:9");
+    let pokemon_wrapper = pokemon::PokemonWrapper::with_base_url("http://127.0.0.1:9");
+    let mut app = server::build(shakespeare_wrapper, pokemon_wrapper).await;
+
+    app.with(ApiKeyMiddleware::new(
+        vec![
+            api_key("team-key", "content-team", None),
+            api_key("scraper-key", "scraper", Some(2)),
+        ],
+        Duration::from_secs(60 * 60),
+    ));
+    app.at("/whoami")
+        .get(|req: Request<server::State>| async move {
+            Ok(req
+                .ext::<ClientId>()
+                .map(|client| client.to_string())
+                .unwrap_or_default())
+        });
+
+    app
+}
+
+#[async_std::test]
+async fn valid_api_key() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge
+    let client = surf::Client::with_http_client(app().await);
+
+    // act
+    let mut from_header = client
+        .get("https://example.com/whoami")
+        .header("X-Api-Key", "team-key")
+        .await?;
+    let mut from_query = client
+        .get("https://example.com/whoami?api_key=team-key")
+        .await?;
+
+    // assert
+    assert_eq!(200, from_header.status());
+    assert_eq!("content-team", from_header.body_string().await?);
+    assert_eq!(200, from_query.status());
+    assert_eq!("content-team", from_query.body_string().await?);
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn invalid_api_key() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge
+    let client = surf::Client::with_http_client(app().await);
+
+    // act
+    let missing = client.get("https://example.com/pokemon/charizard").await?;
+    let unknown = client
+        .get("https://example.com/pokemon/charizard")
+        .header("X-Api-Key", "stolen-key")
+        .await?;
+    let health_check = client.get("https://example.com/health_check").await?;
+
+    // assert
+    assert_eq!(401, missing.status());
+    assert_eq!(401, unknown.status());
+    assert_eq!(200, health_check.status());
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn api_key_over_quota() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge
+    let client = surf::Client::with_http_client(app().await);
+
+    // act
+    let mut statuses = Vec::new();
+    for _ in 0..3 {
+        let res = client
+            .get("https://example.com/whoami")
+            .header("X-Api-Key", "scraper-key")
+            .await?;
+        statuses.push(res.status() as u16);
+    }
+    let over_quota = client
+        .get("https://example.com/whoami")
+        .header("X-Api-Key", "scraper-key")
+        .await?;
+    let other_key = client
+        .get("https://example.com/whoami")
+        .header("X-Api-Key", "team-key")
+        .await?;
+
+    // assert
+    assert_eq!(vec![200, 200, 429], statuses);
+    assert_eq!(429, over_quota.status());
+    assert!(over_quota.header("Retry-After").is_some());
+    assert_eq!(200, other_key.status());
+
+    Ok(())
+}
@@ -0,0 +1,150 @@
+use tide::prelude::json;
+
+use poke_spearify::server;
+use poke_spearify::wrappers::pokemon;
+use poke_spearify::wrappers::shakespeare;
+
+use poke_spearify::controllers::pokemon::BatchResponse;
+
+use assert_json_diff::assert_json_eq;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[async_std::test]
+async fn batch_pokemon() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge wrappers mocks
+    let mock_pokemon_server = MockServer::start().await;
+
+    const CHARIZARD_CONTENT: &str = include_str!("../samples/charizard.json");
+
+    let charizard_as_json: serde_json::Value = serde_json::from_str(CHARIZARD_CONTENT).unwrap();
+    let response = ResponseTemplate::new(200).set_body_json(charizard_as_json);
+
+    Mock::given(method("GET"))
+        .and(path(format!("{}charizard", pokemon::POKEMON_SERVICE_PATH)))
+        .respond_with(response)
+        .expect(1)
+        .mount(&mock_pokemon_server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path(format!(
+            "{}nocharizard",
+            pokemon::POKEMON_SERVICE_PATH
+        )))
+        .respond_with(ResponseTemplate::new(404))
+        .expect(1)
+        .mount(&mock_pokemon_server)
+        .await;
+
+    let mock_translation_server = MockServer::start().await;
+
+    const TRASLATION_CONTENT: &str = include_str!("../samples/shakespeare_translation.json");
+    let translation_as_json: serde_json::Value = serde_json::from_str(TRASLATION_CONTENT).unwrap();
+    let response = ResponseTemplate::new(200).set_body_json(&translation_as_json);
+
+    // repeated pokemons are only translated once
+    Mock::given(method("POST"))
+        .and(path(shakespeare::TRANSLATION_SHAKESPEARE_PATH))
+        .respond_with(response)
+        .expect(1)
+        .mount(&mock_translation_server)
+        .await;
+
+    // act
+    let shakespeare_wrapper =
+        shakespeare::ShakespeareWrapper::with_base_url(&mock_translation_server.uri());
+    let pokemon_wrapper = pokemon::PokemonWrapper::with_base_url(&mock_pokemon_server.uri());
+    let app = server::build(shakespeare_wrapper, pokemon_wrapper).await;
+
+    let mut res = surf::Client::with_http_client(app)
+        .post("https://example.com/pokemon/batch")
+        .body(json!({ "names": ["charizard", "nocharizard", "charizard"] }))
+        .await?;
+
+    // assert
+    assert_eq!(200, res.status());
+
+    let batch_response: BatchResponse = res.body_json().await?;
+    assert_json_eq!(
+        batch_response,
+        json!({
+            "results": [
+                {
+                    "name": "charizard",
+                    "status": 200,
+                    "description": "Rust, a language empowering everyone to buildeth reliable and efficient software."
+                },
+                {
+                    "name": "nocharizard",
+                    "status": 404,
+                    "error": "Not Found",
+                    "code": "not_found"
+                },
+                {
+                    "name": "charizard",
+                    "status": 200,
+                    "description": "Rust, a language empowering everyone to buildeth reliable and efficient software."
+                }
+            ]
+        })
+    );
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn empty_batch() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    let shakespeare_wrapper = shakespeare::ShakespeareWrapper::new();
+    let pokemon_wrapper = pokemon::PokemonWrapper::new();
+    let app = server::build(shakespeare_wrapper, pokemon_wrapper).await;
+
+    let res = surf::Client::with_http_client(app)
+        .post("https://example.com/pokemon/batch")
+        .body(json!({ "names": [] }))
+        .await?;
+
+    assert_eq!(400, res.status());
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn blank_names_in_batch() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge wrappers mocks, nothing is looked up
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(0)
+        .mount(&mock_server)
+        .await;
+
+    let shakespeare_wrapper = shakespeare::ShakespeareWrapper::with_base_url("http://127.0.0.1:9");
+    let pokemon_wrapper = pokemon::PokemonWrapper::with_base_url(&mock_server.uri());
+    let app = server::build(shakespeare_wrapper, pokemon_wrapper).await;
+
+    // act
+    let mut res = surf::Client::with_http_client(app)
+        .post("https://example.com/pokemon/batch")
+        .body(json!({ "names": ["  ", ""] }))
+        .await?;
+
+    // assert
+    assert_eq!(200, res.status());
+    let response: serde_json::Value = res.body_json().await?;
+    let item = json!({
+        "name": "",
+        "status": 400,
+        "error": "A pokemon name can't be blank",
+        "code": "invalid_name"
+    });
+    assert_eq!(json!({ "results": [item.clone(), item] }), response);
+
+    Ok(())
+}
@@ -0,0 +1,224 @@
+use tide::prelude::json;
+
+use poke_spearify::config::RateLimitConfig;
+use poke_spearify::middlewares::rate_limit::RateLimitMiddleware;
+use poke_spearify::server::{self, State};
+use poke_spearify::wrappers::pokemon;
+use poke_spearify::wrappers::shakespeare;
+
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const CHARIZARD_CONTENT: &str = include_str!("../samples/charizard.json");
+const TRASLATION_CONTENT: &str = include_str!("../samples/shakespeare_translation.json");
+
+/// Name and data of each event of a server-sent events body.
+fn events(body: &str) -> Vec<(String, serde_json::Value)> {
+    body.split("\n\n")
+        .filter(|block| !block.trim().is_empty())
+        .map(|block| {
+            let field = |name: &str| {
+                block
+                    .lines()
+                    .find_map(|line| line.strip_prefix(name))
+                    .map(|value| value.trim().to_string())
+                    .unwrap_or_default()
+            };
+            let data = serde_json::from_str(&field("data:")).unwrap();
+            (field("event:"), data)
+        })
+        .collect()
+}
+
+#[async_std::test]
+async fn stream_batch_progress() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge wrappers mocks
+    let mock_pokemon_server = MockServer::start().await;
+    let charizard_as_json: serde_json::Value = serde_json::from_str(CHARIZARD_CONTENT).unwrap();
+    Mock::given(method("GET"))
+        .and(path(format!("{}charizard", pokemon::POKEMON_SERVICE_PATH)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(charizard_as_json))
+        .expect(1)
+        .mount(&mock_pokemon_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!(
+            "{}nocharizard",
+            pokemon::POKEMON_SERVICE_PATH
+        )))
+        .respond_with(ResponseTemplate::new(404))
+        .expect(1)
+        .mount(&mock_pokemon_server)
+        .await;
+
+    let mock_translation_server = MockServer::start().await;
+    let translation_as_json: serde_json::Value = serde_json::from_str(TRASLATION_CONTENT).unwrap();
+    Mock::given(method("POST"))
+        .and(path(shakespeare::TRANSLATION_SHAKESPEARE_PATH))
+        .respond_with(ResponseTemplate::new(200).set_body_json(translation_as_json))
+        .expect(1)
+        .mount(&mock_translation_server)
+        .await;
+
+    let shakespeare_wrapper =
+        shakespeare::ShakespeareWrapper::with_base_url(&mock_translation_server.uri());
+    let pokemon_wrapper = pokemon::PokemonWrapper::with_base_url(&mock_pokemon_server.uri());
+    let state = State::new(shakespeare_wrapper, pokemon_wrapper);
+    state.cache.insert(
+        "pikachu",
+        "Stores electricity",
+        "Stores electricity, forsooth",
+    );
+    let app = server::build_with_state(state).await;
+
+    // act, charizard is only resolved once
+    let mut res = surf::Client::with_http_client(app)
+        .get("https://example.com/pokemon/batch/stream?names=charizard,%20pikachu,nocharizard,Charizard")
+        .await?;
+
+    // assert
+    assert_eq!(200, res.status() as u16);
+    assert_eq!("text/event-stream", res.content_type().unwrap().essence());
+    let translation =
+        "Rust, a language empowering everyone to buildeth reliable and efficient software.";
+    assert_eq!(
+        events(&res.body_string().await?),
+        vec![
+            (
+                "queued".to_string(),
+                json!({ "name": "charizard", "position": 0 })
+            ),
+            (
+                "queued".to_string(),
+                json!({ "name": "pikachu", "position": 1 })
+            ),
+            (
+                "queued".to_string(),
+                json!({ "name": "nocharizard", "position": 2 })
+            ),
+            (
+                "description".to_string(),
+                json!({
+                    "name": "charizard",
+                    "description": "Spits fire that is hot enough to melt boulders. Known to cause forest fires unintentionally."
+                })
+            ),
+            (
+                "translated".to_string(),
+                json!({ "name": "charizard", "translation": translation })
+            ),
+            (
+                "description".to_string(),
+                json!({ "name": "pikachu", "description": "Stores electricity" })
+            ),
+            (
+                "translated".to_string(),
+                json!({ "name": "pikachu", "translation": "Stores electricity, forsooth" })
+            ),
+            (
+                "failed".to_string(),
+                json!({ "name": "nocharizard", "status": 404, "error": "Not Found", "code": "not_found" })
+            ),
+            ("done".to_string(), json!({ "translated": 2, "failed": 1 })),
+        ]
+    );
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn stream_invalid_batch() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge
+    let shakespeare_wrapper = shakespeare::ShakespeareWrapper::with_base_url("http://127.0.0.1:9");
+    let pokemon_wrapper = pokemon::PokemonWrapper::with_base_url("http://127.0.0.1:9");
+    let client =
+        surf::Client::with_http_client(server::build(shakespeare_wrapper, pokemon_wrapper).await);
+
+    // act
+    let missing = client
+        .get("https://example.com/pokemon/batch/stream")
+        .await?;
+    let empty = client
+        .get("https://example.com/v1/pokemon/batch/stream?names=,")
+        .await?;
+
+    // assert
+    assert_eq!(400, missing.status() as u16);
+    assert_eq!(400, empty.status() as u16);
+
+    Ok(())
+}
+
+/// Events of a stream of nocharizard, pikachu (cached) and bulbasaur, limited to 2 requests
+/// refilled at `refill_per_sec`.
+async fn limited_stream(refill_per_sec: f64) -> tide::Result<Vec<(String, serde_json::Value)>> {
+    let mock_pokemon_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&mock_pokemon_server)
+        .await;
+
+    let shakespeare_wrapper = shakespeare::ShakespeareWrapper::with_base_url("http://127.0.0.1:9");
+    let pokemon_wrapper = pokemon::PokemonWrapper::with_base_url(&mock_pokemon_server.uri());
+    let state = State::new(shakespeare_wrapper, pokemon_wrapper);
+    state.cache.insert(
+        "pikachu",
+        "Stores electricity",
+        "Stores electricity, forsooth",
+    );
+    let mut app = server::build_with_state(state).await;
+    app.with(RateLimitMiddleware::new(&RateLimitConfig {
+        enabled: true,
+        capacity: 2,
+        refill_per_sec,
+        trusted_proxies: vec![],
+    }));
+
+    let mut res = surf::Client::with_http_client(app)
+        .get("https://example.com/pokemon/batch/stream?names=nocharizard,pikachu,bulbasaur")
+        .await?;
+    assert_eq!(200, res.status() as u16);
+    Ok(events(&res.body_string().await?))
+}
+
+#[async_std::test]
+async fn stream_batch_charged_per_pokemon() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // act, the request and nocharizard take the 2 tokens, pikachu is cached
+    let waiting = limited_stream(1.0).await?;
+    let limited = limited_stream(0.001).await?;
+
+    // assert, bulbasaur waits for the next token
+    let bulbasaur = |events: &[(String, serde_json::Value)]| {
+        events
+            .iter()
+            .filter(|(_, data)| data["name"] == "bulbasaur")
+            .map(|(event, _)| event.clone())
+            .collect::<Vec<String>>()
+    };
+    assert_eq!(vec!["queued", "waiting", "failed"], bulbasaur(&waiting));
+    assert_eq!(
+        Some(&("done".to_string(), json!({ "translated": 1, "failed": 2 }))),
+        waiting.last()
+    );
+
+    // or fails if it's too far
+    assert_eq!(vec!["queued", "failed"], bulbasaur(&limited));
+    let failed = limited
+        .iter()
+        .find(|(event, data)| event == "failed" && data["name"] == "bulbasaur")
+        .map(|(_, data)| data.clone());
+    assert_eq!(
+        Some(
+            json!({ "name": "bulbasaur", "status": 429, "error": "Too Many Requests", "code": "rate_limited" })
+        ),
+        failed
+    );
+
+    Ok(())
+}
@@ -0,0 +1,104 @@
+use std::sync::Arc;
+
+use poke_spearify::config::Config;
+use poke_spearify::server::{self, State};
+use poke_spearify::wrappers::pokemon;
+use poke_spearify::wrappers::shakespeare;
+use tide::http::{Method, Request, Response, Url};
+use tide::Server;
+
+/// App allowing requests from the frontend origin, with charizard already cached.
+async fn app() -> Server<State> {
+    let shakespeare_wrapper = shakespeare::ShakespeareWrapper::with_base_url("http://127.0.0.1:9");
+    let pokemon_wrapper = pokemon::PokemonWrapper::with_base_url("http://127.0.0.1:9");
+    let mut state = State::new(shakespeare_wrapper, pokemon_wrapper);
+    state
+        .cache
+        .insert("charizard", "Spits fire", "Spits fire, forsooth");
+
+    let mut config = Config::default();
+    config.cors.enabled = true;
+    config.cors.allowed_origins = vec!["https://frontend.example.com".to_string()];
+    config.cors.max_age_secs = 600;
+    state.config = Arc::new(config);
+
+    server::build_with_state(state).await
+}
+
+async fn request(
+    app: &Server<State>,
+    method: Method,
+    path: &str,
+    origin: &str,
+) -> tide::Result<Response> {
+    let url = Url::parse(&format!("https://example.com{}", path))?;
+    let mut req = Request::new(method, url);
+    req.insert_header("Origin", origin);
+    req.insert_header("Access-Control-Request-Method", "POST");
+    app.respond(req).await
+}
+
+#[async_std::test]
+async fn preflight_requests() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge
+    let app = app().await;
+    let origin = "https://frontend.example.com";
+
+    // act
+    for path in &["/pokemon/charizard", "/pokemon/batch", "/translate"] {
+        let res = request(&app, Method::Options, path, origin).await?;
+
+        // assert
+        assert_eq!(200, res.status(), "preflight of {}", path);
+        assert_eq!(origin, res["Access-Control-Allow-Origin"].as_str());
+        assert_eq!(
+            "GET, POST, OPTIONS",
+            res["Access-Control-Allow-Methods"].as_str()
+        );
+        assert!(res["Access-Control-Allow-Headers"]
+            .as_str()
+            .contains("Content-Type"));
+        assert_eq!("600", res["Access-Control-Max-Age"].as_str());
+    }
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn cross_origin_requests() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge
+    let app = app().await;
+
+    // act
+    let allowed = request(
+        &app,
+        Method::Get,
+        "/pokemon/charizard",
+        "https://frontend.example.com",
+    )
+    .await?;
+    let denied = request(
+        &app,
+        Method::Options,
+        "/pokemon/charizard",
+        "https://evil.example.com",
+    )
+    .await?;
+
+    // assert
+    assert_eq!(200, allowed.status());
+    assert_eq!(
+        "https://frontend.example.com",
+        allowed["Access-Control-Allow-Origin"].as_str()
+    );
+    assert!(allowed["Access-Control-Expose-Headers"]
+        .as_str()
+        .contains("X-Request-Id"));
+    assert!(denied.header("Access-Control-Allow-Origin").is_none());
+
+    Ok(())
+}
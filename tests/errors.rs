@@ -0,0 +1,289 @@
+use std::time::Duration;
+
+use tide::prelude::json;
+
+use poke_spearify::middlewares::json_error::{JsonErrorMiddleware, Problem, PROBLEM_JSON};
+use poke_spearify::middlewares::requestid::RequestIdMiddleware;
+use poke_spearify::server;
+use poke_spearify::wrappers::pokemon;
+use poke_spearify::wrappers::shakespeare;
+
+use assert_json_diff::assert_json_eq;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const REQUEST_ID: &str = "8e4f0c1f-2a3f-4d56-9e1a-0f5b7c2d9a10";
+
+#[async_std::test]
+async fn not_found_problem() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge wrappers mocks
+    let mock_pokemon_server = MockServer::start().await;
+    let mock_path = format!("{}nocharizard", pokemon::POKEMON_SERVICE_PATH);
+
+    Mock::given(method("GET"))
+        .and(path(&mock_path))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&mock_pokemon_server)
+        .await;
+
+    // act
+    let shakespeare_wrapper = shakespeare::ShakespeareWrapper::new();
+    let pokemon_wrapper = pokemon::PokemonWrapper::with_base_url(&mock_pokemon_server.uri());
+    let mut app = server::build(shakespeare_wrapper, pokemon_wrapper).await;
+    app.with(RequestIdMiddleware::new());
+    app.with(JsonErrorMiddleware::new());
+
+    let mut res = surf::Client::with_http_client(app)
+        .get("https://example.com/pokemon/nocharizard")
+        .header("X-Request-Id", REQUEST_ID)
+        .await?;
+
+    // assert
+    assert_eq!(404, res.status());
+    assert_eq!(PROBLEM_JSON, res.content_type().unwrap().essence());
+
+    let problem: Problem = res.body_json().await?;
+    assert_eq!("urn:poke-spearify:problem:not_found", problem.problem_type);
+    assert_eq!("Not Found", problem.title);
+    assert_eq!(404, problem.status);
+    assert_eq!("Not Found", problem.detail);
+    assert_eq!("/pokemon/nocharizard", problem.instance);
+    assert_eq!("not_found", problem.code);
+    assert_eq!(REQUEST_ID, problem.request_id.unwrap().as_str());
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn bad_request_problem() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    let shakespeare_wrapper = shakespeare::ShakespeareWrapper::new();
+    let pokemon_wrapper = pokemon::PokemonWrapper::new();
+    let mut app = server::build(shakespeare_wrapper, pokemon_wrapper).await;
+    app.with(JsonErrorMiddleware::new());
+
+    let mut res = surf::Client::with_http_client(app)
+        .post("https://example.com/translate")
+        .body(json!({ "text": "Hello world", "style": "yoda" }))
+        .await?;
+
+    assert_eq!(400, res.status());
+    assert_eq!(PROBLEM_JSON, res.content_type().unwrap().essence());
+
+    let problem: Problem = res.body_json().await?;
+    assert_json_eq!(
+        problem,
+        json!({
+            "type": "urn:poke-spearify:problem:bad_request",
+            "title": "Bad Request",
+            "status": 400,
+            "detail": "Unsupported style: yoda",
+            "instance": "/translate",
+            "code": "bad_request",
+            "request_id": null
+        })
+    );
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn unknown_route_problem() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    let shakespeare_wrapper = shakespeare::ShakespeareWrapper::new();
+    let pokemon_wrapper = pokemon::PokemonWrapper::new();
+    let mut app = server::build(shakespeare_wrapper, pokemon_wrapper).await;
+    app.with(JsonErrorMiddleware::new());
+
+    let mut res = surf::Client::with_http_client(app)
+        .get("https://example.com/nowhere")
+        .await?;
+
+    assert_eq!(404, res.status());
+
+    let problem: Problem = res.body_json().await?;
+    assert_eq!("not_found", problem.code);
+    assert_eq!("/nowhere", problem.instance);
+
+    Ok(())
+}
+
+/// Start a pokemon api mock answering charizard with the given response.
+async fn mock_pokemon(response: ResponseTemplate) -> MockServer {
+    let mock_pokemon_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path(format!("{}charizard", pokemon::POKEMON_SERVICE_PATH)))
+        .respond_with(response)
+        .mount(&mock_pokemon_server)
+        .await;
+
+    mock_pokemon_server
+}
+
+/// Start a translation api mock answering with the given response.
+async fn mock_translation(response: ResponseTemplate) -> MockServer {
+    let mock_translation_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path(shakespeare::TRANSLATION_SHAKESPEARE_PATH))
+        .respond_with(response)
+        .mount(&mock_translation_server)
+        .await;
+
+    mock_translation_server
+}
+
+fn charizard() -> ResponseTemplate {
+    const CHARIZARD_CONTENT: &str = include_str!("../samples/charizard.json");
+    let charizard_as_json: serde_json::Value = serde_json::from_str(CHARIZARD_CONTENT).unwrap();
+    ResponseTemplate::new(200).set_body_json(charizard_as_json)
+}
+
+/// Get charizard with the given wrappers, returning the status and the rendered problem.
+async fn get_charizard(
+    shakespeare_wrapper: shakespeare::ShakespeareWrapper,
+    pokemon_wrapper: pokemon::PokemonWrapper,
+) -> tide::Result<(u16, Problem)> {
+    let mut app = server::build(shakespeare_wrapper, pokemon_wrapper).await;
+    app.with(JsonErrorMiddleware::new());
+
+    let mut res = surf::Client::with_http_client(app)
+        .get("https://example.com/pokemon/charizard")
+        .await?;
+
+    let problem: Problem = res.body_json().await?;
+    Ok((res.status().into(), problem))
+}
+
+#[async_std::test]
+async fn no_description_maps_to_not_found() -> tide::Result<()> {
+    const CHARIZARD_CONTENT: &str = include_str!("../samples/charizard_without_desc.json");
+    let charizard_as_json: serde_json::Value = serde_json::from_str(CHARIZARD_CONTENT).unwrap();
+    let mock_pokemon_server =
+        mock_pokemon(ResponseTemplate::new(200).set_body_json(charizard_as_json)).await;
+
+    let (status, problem) = get_charizard(
+        shakespeare::ShakespeareWrapper::new(),
+        pokemon::PokemonWrapper::with_base_url(&mock_pokemon_server.uri()),
+    )
+    .await?;
+
+    assert_eq!(404, status);
+    assert_eq!("no_description", problem.code);
+    assert_eq!(
+        "The pokemon has no english description to translate",
+        problem.detail
+    );
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn parsing_error_maps_to_bad_gateway() -> tide::Result<()> {
+    const CHARIZARD_CONTENT: &str = include_str!("../samples/charizard_bad.json");
+    let mock_pokemon_server =
+        mock_pokemon(ResponseTemplate::new(200).set_body_json(CHARIZARD_CONTENT)).await;
+
+    let (status, problem) = get_charizard(
+        shakespeare::ShakespeareWrapper::new(),
+        pokemon::PokemonWrapper::with_base_url(&mock_pokemon_server.uri()),
+    )
+    .await?;
+
+    assert_eq!(502, status);
+    assert_eq!("parsing_error", problem.code);
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn upstream_server_error_maps_to_bad_gateway() -> tide::Result<()> {
+    let mock_pokemon_server = mock_pokemon(charizard()).await;
+    let mock_translation_server = mock_translation(ResponseTemplate::new(500)).await;
+
+    let (status, problem) = get_charizard(
+        shakespeare::ShakespeareWrapper::with_base_url(&mock_translation_server.uri()),
+        pokemon::PokemonWrapper::with_base_url(&mock_pokemon_server.uri()),
+    )
+    .await?;
+
+    assert_eq!(502, status);
+    assert_eq!("upstream_error", problem.code);
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn upstream_unavailable_maps_to_service_unavailable() -> tide::Result<()> {
+    let mock_pokemon_server = mock_pokemon(ResponseTemplate::new(503)).await;
+
+    let (status, problem) = get_charizard(
+        shakespeare::ShakespeareWrapper::new(),
+        pokemon::PokemonWrapper::with_base_url(&mock_pokemon_server.uri()),
+    )
+    .await?;
+
+    assert_eq!(503, status);
+    assert_eq!("upstream_error", problem.code);
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn network_error_maps_to_service_unavailable() -> tide::Result<()> {
+    // nothing listens on the discard port.
+    let (status, problem) = get_charizard(
+        shakespeare::ShakespeareWrapper::new(),
+        pokemon::PokemonWrapper::with_base_url("http://127.0.0.1:9"),
+    )
+    .await?;
+
+    assert_eq!(503, status);
+    assert_eq!("network_error", problem.code);
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn timeout_maps_to_gateway_timeout() -> tide::Result<()> {
+    let mock_pokemon_server = mock_pokemon(charizard()).await;
+    let mock_translation_server =
+        mock_translation(ResponseTemplate::new(200).set_delay(Duration::from_secs(2))).await;
+
+    let mut shakespeare_wrapper =
+        shakespeare::ShakespeareWrapper::with_base_url(&mock_translation_server.uri());
+    shakespeare_wrapper.set_timeout(Duration::from_millis(100));
+
+    let (status, problem) = get_charizard(
+        shakespeare_wrapper,
+        pokemon::PokemonWrapper::with_base_url(&mock_pokemon_server.uri()),
+    )
+    .await?;
+
+    assert_eq!(504, status);
+    assert_eq!("timeout", problem.code);
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn over_quota_maps_to_too_many_requests() -> tide::Result<()> {
+    let mock_pokemon_server = mock_pokemon(charizard()).await;
+    let mock_translation_server = mock_translation(ResponseTemplate::new(429)).await;
+
+    let (status, problem) = get_charizard(
+        shakespeare::ShakespeareWrapper::with_base_url(&mock_translation_server.uri()),
+        pokemon::PokemonWrapper::with_base_url(&mock_pokemon_server.uri()),
+    )
+    .await?;
+
+    assert_eq!(429, status);
+    assert_eq!("too_many_requests", problem.code);
+
+    Ok(())
+}
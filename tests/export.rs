@@ -0,0 +1,105 @@
+use std::sync::Arc;
+
+use poke_spearify::config::Config;
+use poke_spearify::server::{self, State};
+use poke_spearify::store::Record;
+use poke_spearify::wrappers::pokemon;
+use poke_spearify::wrappers::shakespeare;
+
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[async_std::test]
+async fn export_served_translations() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge wrappers mocks, only called once by the lookup
+    let mock_pokemon_server = MockServer::start().await;
+
+    const CHARIZARD_CONTENT: &str = include_str!("../samples/charizard.json");
+    let charizard_as_json: serde_json::Value = serde_json::from_str(CHARIZARD_CONTENT).unwrap();
+    Mock::given(method("GET"))
+        .and(path(format!("{}charizard", pokemon::POKEMON_SERVICE_PATH)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(charizard_as_json))
+        .expect(1)
+        .mount(&mock_pokemon_server)
+        .await;
+
+    let mock_translation_server = MockServer::start().await;
+
+    const TRASLATION_CONTENT: &str = include_str!("../samples/shakespeare_translation.json");
+    let translation_as_json: serde_json::Value = serde_json::from_str(TRASLATION_CONTENT).unwrap();
+    Mock::given(method("POST"))
+        .and(path(shakespeare::TRANSLATION_SHAKESPEARE_PATH))
+        .respond_with(ResponseTemplate::new(200).set_body_json(&translation_as_json))
+        .expect(1)
+        .mount(&mock_translation_server)
+        .await;
+
+    let shakespeare_wrapper =
+        shakespeare::ShakespeareWrapper::with_base_url(&mock_translation_server.uri());
+    let pokemon_wrapper = pokemon::PokemonWrapper::with_base_url(&mock_pokemon_server.uri());
+    let mut state = State::new(shakespeare_wrapper, pokemon_wrapper);
+    let mut config = Config::default();
+    config.admin.token = Some("secret".to_string());
+    state.config = Arc::new(config);
+    let app = server::build_with_state(state).await;
+    let client = surf::Client::with_http_client(app);
+
+    // act
+    let res = client.get("https://example.com/pokemon/charizard").await?;
+    assert_eq!(200, res.status());
+
+    let mut jsonl = client
+        .get("https://example.com/admin/export")
+        .header("Authorization", "Bearer secret")
+        .await?;
+    let mut csv = client
+        .get("https://example.com/admin/export?format=csv")
+        .header("Authorization", "Bearer secret")
+        .await?;
+    let mut markdown = client
+        .get("https://example.com/admin/export?format=markdown")
+        .header("Authorization", "Bearer secret")
+        .await?;
+    let unsupported = client
+        .get("https://example.com/admin/export?format=xls")
+        .header("Authorization", "Bearer secret")
+        .await?;
+
+    // assert, streamed so the length isn't known upfront
+    assert_eq!(200, jsonl.status());
+    assert!(jsonl.len().is_none());
+    assert_eq!(
+        Some("application/x-ndjson"),
+        jsonl.content_type().as_ref().map(|mime| mime.essence())
+    );
+    let records: Vec<Record> = jsonl
+        .body_string()
+        .await?
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(1, records.len());
+    assert_eq!("charizard", records[0].name);
+    assert_eq!(Some(6), records[0].id);
+    assert_eq!(
+        translation_as_json["contents"]["translated"],
+        records[0].translation
+    );
+
+    let csv = csv.body_string().await?;
+    assert!(csv.starts_with("name,description,translation,source,recorded_at,id\n"));
+    assert_eq!(2, csv.lines().count());
+
+    let markdown = markdown.body_string().await?;
+    assert!(markdown
+        .lines()
+        .nth(2)
+        .unwrap()
+        .starts_with("| charizard |"));
+
+    assert_eq!(400, unsupported.status());
+
+    Ok(())
+}
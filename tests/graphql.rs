@@ -0,0 +1,222 @@
+use tide::prelude::json;
+
+use poke_spearify::server;
+use poke_spearify::wrappers::pokemon;
+use poke_spearify::wrappers::shakespeare;
+
+use assert_json_diff::assert_json_eq;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const CHARIZARD_CONTENT: &str = include_str!("../samples/charizard.json");
+const TRASLATION_CONTENT: &str = include_str!("../samples/shakespeare_translation.json");
+
+async fn query(client: &surf::Client, query: &str) -> tide::Result<serde_json::Value> {
+    let mut res = client
+        .post("https://example.com/graphql")
+        .body(json!({ "query": query }))
+        .await?;
+    assert_eq!(200, res.status() as u16);
+    res.body_json().await
+}
+
+#[async_std::test]
+async fn batched_pokemon_query() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge wrappers mocks
+    let mock_pokemon_server = MockServer::start().await;
+    let charizard_as_json: serde_json::Value = serde_json::from_str(CHARIZARD_CONTENT).unwrap();
+    Mock::given(method("GET"))
+        .and(path(format!("{}charizard", pokemon::POKEMON_SERVICE_PATH)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(charizard_as_json))
+        .expect(1)
+        .mount(&mock_pokemon_server)
+        .await;
+
+    let mock_translation_server = MockServer::start().await;
+    let translation_as_json: serde_json::Value = serde_json::from_str(TRASLATION_CONTENT).unwrap();
+    Mock::given(method("POST"))
+        .and(path(shakespeare::TRANSLATION_SHAKESPEARE_PATH))
+        .respond_with(ResponseTemplate::new(200).set_body_json(translation_as_json))
+        .expect(1)
+        .mount(&mock_translation_server)
+        .await;
+
+    let shakespeare_wrapper =
+        shakespeare::ShakespeareWrapper::with_base_url(&mock_translation_server.uri());
+    let pokemon_wrapper = pokemon::PokemonWrapper::with_base_url(&mock_pokemon_server.uri());
+    let app = server::build(shakespeare_wrapper, pokemon_wrapper).await;
+    let client = surf::Client::with_http_client(app);
+
+    // act
+    let response = query(
+        &client,
+        r#"{
+            pokemon(name: "charizard") { description }
+            pokemons(names: ["charizard", "Charizard"]) { name translation { style text manual } }
+        }"#,
+    )
+    .await?;
+
+    // assert
+    let translation = json!({
+        "style": "SHAKESPEARE",
+        "text": "Rust, a language empowering everyone to buildeth reliable and efficient software.",
+        "manual": false
+    });
+    assert_json_eq!(
+        response,
+        json!({
+            "data": {
+                "pokemon": {
+                    "description": "Spits fire that is hot enough to melt boulders. Known to cause forest fires unintentionally."
+                },
+                "pokemons": [
+                    { "name": "charizard", "translation": translation },
+                    { "name": "charizard", "translation": translation }
+                ]
+            }
+        })
+    );
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn evolutions_query() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge wrappers mocks
+    let mock_pokemon_server = MockServer::start().await;
+    let charizard_as_json: serde_json::Value = serde_json::from_str(CHARIZARD_CONTENT).unwrap();
+    Mock::given(method("GET"))
+        .and(path(format!("{}charizard", pokemon::POKEMON_SERVICE_PATH)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(charizard_as_json))
+        .mount(&mock_pokemon_server)
+        .await;
+
+    let chain = json!({
+        "chain": {
+            "species": { "name": "charmander", "url": "https://pokeapi.co/api/v2/pokemon-species/4/" },
+            "evolves_to": [{
+                "species": { "name": "charmeleon", "url": "https://pokeapi.co/api/v2/pokemon-species/5/" },
+                "evolves_to": [{
+                    "species": { "name": "charizard", "url": "https://pokeapi.co/api/v2/pokemon-species/6/" },
+                    "evolves_to": []
+                }]
+            }]
+        }
+    });
+    Mock::given(method("GET"))
+        .and(path(format!("{}2/", pokemon::EVOLUTION_CHAIN_PATH)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(chain))
+        .expect(1)
+        .mount(&mock_pokemon_server)
+        .await;
+
+    let shakespeare_wrapper = shakespeare::ShakespeareWrapper::with_base_url("http://127.0.0.1:9");
+    let pokemon_wrapper = pokemon::PokemonWrapper::with_base_url(&mock_pokemon_server.uri());
+    let app = server::build(shakespeare_wrapper, pokemon_wrapper).await;
+    let client = surf::Client::with_http_client(app);
+
+    // act
+    let response = query(
+        &client,
+        r#"{ pokemon(name: "charizard") { evolutions { name } } }"#,
+    )
+    .await?;
+
+    // assert
+    assert_json_eq!(
+        response,
+        json!({
+            "data": {
+                "pokemon": {
+                    "evolutions": [
+                        { "name": "charmander" },
+                        { "name": "charmeleon" },
+                        { "name": "charizard" }
+                    ]
+                }
+            }
+        })
+    );
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn unknown_pokemon_query() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge wrappers mocks
+    let mock_pokemon_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&mock_pokemon_server)
+        .await;
+
+    let shakespeare_wrapper = shakespeare::ShakespeareWrapper::with_base_url("http://127.0.0.1:9");
+    let pokemon_wrapper = pokemon::PokemonWrapper::with_base_url(&mock_pokemon_server.uri());
+    let app = server::build(shakespeare_wrapper, pokemon_wrapper).await;
+    let client = surf::Client::with_http_client(app);
+
+    // act
+    let response = query(
+        &client,
+        r#"{ pokemon(name: "nocharizard") { translation { text } } }"#,
+    )
+    .await?;
+
+    // assert
+    assert_eq!(json!({ "pokemon": null }), response["data"]);
+    assert_eq!("Not Found", response["errors"][0]["message"]);
+    assert_eq!("not_found", response["errors"][0]["extensions"]["code"]);
+    assert_eq!(404, response["errors"][0]["extensions"]["status"]);
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn expensive_queries() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge, nothing reaches the upstreams
+    let shakespeare_wrapper = shakespeare::ShakespeareWrapper::with_base_url("http://127.0.0.1:9");
+    let pokemon_wrapper = pokemon::PokemonWrapper::with_base_url("http://127.0.0.1:9");
+    let app = server::build(shakespeare_wrapper, pokemon_wrapper).await;
+    let client = surf::Client::with_http_client(app);
+
+    let aliased = |count: usize, fields: &str| {
+        let pokemons: Vec<String> = (0..count)
+            .map(|i| format!(r#"p{}: pokemon(name: "pokemon{}") {{ {} }}"#, i, i, fields))
+            .collect();
+        format!("{{ {} }}", pokemons.join(" "))
+    };
+
+    // act
+    let too_complex = query(&client, &aliased(40, "translation { text }")).await?;
+    let too_many_keys = query(&client, &aliased(21, "description")).await?;
+
+    // assert
+    assert_eq!(json!(null), too_complex["data"]);
+    assert_eq!("Query is too complex.", too_complex["errors"][0]["message"]);
+
+    let codes: Vec<&str> = too_many_keys["errors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|error| error["extensions"]["code"].as_str().unwrap())
+        .collect();
+    assert_eq!(21, codes.len());
+    assert_eq!(
+        1,
+        codes
+            .iter()
+            .filter(|code| **code == "too_many_keys")
+            .count()
+    );
+
+    Ok(())
+}
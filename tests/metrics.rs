@@ -0,0 +1,96 @@
+use poke_spearify::middlewares::metrics::MetricsMiddleware;
+use poke_spearify::server::{self, State};
+use poke_spearify::wrappers::pokemon;
+use poke_spearify::wrappers::shakespeare;
+
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const CHARIZARD_CONTENT: &str = include_str!("../samples/charizard.json");
+const TRASLATION_CONTENT: &str = include_str!("../samples/shakespeare_translation.json");
+
+#[async_std::test]
+async fn export_metrics() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge wrappers mocks
+    let mock_pokemon_server = MockServer::start().await;
+    let charizard_as_json: serde_json::Value = serde_json::from_str(CHARIZARD_CONTENT).unwrap();
+    Mock::given(method("GET"))
+        .and(path(format!("{}charizard", pokemon::POKEMON_SERVICE_PATH)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(charizard_as_json))
+        .expect(1)
+        .mount(&mock_pokemon_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!(
+            "{}nocharizard",
+            pokemon::POKEMON_SERVICE_PATH
+        )))
+        .respond_with(ResponseTemplate::new(404))
+        .expect(1)
+        .mount(&mock_pokemon_server)
+        .await;
+
+    let mock_translation_server = MockServer::start().await;
+    let translation_as_json: serde_json::Value = serde_json::from_str(TRASLATION_CONTENT).unwrap();
+    Mock::given(method("POST"))
+        .and(path(shakespeare::TRANSLATION_SHAKESPEARE_PATH))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(translation_as_json)
+                .insert_header("X-RateLimit-Remaining", "4"),
+        )
+        .expect(1)
+        .mount(&mock_translation_server)
+        .await;
+
+    let shakespeare_wrapper =
+        shakespeare::ShakespeareWrapper::with_base_url(&mock_translation_server.uri());
+    let pokemon_wrapper = pokemon::PokemonWrapper::with_base_url(&mock_pokemon_server.uri());
+    let state = State::new(shakespeare_wrapper, pokemon_wrapper);
+    let metrics = state.metrics.clone();
+    let mut app = server::build_with_state(state).await;
+    app.with(MetricsMiddleware::new(metrics));
+    let client = surf::Client::with_http_client(app);
+
+    // act
+    for url in [
+        "https://example.com/pokemon/charizard",
+        "https://example.com/pokemon/charizard",
+        "https://example.com/pokemon/nocharizard",
+        "https://example.com/nothing/here",
+    ] {
+        client.get(url).await?;
+    }
+    let mut res = client.get("https://example.com/metrics").await?;
+
+    // assert
+    assert_eq!(200, res.status() as u16);
+    assert_eq!("text/plain", res.content_type().unwrap().essence());
+    let body = res.body_string().await?;
+    for line in [
+        "# TYPE http_requests_total counter",
+        r#"http_requests_total{route="/pokemon/:pokemon_name",status="200"} 2"#,
+        r#"http_requests_total{route="/pokemon/:pokemon_name",status="404"} 1"#,
+        r#"http_requests_total{route="unmatched",status="404"} 1"#,
+        r#"http_request_duration_seconds_count{route="/pokemon/:pokemon_name",status="200"} 2"#,
+        r#"upstream_requests_total{upstream="pokeapi"} 2"#,
+        r#"upstream_requests_total{upstream="funtranslations"} 1"#,
+        r#"upstream_request_duration_seconds_bucket{upstream="funtranslations",le="+Inf"} 1"#,
+        r#"upstream_errors_total{upstream="pokeapi",code="not_found"} 1"#,
+        "cache_hits_total 1",
+        "cache_misses_total 2",
+        "cache_entries 1",
+        "translation_quota_remaining 4",
+    ] {
+        assert!(
+            body.lines().any(|l| l == line),
+            "missing `{}` in:\n{}",
+            line,
+            body
+        );
+    }
+
+    Ok(())
+}
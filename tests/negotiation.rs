@@ -0,0 +1,116 @@
+use poke_spearify::server::{self, State};
+use poke_spearify::wrappers::pokemon;
+use poke_spearify::wrappers::shakespeare;
+
+/// Client of an app with charizard already cached, the upstream apis are never called.
+async fn client() -> surf::Client {
+    let shakespeare_wrapper = shakespeare::ShakespeareWrapper::with_base_url("http://127.0.0.1:9");
+    let pokemon_wrapper = pokemon::PokemonWrapper::with_base_url("http://127.0.0.1:9");
+    let state = State::new(shakespeare_wrapper, pokemon_wrapper);
+    state
+        .cache
+        .insert("charizard", "Spits fire", "Spits fire <hot> & bright");
+
+    surf::Client::with_http_client(server::build_with_state(state).await)
+}
+
+async fn get(
+    client: &surf::Client,
+    url: &str,
+    accept: &str,
+) -> tide::Result<(u16, String, String)> {
+    let mut res = client.get(url).header("Accept", accept).await?;
+    let content_type = res
+        .content_type()
+        .map(|mime| mime.essence().to_string())
+        .unwrap_or_default();
+    Ok((res.status() as u16, content_type, res.body_string().await?))
+}
+
+#[async_std::test]
+async fn negotiate_pokemon_format() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge
+    let client = client().await;
+    let url = "https://example.com/pokemon/charizard";
+
+    // act
+    let json = get(&client, url, "*/*").await?;
+    let text = get(&client, url, "text/plain").await?;
+    let html = get(&client, url, "text/html,application/xhtml+xml,*/*;q=0.8").await?;
+    let xml = get(&client, url, "application/xml").await?;
+    let yaml = get(&client, url, "application/yaml").await?;
+
+    // assert
+    assert_eq!(
+        (
+            200,
+            "application/json".to_string(),
+            r#"{"name":"charizard","description":"Spits fire <hot> & bright"}"#.to_string()
+        ),
+        json
+    );
+    assert_eq!(
+        (
+            200,
+            "text/plain".to_string(),
+            "Spits fire <hot> & bright".to_string()
+        ),
+        text
+    );
+
+    assert_eq!((200, "text/html"), (html.0, html.1.as_str()));
+    assert!(html.2.contains("<h1>charizard</h1>"));
+    assert!(html
+        .2
+        .contains("<p>Spits fire &lt;hot&gt; &amp; bright</p>"));
+
+    assert_eq!((200, "application/xml"), (xml.0, xml.1.as_str()));
+    assert!(xml.2.ends_with(
+        "<pokemon><name>charizard</name><description>Spits fire &lt;hot&gt; &amp; bright</description></pokemon>"
+    ));
+
+    assert_eq!((200, "application/yaml"), (yaml.0, yaml.1.as_str()));
+    assert_eq!(
+        "name: charizard\ndescription: Spits fire <hot> & bright\n",
+        yaml.2
+    );
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn format_query_parameter() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge
+    let client = client().await;
+
+    // act
+    let yaml = get(
+        &client,
+        "https://example.com/pokemon/charizard?format=yaml",
+        "application/json",
+    )
+    .await?;
+    let unknown = get(
+        &client,
+        "https://example.com/pokemon/charizard?format=pdf",
+        "*/*",
+    )
+    .await?;
+    let not_acceptable = get(
+        &client,
+        "https://example.com/pokemon/charizard",
+        "application/pdf",
+    )
+    .await?;
+
+    // assert
+    assert_eq!((200, "application/yaml"), (yaml.0, yaml.1.as_str()));
+    assert_eq!(400, unknown.0);
+    assert_eq!(406, not_acceptable.0);
+
+    Ok(())
+}
@@ -0,0 +1,103 @@
+use std::collections::BTreeSet;
+
+use poke_spearify::server;
+use poke_spearify::wrappers::pokemon;
+use poke_spearify::wrappers::shakespeare;
+
+/// Routes registered by the server, as `(method, path)` in OpenAPI syntax.
+fn registered_routes() -> BTreeSet<(String, String)> {
+    server::ROUTES
+        .iter()
+        .map(|(method, path)| {
+            let path = path
+                .split('/')
+                .map(|segment| match segment.strip_prefix(':') {
+                    Some(param) => format!("{{{}}}", param),
+                    None => segment.to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join("/");
+            (method.to_string().to_lowercase(), path)
+        })
+        .collect()
+}
+
+#[async_std::test]
+async fn spec_matches_routes() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge
+    let shakespeare_wrapper = shakespeare::ShakespeareWrapper::with_base_url("http://127.0.0.1:9");
+    let pokemon_wrapper = pokemon::PokemonWrapper::with_base_url("http://127.0.0.1:9");
+    let app = server::build(shakespeare_wrapper, pokemon_wrapper).await;
+    let client = surf::Client::with_http_client(app);
+
+    // act
+    let mut res = client.get("https://example.com/openapi.json").await?;
+    let spec: serde_json::Value = res.body_json().await?;
+
+    // assert
+    assert_eq!(200, res.status() as u16);
+    assert!(spec["components"]["schemas"]["PokemonResponse"].is_object());
+    assert!(spec["components"]["schemas"]["Problem"].is_object());
+
+    let mut documented = BTreeSet::new();
+    for (path, item) in spec["paths"].as_object().expect("paths") {
+        for method in item.as_object().expect("path item").keys() {
+            documented.insert((method.to_string(), path.to_string()));
+        }
+    }
+    assert_eq!(registered_routes(), documented);
+
+    // every documented operation reaches a handler, the router would answer 404 or 405
+    for (method, path) in &documented {
+        let url = format!(
+            "https://example.com{}",
+            path.replace("{pokemon_name}", "charizard")
+        );
+        let method: surf::http::Method = method.to_uppercase().parse()?;
+        let res = client
+            .send(surf::Request::new(method, url.parse()?))
+            .await?;
+        assert!(
+            ![404, 405].contains(&(res.status() as u16)),
+            "{} {} is not routed",
+            method,
+            path
+        );
+    }
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn docs_viewer() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge
+    let shakespeare_wrapper = shakespeare::ShakespeareWrapper::with_base_url("http://127.0.0.1:9");
+    let pokemon_wrapper = pokemon::PokemonWrapper::with_base_url("http://127.0.0.1:9");
+    let app = server::build(shakespeare_wrapper, pokemon_wrapper).await;
+
+    let client = surf::Client::with_http_client(app);
+
+    // act
+    let mut res = client.get("https://example.com/docs").await?;
+
+    // assert
+    assert_eq!(200, res.status() as u16);
+    assert_eq!("text/html", res.content_type().unwrap().essence());
+    let page = res.body_string().await?;
+    assert!(page.contains("/openapi.json"));
+    assert!(page.contains("/static/docs.js"));
+    assert!(!page.contains("https://"));
+
+    let res = client.get("https://example.com/static/docs.js").await?;
+    assert_eq!(200, res.status() as u16);
+    assert_eq!(
+        "application/javascript",
+        res.content_type().unwrap().essence()
+    );
+
+    Ok(())
+}
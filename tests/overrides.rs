@@ -0,0 +1,132 @@
+use std::sync::Arc;
+
+use tide::prelude::json;
+
+use poke_spearify::config::Config;
+use poke_spearify::controllers::admin::ImportResponse;
+use poke_spearify::controllers::pokemon::PokemonResponse;
+use poke_spearify::server::{self, State};
+use poke_spearify::store::Source;
+use poke_spearify::wrappers::pokemon;
+use poke_spearify::wrappers::shakespeare;
+
+use assert_json_diff::assert_json_eq;
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[async_std::test]
+async fn get_manual_translation() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge wrappers mocks, manual translations never reach the upstream apis
+    let mock_pokemon_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(0)
+        .mount(&mock_pokemon_server)
+        .await;
+
+    let mock_translation_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(0)
+        .mount(&mock_translation_server)
+        .await;
+
+    let shakespeare_wrapper =
+        shakespeare::ShakespeareWrapper::with_base_url(&mock_translation_server.uri());
+    let pokemon_wrapper = pokemon::PokemonWrapper::with_base_url(&mock_pokemon_server.uri());
+    let mut state = State::new(shakespeare_wrapper, pokemon_wrapper);
+    let mut config = Config::default();
+    config.admin.token = Some("secret".to_string());
+    state.config = Arc::new(config);
+    let app = server::build_with_state(state).await;
+    let state = app.state().clone();
+    let client = surf::Client::with_http_client(app);
+
+    // act
+    let mut import = client
+        .post("https://example.com/admin/overrides?format=csv")
+        .header("Authorization", "Bearer secret")
+        .body("name,translation\nCharizard,\"Spits fire, forsooth\"\n")
+        .await?;
+    let mut res = client.get("https://example.com/pokemon/Charizard").await?;
+
+    // assert
+    assert_eq!(200, import.status());
+    let import: ImportResponse = import.body_json().await?;
+    assert_eq!(1, import.imported);
+    assert_eq!(1, import.total);
+
+    assert_eq!(200, res.status());
+    let pokemon_response: PokemonResponse = res.body_json().await?;
+    assert_json_eq!(
+        pokemon_response,
+        json!({
+            "name": "charizard",
+            "description": "Spits fire, forsooth"
+        })
+    );
+
+    let record = state.store.get("charizard").unwrap();
+    assert_eq!(Source::Manual, record.source);
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn import_invalid_file() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge
+    let shakespeare_wrapper = shakespeare::ShakespeareWrapper::with_base_url("http://127.0.0.1:9");
+    let pokemon_wrapper = pokemon::PokemonWrapper::with_base_url("http://127.0.0.1:9");
+    let mut state = State::new(shakespeare_wrapper, pokemon_wrapper);
+    let mut config = Config::default();
+    config.admin.token = Some("secret".to_string());
+    state.config = Arc::new(config);
+    let app = server::build_with_state(state).await;
+    let state = app.state().clone();
+
+    // act
+    let res = surf::Client::with_http_client(app)
+        .post("https://example.com/admin/overrides")
+        .header("Authorization", "Bearer secret")
+        .body("{\"name\": \"charizard\"}\n")
+        .await?;
+
+    // assert
+    assert_eq!(400, res.status());
+    assert!(state.overrides.is_empty());
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn import_too_big_file() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge
+    let shakespeare_wrapper = shakespeare::ShakespeareWrapper::with_base_url("http://127.0.0.1:9");
+    let pokemon_wrapper = pokemon::PokemonWrapper::with_base_url("http://127.0.0.1:9");
+    let mut state = State::new(shakespeare_wrapper, pokemon_wrapper);
+    let mut config = Config::default();
+    config.admin.token = Some("secret".to_string());
+    state.config = Arc::new(config);
+    let app = server::build_with_state(state).await;
+    let state = app.state().clone();
+    let row = "{\"name\": \"charizard\", \"translation\": \"Spits fire, forsooth\"}\n";
+
+    // act
+    let res = surf::Client::with_http_client(app)
+        .post("https://example.com/admin/overrides")
+        .header("Authorization", "Bearer secret")
+        .body(row.repeat(200_000))
+        .await?;
+
+    // assert
+    assert_eq!(413, res.status());
+    assert!(state.overrides.is_empty());
+
+    Ok(())
+}
@@ -17,15 +17,11 @@ async fn get_pokemon() -> tide::Result<()> {
     // arrenge wrappers mocks
     let mock_pokemon_server = MockServer::start().await;
     let existing_pokemon = "charizard";
-    let mock_path = format!(
-        "{}{}",
-        pokemon::POKEMON_SERVICE_PATH,
-        existing_pokemon.to_string()
-    );
+    let mock_path = format!("{}{}", pokemon::POKEMON_SERVICE_PATH, existing_pokemon);
 
     const CHARIZARD_CONTENT: &str = include_str!("../samples/charizard.json");
 
-    let charizard_as_json: serde_json::Value = serde_json::from_str(&CHARIZARD_CONTENT).unwrap();
+    let charizard_as_json: serde_json::Value = serde_json::from_str(CHARIZARD_CONTENT).unwrap();
     let response = ResponseTemplate::new(200).set_body_json(charizard_as_json);
 
     Mock::given(method("GET"))
@@ -37,7 +33,7 @@ async fn get_pokemon() -> tide::Result<()> {
     let mock_translation_server = MockServer::start().await;
 
     const TRASLATION_CONTENT: &str = include_str!("../samples/shakespeare_translation.json");
-    let translation_as_json: serde_json::Value = serde_json::from_str(&TRASLATION_CONTENT).unwrap();
+    let translation_as_json: serde_json::Value = serde_json::from_str(TRASLATION_CONTENT).unwrap();
     let response = ResponseTemplate::new(200).set_body_json(&translation_as_json);
 
     Mock::given(method("POST"))
@@ -77,15 +73,11 @@ async fn get_pokemon_with_api_key() -> tide::Result<()> {
     // arrenge wrappers mocks
     let mock_pokemon_server = MockServer::start().await;
     let existing_pokemon = "charizard";
-    let mock_path = format!(
-        "{}{}",
-        pokemon::POKEMON_SERVICE_PATH,
-        existing_pokemon.to_string()
-    );
+    let mock_path = format!("{}{}", pokemon::POKEMON_SERVICE_PATH, existing_pokemon);
 
     const CHARIZARD_CONTENT: &str = include_str!("../samples/charizard.json");
 
-    let charizard_as_json: serde_json::Value = serde_json::from_str(&CHARIZARD_CONTENT).unwrap();
+    let charizard_as_json: serde_json::Value = serde_json::from_str(CHARIZARD_CONTENT).unwrap();
     let response = ResponseTemplate::new(200).set_body_json(charizard_as_json);
 
     Mock::given(method("GET"))
@@ -97,7 +89,7 @@ async fn get_pokemon_with_api_key() -> tide::Result<()> {
     let mock_translation_server = MockServer::start().await;
 
     const TRASLATION_CONTENT: &str = include_str!("../samples/shakespeare_translation.json");
-    let translation_as_json: serde_json::Value = serde_json::from_str(&TRASLATION_CONTENT).unwrap();
+    let translation_as_json: serde_json::Value = serde_json::from_str(TRASLATION_CONTENT).unwrap();
     let response = ResponseTemplate::new(200).set_body_json(&translation_as_json);
 
     Mock::given(method("POST"))
@@ -139,11 +131,7 @@ async fn get_non_existing_pokemon() -> tide::Result<()> {
     // arrenge wrappers mocks
     let mock_pokemon_server = MockServer::start().await;
     let existing_pokemon = "nocharizard";
-    let mock_path = format!(
-        "{}{}",
-        pokemon::POKEMON_SERVICE_PATH,
-        existing_pokemon.to_string()
-    );
+    let mock_path = format!("{}{}", pokemon::POKEMON_SERVICE_PATH, existing_pokemon);
 
     Mock::given(method("GET"))
         .and(path(&mock_path))
@@ -154,7 +142,7 @@ async fn get_non_existing_pokemon() -> tide::Result<()> {
     let mock_translation_server = MockServer::start().await;
 
     const TRASLATION_CONTENT: &str = include_str!("../samples/shakespeare_translation.json");
-    let translation_as_json: serde_json::Value = serde_json::from_str(&TRASLATION_CONTENT).unwrap();
+    let translation_as_json: serde_json::Value = serde_json::from_str(TRASLATION_CONTENT).unwrap();
     let response = ResponseTemplate::new(200).set_body_json(&translation_as_json);
 
     Mock::given(method("POST"))
@@ -185,15 +173,11 @@ async fn over_quota_in_translation() -> tide::Result<()> {
     // arrenge wrappers mocks
     let mock_pokemon_server = MockServer::start().await;
     let existing_pokemon = "charizard";
-    let mock_path = format!(
-        "{}{}",
-        pokemon::POKEMON_SERVICE_PATH,
-        existing_pokemon.to_string()
-    );
+    let mock_path = format!("{}{}", pokemon::POKEMON_SERVICE_PATH, existing_pokemon);
 
     const CHARIZARD_CONTENT: &str = include_str!("../samples/charizard.json");
 
-    let charizard_as_json: serde_json::Value = serde_json::from_str(&CHARIZARD_CONTENT).unwrap();
+    let charizard_as_json: serde_json::Value = serde_json::from_str(CHARIZARD_CONTENT).unwrap();
     let response = ResponseTemplate::new(200).set_body_json(charizard_as_json);
 
     Mock::given(method("GET"))
@@ -224,3 +208,137 @@ async fn over_quota_in_translation() -> tide::Result<()> {
 
     Ok(())
 }
+
+#[async_std::test]
+async fn get_cached_pokemon() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge wrappers mocks
+    let mock_pokemon_server = MockServer::start().await;
+    let existing_pokemon = "charizard";
+    let mock_path = format!("{}{}", pokemon::POKEMON_SERVICE_PATH, existing_pokemon);
+
+    const CHARIZARD_CONTENT: &str = include_str!("../samples/charizard.json");
+
+    let charizard_as_json: serde_json::Value = serde_json::from_str(CHARIZARD_CONTENT).unwrap();
+    let response = ResponseTemplate::new(200).set_body_json(charizard_as_json);
+
+    Mock::given(method("GET"))
+        .and(path(&mock_path))
+        .respond_with(response)
+        .expect(1)
+        .mount(&mock_pokemon_server)
+        .await;
+
+    let mock_translation_server = MockServer::start().await;
+
+    const TRASLATION_CONTENT: &str = include_str!("../samples/shakespeare_translation.json");
+    let translation_as_json: serde_json::Value = serde_json::from_str(TRASLATION_CONTENT).unwrap();
+    let response = ResponseTemplate::new(200).set_body_json(&translation_as_json);
+
+    Mock::given(method("POST"))
+        .and(path(shakespeare::TRANSLATION_SHAKESPEARE_PATH))
+        .respond_with(response)
+        .expect(1)
+        .mount(&mock_translation_server)
+        .await;
+
+    // act
+    let shakespeare_wrapper =
+        shakespeare::ShakespeareWrapper::with_base_url(&mock_translation_server.uri());
+    let pokemon_wrapper = pokemon::PokemonWrapper::with_base_url(&mock_pokemon_server.uri());
+    let app = server::build(shakespeare_wrapper, pokemon_wrapper).await;
+    let client = surf::Client::with_http_client(app);
+
+    let pokemon_url = format!("https://example.com/pokemon/{}", existing_pokemon);
+    let first = client.get(&pokemon_url).await?;
+    let mut second = client.get(&pokemon_url).await?;
+
+    // assert, the upstream apis are called only once
+    assert_eq!(200, first.status());
+    assert_eq!(200, second.status());
+
+    let pokemon_response: PokemonResponse = second.body_json().await?;
+    assert_json_eq!(
+        pokemon_response,
+        json!({
+            "name": "charizard",
+            "description": "Rust, a language empowering everyone to buildeth reliable and efficient software."
+        })
+    );
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn conditional_get_pokemon() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge wrappers mocks, only called by the first request
+    let mock_pokemon_server = MockServer::start().await;
+    let existing_pokemon = "charizard";
+    let mock_path = format!("{}{}", pokemon::POKEMON_SERVICE_PATH, existing_pokemon);
+
+    const CHARIZARD_CONTENT: &str = include_str!("../samples/charizard.json");
+
+    let charizard_as_json: serde_json::Value = serde_json::from_str(CHARIZARD_CONTENT).unwrap();
+    let response = ResponseTemplate::new(200).set_body_json(charizard_as_json);
+
+    Mock::given(method("GET"))
+        .and(path(&mock_path))
+        .respond_with(response)
+        .expect(1)
+        .mount(&mock_pokemon_server)
+        .await;
+
+    let mock_translation_server = MockServer::start().await;
+
+    const TRASLATION_CONTENT: &str = include_str!("../samples/shakespeare_translation.json");
+    let translation_as_json: serde_json::Value = serde_json::from_str(TRASLATION_CONTENT).unwrap();
+    let response = ResponseTemplate::new(200).set_body_json(&translation_as_json);
+
+    Mock::given(method("POST"))
+        .and(path(shakespeare::TRANSLATION_SHAKESPEARE_PATH))
+        .respond_with(response)
+        .expect(1)
+        .mount(&mock_translation_server)
+        .await;
+
+    // act
+    let shakespeare_wrapper =
+        shakespeare::ShakespeareWrapper::with_base_url(&mock_translation_server.uri());
+    let pokemon_wrapper = pokemon::PokemonWrapper::with_base_url(&mock_pokemon_server.uri());
+    let app = server::build(shakespeare_wrapper, pokemon_wrapper).await;
+    let client = surf::Client::with_http_client(app);
+
+    let pokemon_url = format!("https://example.com/pokemon/{}", existing_pokemon);
+    let first = client.get(&pokemon_url).await?;
+    let etag = first["ETag"].as_str().to_string();
+    let last_modified = first["Last-Modified"].as_str().to_string();
+
+    let mut not_modified = client
+        .get(&pokemon_url)
+        .header("If-None-Match", etag.as_str())
+        .await?;
+    let not_modified_since = client
+        .get(&pokemon_url)
+        .header("If-Modified-Since", last_modified.as_str())
+        .await?;
+    let modified = client
+        .get(&pokemon_url)
+        .header("If-None-Match", "\"other\"")
+        .await?;
+
+    // assert
+    assert_eq!(200, first.status());
+    assert!(etag.starts_with('"'));
+    assert_eq!("public, max-age=86400", first["Cache-Control"].as_str());
+
+    assert_eq!(304, not_modified.status());
+    assert_eq!(etag, not_modified["ETag"].as_str());
+    assert!(not_modified.body_string().await?.is_empty());
+    assert_eq!(304, not_modified_since.status());
+    assert_eq!(200, modified.status());
+
+    Ok(())
+}
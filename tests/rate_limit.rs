@@ -0,0 +1,188 @@
+use poke_spearify::config::RateLimitConfig;
+use poke_spearify::middlewares::rate_limit::RateLimitMiddleware;
+use poke_spearify::server::{self, State};
+use poke_spearify::wrappers::pokemon;
+use poke_spearify::wrappers::shakespeare;
+use tide::http::{Method, Request, Response, Url};
+use tide::Server;
+
+/// App limited to 2 requests per client, with charizard already cached.
+async fn app() -> Server<State> {
+    let shakespeare_wrapper = shakespeare::ShakespeareWrapper::with_base_url("http://127.0.0.1:9");
+    let pokemon_wrapper = pokemon::PokemonWrapper::with_base_url("http://127.0.0.1:9");
+    let state = State::new(shakespeare_wrapper, pokemon_wrapper);
+    state
+        .cache
+        .insert("charizard", "Spits fire", "Spits fire, forsooth");
+
+    let mut app = server::build_with_state(state).await;
+    app.with(RateLimitMiddleware::new(&RateLimitConfig {
+        enabled: true,
+        capacity: 2,
+        refill_per_sec: 0.001,
+        trusted_proxies: vec!["10.0.0.1".parse().unwrap()],
+    }));
+    app
+}
+
+async fn get(
+    app: &Server<State>,
+    path: &str,
+    peer_addr: &str,
+    forwarded_for: Option<&str>,
+) -> tide::Result<Response> {
+    let url = Url::parse(&format!("https://example.com{}", path))?;
+    let mut req = Request::new(Method::Get, url);
+    req.set_peer_addr(Some(peer_addr));
+    if let Some(forwarded_for) = forwarded_for {
+        req.insert_header("X-Forwarded-For", forwarded_for);
+    }
+    app.respond(req).await
+}
+
+#[async_std::test]
+async fn rate_limit_per_ip() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge
+    let app = app().await;
+
+    // act
+    let first = get(&app, "/pokemon/charizard", "1.1.1.1:4000", None).await?;
+    let second = get(&app, "/pokemon/charizard", "1.1.1.1:4001", None).await?;
+    let limited = get(&app, "/pokemon/charizard", "1.1.1.1:4002", None).await?;
+    let other_ip = get(&app, "/pokemon/charizard", "2.2.2.2:4000", None).await?;
+    let health_check = get(&app, "/health_check", "1.1.1.1:4003", None).await?;
+    let style = get(&app, "/static/style.css", "1.1.1.1:4004", None).await?;
+    let search = get(&app, "/search?q=char", "1.1.1.1:4005", None).await?;
+    let graphql = get(&app, "/graphql", "1.1.1.1:4006", None).await?;
+
+    // assert
+    assert_eq!(200, first.status());
+    assert_eq!(
+        Some("2"),
+        first["RateLimit-Limit"].get(0).map(|v| v.as_str())
+    );
+    assert_eq!("1", first["RateLimit-Remaining"].as_str());
+    assert_eq!(200, second.status());
+    assert_eq!("0", second["RateLimit-Remaining"].as_str());
+
+    assert_eq!(429, limited.status());
+    assert!(limited.header("Retry-After").is_some());
+    assert!(limited.header("RateLimit-Reset").is_some());
+
+    assert_eq!(200, other_ip.status());
+    assert_eq!(200, health_check.status());
+    assert!(health_check.header("RateLimit-Limit").is_none());
+    assert_eq!(200, style.status());
+    assert!(style.header("RateLimit-Limit").is_none());
+    assert_eq!(429, search.status());
+    assert_eq!(429, graphql.status());
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn forwarded_for_trusted_proxies() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge
+    let app = app().await;
+    let forwarded_for = Some("6.6.6.6, 3.3.3.3");
+
+    // act, behind the trusted proxy the client is the last forwarded address
+    for _ in 0..2 {
+        let res = get(&app, "/pokemon/charizard", "10.0.0.1:5000", forwarded_for).await?;
+        assert_eq!(200, res.status());
+    }
+    let limited = get(&app, "/pokemon/charizard", "10.0.0.1:5000", forwarded_for).await?;
+    let other_client = get(&app, "/pokemon/charizard", "10.0.0.1:5000", Some("4.4.4.4")).await?;
+    // an untrusted peer can't pretend to be another client
+    let spoofed = get(&app, "/pokemon/charizard", "5.5.5.5:5000", Some("4.4.4.4")).await?;
+    let spoofer = get(&app, "/pokemon/charizard", "5.5.5.5:5000", Some("7.7.7.7")).await?;
+    let spoofer_limited = get(&app, "/pokemon/charizard", "5.5.5.5:5000", Some("8.8.8.8")).await?;
+
+    // assert
+    assert_eq!(429, limited.status());
+    assert_eq!(200, other_client.status());
+    assert_eq!(200, spoofed.status());
+    assert_eq!(200, spoofer.status());
+    assert_eq!(429, spoofer_limited.status());
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn graphql_charged_per_upstream_call() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge
+    let app = app().await;
+    let url = Url::parse("https://example.com/graphql")?;
+    let mut req = Request::new(Method::Post, url);
+    req.set_peer_addr(Some("1.1.1.1:4000"));
+    req.set_body(tide::prelude::json!({
+        "query": r#"{
+            cached: pokemon(name: "charizard") { description }
+            first: pokemon(name: "bulbasaur") { description }
+            second: pokemon(name: "squirtle") { description }
+        }"#
+    }));
+
+    // act, the query takes a token and the first call the last one
+    let mut res: Response = app.respond(req).await?;
+
+    // assert
+    assert_eq!(200, res.status());
+    let response: serde_json::Value = res.body_json().await?;
+    assert_eq!("Spits fire", response["data"]["cached"]["description"]);
+    let codes: Vec<&str> = response["errors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|error| error["extensions"]["code"].as_str().unwrap())
+        .collect();
+    assert_eq!(2, codes.len());
+    assert!(codes.contains(&"rate_limited"));
+    assert!(!codes.contains(&"too_many_keys"));
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn batch_charged_per_upstream_call() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge
+    let app = app().await;
+    let url = Url::parse("https://example.com/pokemon/batch")?;
+    let mut req = Request::new(Method::Post, url);
+    req.set_peer_addr(Some("1.1.1.1:4000"));
+    req.set_body(tide::prelude::json!({
+        "names": ["charizard", "bulbasaur", "Bulbasaur", "squirtle"]
+    }));
+
+    // act, the batch takes a token and bulbasaur the last one
+    let mut res: Response = app.respond(req).await?;
+    let drained = get(&app, "/pokemon/charizard", "1.1.1.1:4001", None).await?;
+
+    // assert
+    assert_eq!(200, res.status());
+    let response: serde_json::Value = res.body_json().await?;
+    let results = response["results"].as_array().unwrap();
+    assert_eq!(200, results[0]["status"]);
+    assert_ne!("rate_limited", results[1]["code"]);
+    assert_eq!(results[1], results[2]);
+    assert_eq!(
+        tide::prelude::json!({
+            "name": "squirtle",
+            "status": 429,
+            "error": "Too Many Requests",
+            "code": "rate_limited"
+        }),
+        results[3]
+    );
+    assert_eq!(429, drained.status());
+
+    Ok(())
+}
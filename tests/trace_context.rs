@@ -0,0 +1,116 @@
+use poke_spearify::config::RequestIdPolicy;
+use poke_spearify::middlewares::requestid::RequestIdMiddleware;
+use poke_spearify::server;
+use poke_spearify::wrappers::pokemon;
+use poke_spearify::wrappers::shakespeare;
+
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+
+const CHARIZARD_CONTENT: &str = include_str!("../samples/charizard.json");
+const TRASLATION_CONTENT: &str = include_str!("../samples/shakespeare_translation.json");
+
+const ULID: &str = "01ARZ3NDEKTSV4RRFFQ69G5FAV";
+const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+const TRACEPARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+/// Calls in the trace of the caller, as a child of this server.
+fn continues_trace(request: &Request) -> bool {
+    request
+        .headers
+        .get(&"traceparent".into())
+        .map(|traceparent| {
+            let traceparent = traceparent.last().as_str();
+            traceparent.starts_with(&format!("00-{}-", TRACE_ID))
+                && !traceparent.contains("00f067aa0ba902b7")
+        })
+        .unwrap_or(false)
+}
+
+#[async_std::test]
+async fn forward_correlation_headers() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge wrappers mocks
+    let mock_pokemon_server = MockServer::start().await;
+    let charizard_as_json: serde_json::Value = serde_json::from_str(CHARIZARD_CONTENT).unwrap();
+    Mock::given(method("GET"))
+        .and(path(format!("{}charizard", pokemon::POKEMON_SERVICE_PATH)))
+        .and(header("X-Request-Id", ULID))
+        .and(header("tracestate", "vendor=value"))
+        .and(continues_trace)
+        .respond_with(ResponseTemplate::new(200).set_body_json(charizard_as_json))
+        .expect(1)
+        .mount(&mock_pokemon_server)
+        .await;
+
+    let mock_translation_server = MockServer::start().await;
+    let translation_as_json: serde_json::Value = serde_json::from_str(TRASLATION_CONTENT).unwrap();
+    Mock::given(method("POST"))
+        .and(path(shakespeare::TRANSLATION_SHAKESPEARE_PATH))
+        .and(header("X-Request-Id", ULID))
+        .and(continues_trace)
+        .respond_with(ResponseTemplate::new(200).set_body_json(translation_as_json))
+        .expect(1)
+        .mount(&mock_translation_server)
+        .await;
+
+    let shakespeare_wrapper =
+        shakespeare::ShakespeareWrapper::with_base_url(&mock_translation_server.uri());
+    let pokemon_wrapper = pokemon::PokemonWrapper::with_base_url(&mock_pokemon_server.uri());
+    let mut app = server::build(shakespeare_wrapper, pokemon_wrapper).await;
+    app.with(RequestIdMiddleware::with_policy(
+        RequestIdPolicy::UuidOrUlid,
+    ));
+
+    // act
+    let res = surf::Client::with_http_client(app)
+        .get("https://example.com/pokemon/charizard")
+        .header("X-Request-Id", ULID)
+        .header("traceparent", TRACEPARENT)
+        .header("tracestate", "vendor=value")
+        .await?;
+
+    // assert
+    assert_eq!(200, res.status() as u16);
+    assert_eq!(ULID, res["X-Request-Id"].as_str());
+    let traceparent = res["traceparent"].as_str();
+    assert!(traceparent.starts_with(&format!("00-{}-", TRACE_ID)));
+    assert!(traceparent.ends_with("-01"));
+    assert_eq!("vendor=value", res["tracestate"].as_str());
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn replace_rejected_ids() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge
+    let shakespeare_wrapper = shakespeare::ShakespeareWrapper::with_base_url("http://127.0.0.1:9");
+    let pokemon_wrapper = pokemon::PokemonWrapper::with_base_url("http://127.0.0.1:9");
+    let mut app = server::build(shakespeare_wrapper, pokemon_wrapper).await;
+    app.with(RequestIdMiddleware::new());
+
+    // act
+    let res = surf::Client::with_http_client(app)
+        .get("https://example.com/health_check")
+        .header("X-Request-Id", ULID)
+        .header(
+            "traceparent",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+        )
+        .await?;
+
+    // assert
+    assert_eq!(200, res.status() as u16);
+    let request_id = res["X-Request-Id"].as_str();
+    assert_ne!(ULID, request_id);
+    assert!(request_id.parse::<uuid::Uuid>().is_ok());
+    let traceparent = res["traceparent"].as_str();
+    assert!(traceparent.starts_with("00-"));
+    assert!(!traceparent.contains("00000000000000000000000000000000"));
+    assert!(res.header("tracestate").is_none());
+
+    Ok(())
+}
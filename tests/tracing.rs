@@ -0,0 +1,180 @@
+use std::sync::{Arc, Mutex};
+
+use tide_tracing::TraceMiddleware;
+
+use poke_spearify::config::{OtlpProtocol, TracingConfig};
+use poke_spearify::middlewares::requestid::RequestIdMiddleware;
+use poke_spearify::server;
+use poke_spearify::telemetry;
+use poke_spearify::wrappers::pokemon;
+use poke_spearify::wrappers::shakespeare;
+
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+const CHARIZARD_CONTENT: &str = include_str!("../samples/charizard.json");
+const TRASLATION_CONTENT: &str = include_str!("../samples/shakespeare_translation.json");
+
+/// Collector stand-in, keeping the bodies of the OTLP requests.
+#[derive(Clone, Default)]
+struct Collector {
+    bodies: Arc<Mutex<Vec<Vec<u8>>>>,
+}
+
+impl Respond for Collector {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        self.bodies.lock().unwrap().push(request.body.clone());
+        ResponseTemplate::new(200)
+    }
+}
+
+impl Collector {
+    /// Exported spans, from the OTLP/JSON requests received.
+    fn spans(&self) -> Vec<serde_json::Value> {
+        let mut spans = Vec::new();
+        for body in self.bodies.lock().unwrap().iter() {
+            let body: serde_json::Value = serde_json::from_slice(body).unwrap();
+            for resource_spans in body["resourceSpans"].as_array().unwrap() {
+                for scope_spans in resource_spans["scopeSpans"].as_array().unwrap() {
+                    spans.extend(scope_spans["spans"].as_array().unwrap().iter().cloned());
+                }
+            }
+        }
+        spans
+    }
+}
+
+fn span<'a>(spans: &'a [serde_json::Value], name: &str) -> &'a serde_json::Value {
+    spans
+        .iter()
+        .find(|span| span["name"] == name)
+        .unwrap_or_else(|| panic!("span {} not exported", name))
+}
+
+/// Value of an attribute of the span, as a string whatever its type.
+fn attribute(span: &serde_json::Value, key: &str) -> Option<String> {
+    let attribute = span["attributes"]
+        .as_array()?
+        .iter()
+        .find(|attribute| attribute["key"] == key)?;
+    let value = attribute["value"].as_object()?.values().next()?;
+    Some(match value {
+        serde_json::Value::String(value) => value.clone(),
+        value => value.to_string(),
+    })
+}
+
+#[async_std::test]
+async fn export_upstream_spans() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge wrappers mocks
+    let collector = Collector::default();
+    let mock_collector_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/traces"))
+        .respond_with(collector.clone())
+        .mount(&mock_collector_server)
+        .await;
+
+    let mock_pokemon_server = MockServer::start().await;
+    let charizard_as_json: serde_json::Value = serde_json::from_str(CHARIZARD_CONTENT).unwrap();
+    Mock::given(method("GET"))
+        .and(path(format!("{}charizard", pokemon::POKEMON_SERVICE_PATH)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(charizard_as_json))
+        .expect(1)
+        .mount(&mock_pokemon_server)
+        .await;
+
+    let mock_translation_server = MockServer::start().await;
+    let translation_as_json: serde_json::Value = serde_json::from_str(TRASLATION_CONTENT).unwrap();
+    Mock::given(method("POST"))
+        .and(path(shakespeare::TRANSLATION_SHAKESPEARE_PATH))
+        .respond_with(ResponseTemplate::new(200).set_body_json(translation_as_json))
+        .expect(1)
+        .mount(&mock_translation_server)
+        .await;
+
+    let config = TracingConfig {
+        enabled: true,
+        endpoint: format!("{}/v1/traces", mock_collector_server.uri()),
+        protocol: OtlpProtocol::HttpJson,
+        service_name: "poke-spearify-test".to_string(),
+    };
+    let provider = telemetry::tracer_provider(&config)?;
+    let _guard = tracing::subscriber::set_default(telemetry::subscriber(&provider));
+
+    let shakespeare_wrapper =
+        shakespeare::ShakespeareWrapper::with_base_url(&mock_translation_server.uri());
+    let pokemon_wrapper = pokemon::PokemonWrapper::with_base_url(&mock_pokemon_server.uri());
+    let mut app = server::build(shakespeare_wrapper, pokemon_wrapper).await;
+    app.with(TraceMiddleware::new());
+    app.with(RequestIdMiddleware::new());
+
+    // act
+    let request_id = "6e5b1c1a-3f5e-4c7b-9b1e-0c1f2a3b4c5d";
+    let res = surf::Client::with_http_client(app)
+        .get("https://example.com/pokemon/charizard")
+        .header("X-Request-Id", request_id)
+        .header(
+            "traceparent",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+        )
+        .await?;
+    provider.force_flush()?;
+
+    // assert
+    assert_eq!(200, res.status() as u16);
+    let spans = collector.spans();
+    let request = span(&spans, "Request");
+    assert_eq!(
+        Some(request_id.to_string()),
+        attribute(request, "request_id")
+    );
+    assert_eq!("4bf92f3577b34da6a3ce929d0e0e4736", request["traceId"]);
+    assert_eq!("00f067aa0ba902b7", request["parentSpanId"]);
+    assert_eq!(
+        format!(
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-{}-01",
+            request["spanId"].as_str().unwrap()
+        ),
+        res["traceparent"].as_str()
+    );
+
+    let fetch_pokemon = span(&spans, "fetch_pokemon");
+    assert_eq!(request["spanId"], fetch_pokemon["parentSpanId"]);
+    assert_eq!(
+        Some("GET".to_string()),
+        attribute(fetch_pokemon, "http.request.method")
+    );
+    assert_eq!(
+        Some(format!(
+            "{}{}charizard",
+            mock_pokemon_server.uri(),
+            pokemon::POKEMON_SERVICE_PATH
+        )),
+        attribute(fetch_pokemon, "url.full")
+    );
+    assert_eq!(
+        Some("200".to_string()),
+        attribute(fetch_pokemon, "http.response.status_code")
+    );
+
+    let fetch_translation = span(&spans, "fetch_translation");
+    assert_eq!(
+        Some("POST".to_string()),
+        attribute(fetch_translation, "http.request.method")
+    );
+    assert_eq!(
+        Some("funtranslations".to_string()),
+        attribute(fetch_translation, "peer.service")
+    );
+    assert_eq!(
+        Some("200".to_string()),
+        attribute(fetch_translation, "http.response.status_code")
+    );
+    assert_eq!(None, attribute(fetch_translation, "error.type"));
+
+    provider.shutdown()?;
+    Ok(())
+}
@@ -0,0 +1,111 @@
+use tide::prelude::json;
+
+use poke_spearify::middlewares::requestid::RequestIdMiddleware;
+use poke_spearify::server;
+use poke_spearify::wrappers::pokemon;
+use poke_spearify::wrappers::shakespeare;
+
+use poke_spearify::controllers::translate::TranslateResponse;
+
+use assert_json_diff::assert_json_eq;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const REQUEST_ID: &str = "8e4f0c1f-2a3f-4d56-9e1a-0f5b7c2d9a10";
+
+#[async_std::test]
+async fn translate_text() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge wrappers mocks
+    let mock_translation_server = MockServer::start().await;
+
+    const TRASLATION_CONTENT: &str = include_str!("../samples/shakespeare_translation.json");
+    let translation_as_json: serde_json::Value = serde_json::from_str(TRASLATION_CONTENT).unwrap();
+    let response = ResponseTemplate::new(200).set_body_json(&translation_as_json);
+
+    Mock::given(method("POST"))
+        .and(path(shakespeare::TRANSLATION_SHAKESPEARE_PATH))
+        .respond_with(response)
+        .mount(&mock_translation_server)
+        .await;
+
+    // act
+    let shakespeare_wrapper =
+        shakespeare::ShakespeareWrapper::with_base_url(&mock_translation_server.uri());
+    let pokemon_wrapper = pokemon::PokemonWrapper::new();
+    let mut app = server::build(shakespeare_wrapper, pokemon_wrapper).await;
+    app.with(RequestIdMiddleware::new());
+
+    let mut res = surf::Client::with_http_client(app)
+        .post("https://example.com/translate")
+        .header("X-Request-Id", REQUEST_ID)
+        .body(json!({
+            "text": "Rust, a language empowering everyone to build reliable and efficient software.",
+            "style": "shakespeare"
+        }))
+        .await?;
+
+    // assert
+    assert_eq!(200, res.status());
+
+    let translate_response: TranslateResponse = res.body_json().await?;
+    assert_json_eq!(
+        translate_response,
+        json!({
+            "translated": "Rust, a language empowering everyone to buildeth reliable and efficient software.",
+            "style": "shakespeare",
+            "request_id": REQUEST_ID
+        })
+    );
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn translate_over_quota() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge wrappers mocks
+    let mock_translation_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path(shakespeare::TRANSLATION_SHAKESPEARE_PATH))
+        .respond_with(ResponseTemplate::new(429))
+        .mount(&mock_translation_server)
+        .await;
+
+    // act
+    let shakespeare_wrapper =
+        shakespeare::ShakespeareWrapper::with_base_url(&mock_translation_server.uri());
+    let pokemon_wrapper = pokemon::PokemonWrapper::new();
+    let app = server::build(shakespeare_wrapper, pokemon_wrapper).await;
+
+    let res = surf::Client::with_http_client(app)
+        .post("https://example.com/translate")
+        .body(json!({ "text": "Hello world" }))
+        .await?;
+
+    // assert
+    assert_eq!(429, res.status());
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn translate_unsupported_style() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    let shakespeare_wrapper = shakespeare::ShakespeareWrapper::new();
+    let pokemon_wrapper = pokemon::PokemonWrapper::new();
+    let app = server::build(shakespeare_wrapper, pokemon_wrapper).await;
+
+    let res = surf::Client::with_http_client(app)
+        .post("https://example.com/translate")
+        .body(json!({ "text": "Hello world", "style": "yoda" }))
+        .await?;
+
+    assert_eq!(400, res.status());
+
+    Ok(())
+}
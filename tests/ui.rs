@@ -0,0 +1,141 @@
+use poke_spearify::server::{self, State};
+use poke_spearify::wrappers::pokemon;
+use poke_spearify::wrappers::shakespeare;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[async_std::test]
+async fn search_pokemon() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge wrappers mocks
+    let mock_server = MockServer::start().await;
+    let response = ResponseTemplate::new(200).set_body_json(serde_json::json!({
+        "count": 3,
+        "results": [
+            { "name": "charmander", "url": "https://pokeapi.co/api/v2/pokemon-species/4/" },
+            { "name": "charmeleon", "url": "https://pokeapi.co/api/v2/pokemon-species/5/" },
+            { "name": "squirtle", "url": "https://pokeapi.co/api/v2/pokemon-species/7/" }
+        ]
+    }));
+    Mock::given(method("GET"))
+        .and(path(pokemon::POKEMON_SERVICE_PATH))
+        .respond_with(response)
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let shakespeare_wrapper = shakespeare::ShakespeareWrapper::with_base_url("http://127.0.0.1:9");
+    let pokemon_wrapper = pokemon::PokemonWrapper::with_base_url(&mock_server.uri());
+    let app = server::build(shakespeare_wrapper, pokemon_wrapper).await;
+    let client = surf::Client::with_http_client(app);
+
+    // act
+    let mut res = client.get("https://example.com/search?q=Charm").await?;
+
+    // assert
+    assert_eq!(200, res.status() as u16);
+    assert_eq!("text/html", res.content_type().unwrap().essence());
+    let body = res.body_string().await?;
+    assert!(body.contains(r#"<a href="/pokedex/charmander">charmander</a>"#));
+    assert!(body.contains(r#"<a href="/pokedex/charmeleon">charmeleon</a>"#));
+    assert!(!body.contains("squirtle"));
+
+    // the species are listed once, the mock expects a single call
+    let mut res = client.get("https://example.com/search?q=squirt").await?;
+    assert_eq!(200, res.status() as u16);
+    let body = res.body_string().await?;
+    assert!(body.contains(r#"<a href="/pokedex/squirtle">squirtle</a>"#));
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn pokemon_page() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge wrappers mocks
+    let mock_server = MockServer::start().await;
+    let response = ResponseTemplate::new(200).set_body_json(serde_json::json!({
+        "sprites": { "front_default": "https://sprites.example.com/6.png" }
+    }));
+    Mock::given(method("GET"))
+        .and(path(format!("{}charizard", pokemon::POKEMON_FORMS_PATH)))
+        .respond_with(response)
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let shakespeare_wrapper = shakespeare::ShakespeareWrapper::with_base_url("http://127.0.0.1:9");
+    let pokemon_wrapper = pokemon::PokemonWrapper::with_base_url(&mock_server.uri());
+    let state = State::new(shakespeare_wrapper, pokemon_wrapper);
+    state
+        .cache
+        .insert("charizard", "Spits fire", "Spits fire <hot> & bright");
+    let client = surf::Client::with_http_client(server::build_with_state(state).await);
+
+    // act
+    client.get("https://example.com/pokedex/charizard").await?;
+    let mut res = client.get("https://example.com/pokedex/charizard").await?;
+
+    // assert, the sprite was asked for once
+    assert_eq!(200, res.status() as u16);
+    let body = res.body_string().await?;
+    assert!(body.contains(r#"src="https://sprites.example.com/6.png""#));
+    assert!(body.contains("<p>Spits fire</p>"));
+    assert!(body.contains("Spits fire &lt;hot&gt; &amp; bright"));
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn pokemon_page_not_found() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge wrappers mocks
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&mock_server)
+        .await;
+
+    let shakespeare_wrapper = shakespeare::ShakespeareWrapper::with_base_url("http://127.0.0.1:9");
+    let pokemon_wrapper = pokemon::PokemonWrapper::with_base_url(&mock_server.uri());
+    let app = server::build(shakespeare_wrapper, pokemon_wrapper).await;
+    let client = surf::Client::with_http_client(app);
+
+    // act
+    let mut res = client
+        .get("https://example.com/pokedex/nocharizard")
+        .await?;
+
+    // assert
+    assert_eq!(404, res.status() as u16);
+    assert_eq!("text/html", res.content_type().unwrap().essence());
+    assert!(res.body_string().await?.contains("Not Found"));
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn embedded_stylesheet() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge
+    let shakespeare_wrapper = shakespeare::ShakespeareWrapper::with_base_url("http://127.0.0.1:9");
+    let pokemon_wrapper = pokemon::PokemonWrapper::with_base_url("http://127.0.0.1:9");
+    let app = server::build(shakespeare_wrapper, pokemon_wrapper).await;
+    let client = surf::Client::with_http_client(app);
+
+    // act
+    let index = client.get("https://example.com/").await?;
+    let style = client.get("https://example.com/static/style.css").await?;
+
+    // assert
+    assert_eq!(200, index.status() as u16);
+    assert_eq!(200, style.status() as u16);
+    assert_eq!("text/css", style.content_type().unwrap().essence());
+    assert!(style["Cache-Control"].as_str().contains("max-age"));
+
+    Ok(())
+}
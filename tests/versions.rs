@@ -0,0 +1,209 @@
+use std::sync::Arc;
+
+use tide::prelude::json;
+
+use poke_spearify::config::Config;
+use poke_spearify::server::{self, State};
+use poke_spearify::wrappers::pokemon;
+use poke_spearify::wrappers::shakespeare;
+
+use assert_json_diff::assert_json_eq;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const CHARIZARD_CONTENT: &str = include_str!("../samples/charizard.json");
+
+/// Pokemon api mock answering charizard.
+async fn pokemon_server() -> MockServer {
+    let mock_server = MockServer::start().await;
+    let charizard_as_json: serde_json::Value = serde_json::from_str(CHARIZARD_CONTENT).unwrap();
+    Mock::given(method("GET"))
+        .and(path(format!("{}charizard", pokemon::POKEMON_SERVICE_PATH)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(charizard_as_json))
+        .mount(&mock_server)
+        .await;
+    mock_server
+}
+
+#[async_std::test]
+async fn deprecated_v1() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge
+    let shakespeare_wrapper = shakespeare::ShakespeareWrapper::with_base_url("http://127.0.0.1:9");
+    let pokemon_wrapper = pokemon::PokemonWrapper::with_base_url("http://127.0.0.1:9");
+    let mut state = State::new(shakespeare_wrapper, pokemon_wrapper);
+    state
+        .cache
+        .insert("charizard", "Spits fire", "Spits fire, forsooth");
+
+    let mut config = Config::default();
+    config.versions.v1.deprecation = Some("Thu, 01 Jan 2026 00:00:00 GMT".to_string());
+    config.versions.v1.sunset = Some("Fri, 01 Jan 2027 00:00:00 GMT".to_string());
+    config.versions.v1.link = Some("https://example.com/docs".to_string());
+    state.config = Arc::new(config);
+    let client = surf::Client::with_http_client(server::build_with_state(state).await);
+
+    // act
+    let mut unversioned = client.get("https://example.com/pokemon/charizard").await?;
+    let mut v1 = client
+        .get("https://example.com/v1/pokemon/charizard")
+        .await?;
+
+    // assert
+    for res in [&unversioned, &v1] {
+        assert_eq!(200, res.status() as u16);
+        assert_eq!("@1767225600", res["Deprecation"].as_str());
+        assert_eq!("Fri, 01 Jan 2027 00:00:00 GMT", res["Sunset"].as_str());
+        assert_eq!(
+            r#"<https://example.com/docs>; rel="deprecation""#,
+            res["Link"].as_str()
+        );
+    }
+    let expected = r#"{"name":"charizard","description":"Spits fire, forsooth"}"#;
+    assert_eq!(expected, unversioned.body_string().await?);
+    assert_eq!(expected, v1.body_string().await?);
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn get_pokemon_v2() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge wrappers mocks
+    let mock_pokemon_server = pokemon_server().await;
+
+    let mock_translation_server = MockServer::start().await;
+    const TRASLATION_CONTENT: &str = include_str!("../samples/shakespeare_translation.json");
+    let translation_as_json: serde_json::Value = serde_json::from_str(TRASLATION_CONTENT).unwrap();
+    Mock::given(method("POST"))
+        .and(path(shakespeare::TRANSLATION_SHAKESPEARE_PATH))
+        .respond_with(ResponseTemplate::new(200).set_body_json(translation_as_json))
+        .expect(1)
+        .mount(&mock_translation_server)
+        .await;
+
+    let shakespeare_wrapper =
+        shakespeare::ShakespeareWrapper::with_base_url(&mock_translation_server.uri());
+    let pokemon_wrapper = pokemon::PokemonWrapper::with_base_url(&mock_pokemon_server.uri());
+    let app = server::build(shakespeare_wrapper, pokemon_wrapper).await;
+
+    // act
+    let mut res = surf::Client::with_http_client(app)
+        .get("https://example.com/v2/pokemon/charizard")
+        .await?;
+
+    // assert
+    assert_eq!(200, res.status() as u16);
+    assert!(res.header("Deprecation").is_none());
+    let body: serde_json::Value = res.body_json().await?;
+    assert_json_eq!(
+        body,
+        json!({
+            "id": 6,
+            "name": "charizard",
+            "description": "Rust, a language empowering everyone to buildeth reliable and efficient software.",
+            "language": "en",
+            "translated": true,
+            "style": "shakespeare"
+        })
+    );
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn get_untranslated_pokemon_v2() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge wrappers mocks
+    let mock_pokemon_server = pokemon_server().await;
+
+    let shakespeare_wrapper = shakespeare::ShakespeareWrapper::with_base_url("http://127.0.0.1:9");
+    let pokemon_wrapper = pokemon::PokemonWrapper::with_base_url(&mock_pokemon_server.uri());
+    let app = server::build(shakespeare_wrapper, pokemon_wrapper).await;
+    let client = surf::Client::with_http_client(app);
+
+    // act
+    let mut v2 = client
+        .get("https://example.com/v2/pokemon/charizard")
+        .await?;
+    let v1 = client
+        .get("https://example.com/v1/pokemon/charizard")
+        .await?;
+
+    // assert
+    assert_eq!(200, v2.status() as u16);
+    let body: serde_json::Value = v2.body_json().await?;
+    assert_json_eq!(
+        body,
+        json!({
+            "id": 6,
+            "name": "charizard",
+            "description": "Spits fire that is hot enough to melt boulders. Known to cause forest fires unintentionally.",
+            "language": "en",
+            "translated": false,
+            "style": null
+        })
+    );
+    assert_eq!(503, v1.status() as u16);
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn cached_pokemon_v2() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge wrappers mocks, the pokédex number is cached along the translation
+    let mock_pokemon_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(0)
+        .mount(&mock_pokemon_server)
+        .await;
+
+    let shakespeare_wrapper = shakespeare::ShakespeareWrapper::with_base_url("http://127.0.0.1:9");
+    let pokemon_wrapper = pokemon::PokemonWrapper::with_base_url(&mock_pokemon_server.uri());
+    let state = State::new(shakespeare_wrapper, pokemon_wrapper);
+    let species = pokemon::Species {
+        id: 6,
+        description: "Spits fire".to_string(),
+    };
+    state
+        .cache
+        .insert_species("charizard", &species, "Spits fire, forsooth");
+    let client = surf::Client::with_http_client(server::build_with_state(state).await);
+
+    // act
+    let mut res = client
+        .get("https://example.com/v2/pokemon/charizard")
+        .await?;
+    let etag = res["ETag"].as_str().to_string();
+    let not_modified = client
+        .get("https://example.com/v2/pokemon/charizard")
+        .header("If-None-Match", etag.as_str())
+        .await?;
+    let mut text = client
+        .get("https://example.com/v2/pokemon/charizard")
+        .header("Accept", "text/plain")
+        .await?;
+
+    // assert
+    assert_eq!(200, res.status() as u16);
+    assert!(res.header("Last-Modified").is_some());
+    assert_eq!("Accept", res["Vary"].as_str());
+    let body: serde_json::Value = res.body_json().await?;
+    assert_eq!(6, body["id"]);
+    assert_eq!("Spits fire, forsooth", body["description"]);
+
+    assert_eq!(304, not_modified.status() as u16);
+    assert_eq!(etag, not_modified["ETag"].as_str());
+
+    assert_eq!(200, text.status() as u16);
+    assert_eq!("text/plain", text.content_type().unwrap().essence());
+    assert_eq!("Spits fire, forsooth", text.body_string().await?);
+
+    Ok(())
+}
@@ -0,0 +1,206 @@
+use std::sync::Arc;
+
+use tide::prelude::json;
+
+use poke_spearify::config::{Config, WarmupConfig};
+use poke_spearify::controllers::admin::WarmupStatus;
+use poke_spearify::server::{self, State};
+use poke_spearify::warmup::{Step, Warmup};
+use poke_spearify::wrappers::pokemon;
+use poke_spearify::wrappers::shakespeare;
+
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// Start a pokemon api mock listing bulbasaur, nocharizard, charizard and charmander.
+async fn mock_pokedex() -> MockServer {
+    let mock_pokemon_server = MockServer::start().await;
+
+    let species = json!({
+        "count": 4,
+        "results": [
+            { "name": "bulbasaur", "url": "" },
+            { "name": "nocharizard", "url": "" },
+            { "name": "charizard", "url": "" },
+            { "name": "charmander", "url": "" }
+        ]
+    });
+    Mock::given(method("GET"))
+        .and(path(pokemon::POKEMON_SERVICE_PATH))
+        .and(query_param("offset", "0"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(species))
+        .mount(&mock_pokemon_server)
+        .await;
+
+    const CHARIZARD_CONTENT: &str = include_str!("../samples/charizard.json");
+    let charizard_as_json: serde_json::Value = serde_json::from_str(CHARIZARD_CONTENT).unwrap();
+    Mock::given(method("GET"))
+        .and(path(format!("{}charizard", pokemon::POKEMON_SERVICE_PATH)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(charizard_as_json))
+        .mount(&mock_pokemon_server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path(format!(
+            "{}nocharizard",
+            pokemon::POKEMON_SERVICE_PATH
+        )))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&mock_pokemon_server)
+        .await;
+
+    mock_pokemon_server
+}
+
+async fn mock_translation() -> MockServer {
+    let mock_translation_server = MockServer::start().await;
+
+    const TRASLATION_CONTENT: &str = include_str!("../samples/shakespeare_translation.json");
+    let translation_as_json: serde_json::Value = serde_json::from_str(TRASLATION_CONTENT).unwrap();
+    Mock::given(method("POST"))
+        .and(path(shakespeare::TRANSLATION_SHAKESPEARE_PATH))
+        .respond_with(ResponseTemplate::new(200).set_body_json(&translation_as_json))
+        .expect(1)
+        .mount(&mock_translation_server)
+        .await;
+
+    mock_translation_server
+}
+
+#[async_std::test]
+async fn warmup_within_budget() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge
+    let mock_pokemon_server = mock_pokedex().await;
+    let mock_translation_server = mock_translation().await;
+
+    let progress_path =
+        std::env::temp_dir().join(format!("poke-spearify-warmup-{}.json", std::process::id()));
+    let config = WarmupConfig {
+        enabled: true,
+        hourly_budget: 1,
+        progress_path: Some(progress_path.clone()),
+        ..WarmupConfig::default()
+    };
+
+    let shakespeare_wrapper =
+        shakespeare::ShakespeareWrapper::with_base_url(&mock_translation_server.uri());
+    let pokemon_wrapper = pokemon::PokemonWrapper::with_base_url(&mock_pokemon_server.uri());
+    let mut state = State::new(shakespeare_wrapper.clone(), pokemon_wrapper.clone());
+    state
+        .cache
+        .insert("bulbasaur", "A seed", "A seed, forsooth");
+    let mut app_config = Config::default();
+    app_config.admin.token = Some("secret".to_string());
+    state.config = Arc::new(app_config);
+
+    let warmup = Warmup::new(
+        &config,
+        pokemon_wrapper.clone(),
+        shakespeare_wrapper.clone(),
+        state.cache.clone(),
+    )?;
+    state.warmup = Some(warmup.clone());
+
+    // act, bulbasaur is already cached and nocharizard doesn't exist
+    assert_eq!(Step::Failed("nocharizard".to_string()), warmup.step().await);
+    assert_eq!(
+        Step::Translated("charizard".to_string()),
+        warmup.step().await
+    );
+    assert_eq!(Step::BudgetExhausted, warmup.step().await);
+
+    // assert
+    assert!(state.cache.get("charizard").is_some());
+
+    let progress = warmup.progress();
+    assert_eq!(3, progress.offset);
+    assert_eq!(Some(4), progress.total);
+    assert_eq!(1, progress.skipped);
+    assert_eq!(1, progress.failed);
+    assert_eq!(1, progress.translated);
+
+    // the progress is resumed by a new scheduler
+    let resumed = Warmup::new(
+        &config,
+        pokemon_wrapper,
+        shakespeare_wrapper,
+        state.cache.clone(),
+    )?;
+    assert_eq!(progress, resumed.progress());
+
+    // and exposed by the admin api
+    let app = server::build_with_state(state).await;
+    let mut res = surf::Client::with_http_client(app)
+        .get("https://example.com/admin/warmup")
+        .header("Authorization", "Bearer secret")
+        .await?;
+    assert_eq!(200, res.status());
+
+    let status: WarmupStatus = res.body_json().await?;
+    assert!(status.enabled);
+    assert_eq!(Some(0), status.remaining_hourly);
+    assert_eq!(Some(3), status.progress.map(|progress| progress.offset));
+
+    std::fs::remove_file(&progress_path)?;
+    Ok(())
+}
+
+#[async_std::test]
+async fn warmup_keeps_quota_reserve() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge
+    let mock_pokemon_server = mock_pokedex().await;
+    let mock_translation_server = MockServer::start().await;
+
+    const TRASLATION_CONTENT: &str = include_str!("../samples/shakespeare_translation.json");
+    let translation_as_json: serde_json::Value = serde_json::from_str(TRASLATION_CONTENT).unwrap();
+    Mock::given(method("POST"))
+        .and(path(shakespeare::TRANSLATION_SHAKESPEARE_PATH))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("X-RateLimit-Remaining", "2")
+                .set_body_json(&translation_as_json),
+        )
+        .expect(1)
+        .mount(&mock_translation_server)
+        .await;
+
+    let config = WarmupConfig {
+        enabled: true,
+        hourly_budget: 10,
+        quota_reserve: 2,
+        ..WarmupConfig::default()
+    };
+
+    let shakespeare_wrapper =
+        shakespeare::ShakespeareWrapper::with_base_url(&mock_translation_server.uri());
+    let pokemon_wrapper = pokemon::PokemonWrapper::with_base_url(&mock_pokemon_server.uri());
+    let state = State::new(shakespeare_wrapper.clone(), pokemon_wrapper.clone());
+    state
+        .cache
+        .insert("bulbasaur", "A seed", "A seed, forsooth");
+
+    let warmup = Warmup::new(
+        &config,
+        pokemon_wrapper,
+        shakespeare_wrapper,
+        state.cache.clone(),
+    )?;
+
+    // act
+    assert_eq!(Step::Failed("nocharizard".to_string()), warmup.step().await);
+    assert_eq!(
+        Step::Translated("charizard".to_string()),
+        warmup.step().await
+    );
+
+    // assert, the quota left is kept for the requests
+    assert_eq!(Step::QuotaReserved, warmup.step().await);
+    assert_eq!(3, warmup.progress().offset);
+    assert_eq!(1, warmup.progress().translated);
+
+    Ok(())
+}
@@ -0,0 +1,289 @@
+use std::sync::Arc;
+
+use async_tungstenite::async_std::{connect_async, ConnectStream};
+use async_tungstenite::tungstenite::Message;
+use async_tungstenite::WebSocketStream;
+use futures::{SinkExt, StreamExt};
+use tide::listener::Listener;
+use tide::prelude::json;
+use tide::Server;
+
+use poke_spearify::config::{Config, RateLimitConfig};
+use poke_spearify::middlewares::rate_limit::RateLimitMiddleware;
+use poke_spearify::server::{self, State};
+use poke_spearify::wrappers::pokemon;
+use poke_spearify::wrappers::shakespeare;
+
+use assert_json_diff::assert_json_eq;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const TRASLATION_CONTENT: &str = include_str!("../samples/shakespeare_translation.json");
+
+/// Serve the app on a random port and open a session.
+async fn connect(state: State) -> tide::Result<WebSocketStream<ConnectStream>> {
+    connect_app(server::build_with_state(state).await).await
+}
+
+async fn connect_app(app: Server<State>) -> tide::Result<WebSocketStream<ConnectStream>> {
+    let mut listener = app.bind("127.0.0.1:0").await?;
+    let url = listener.info()[0].connection().replace("http://", "ws://");
+    async_std::task::spawn(async move { listener.accept().await });
+
+    let (stream, _) = connect_async(format!("{}/ws", url)).await?;
+    Ok(stream)
+}
+
+async fn send(stream: &mut WebSocketStream<ConnectStream>, message: serde_json::Value) {
+    stream
+        .send(Message::Text(message.to_string()))
+        .await
+        .unwrap();
+}
+
+async fn receive(stream: &mut WebSocketStream<ConnectStream>) -> serde_json::Value {
+    match stream.next().await {
+        Some(Ok(Message::Text(text))) => serde_json::from_str(&text).unwrap(),
+        other => panic!("unexpected message: {:?}", other),
+    }
+}
+
+#[async_std::test]
+async fn lookup_and_translate() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge wrappers mocks
+    let mock_translation_server = MockServer::start().await;
+    let translation_as_json: serde_json::Value = serde_json::from_str(TRASLATION_CONTENT).unwrap();
+    Mock::given(method("POST"))
+        .and(path(shakespeare::TRANSLATION_SHAKESPEARE_PATH))
+        .respond_with(ResponseTemplate::new(200).set_body_json(translation_as_json))
+        .expect(1)
+        .mount(&mock_translation_server)
+        .await;
+
+    let shakespeare_wrapper =
+        shakespeare::ShakespeareWrapper::with_base_url(&mock_translation_server.uri());
+    let pokemon_wrapper = pokemon::PokemonWrapper::with_base_url("http://127.0.0.1:9");
+    let state = State::new(shakespeare_wrapper, pokemon_wrapper);
+    state
+        .cache
+        .insert("charizard", "Spits fire", "Spits fire, forsooth");
+    let mut stream = connect(state).await?;
+
+    // act
+    let lookup_id = "6e5b1c1a-3f5e-4c7b-9b1e-0c1f2a3b4c5d";
+    send(
+        &mut stream,
+        json!({ "type": "lookup", "request_id": lookup_id, "name": "charizard" }),
+    )
+    .await;
+    let lookup = receive(&mut stream).await;
+    send(
+        &mut stream,
+        json!({ "type": "translate", "text": "Rust, a language empowering everyone to build reliable and efficient software." }),
+    )
+    .await;
+    let translation = receive(&mut stream).await;
+    send(&mut stream, json!({ "type": "translate", "text": "" })).await;
+    let invalid_text = receive(&mut stream).await;
+    send(&mut stream, json!({ "type": "dance" })).await;
+    let invalid_message = receive(&mut stream).await;
+
+    // assert
+    assert_json_eq!(
+        lookup,
+        json!({
+            "request_id": lookup_id,
+            "type": "pokemon",
+            "name": "charizard",
+            "description": "Spits fire, forsooth"
+        })
+    );
+    assert_eq!("translation", translation["type"]);
+    assert_eq!(
+        "Rust, a language empowering everyone to buildeth reliable and efficient software.",
+        translation["translated"]
+    );
+    assert!(translation["request_id"].is_string());
+    assert_eq!("error", invalid_text["type"]);
+    assert_eq!("invalid_text", invalid_text["code"]);
+    assert_eq!(400, invalid_text["status"]);
+    assert_eq!("invalid_message", invalid_message["code"]);
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn rate_limited_session() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge
+    let shakespeare_wrapper = shakespeare::ShakespeareWrapper::with_base_url("http://127.0.0.1:9");
+    let pokemon_wrapper = pokemon::PokemonWrapper::with_base_url("http://127.0.0.1:9");
+    let mut state = State::new(shakespeare_wrapper, pokemon_wrapper);
+    state
+        .cache
+        .insert("charizard", "Spits fire", "Spits fire, forsooth");
+    // the connection is limited even without the http rate limit
+    let mut config = Config::default();
+    config.rate_limit.enabled = false;
+    config.rate_limit.capacity = 1;
+    config.rate_limit.refill_per_sec = 0.01;
+    state.config = Arc::new(config);
+    let mut stream = connect(state).await?;
+
+    // act
+    let lookup = json!({ "type": "lookup", "name": "charizard" });
+    send(&mut stream, lookup.clone()).await;
+    let first = receive(&mut stream).await;
+    send(&mut stream, lookup).await;
+    let second = receive(&mut stream).await;
+
+    // assert
+    assert_eq!("pokemon", first["type"]);
+    assert_eq!("error", second["type"]);
+    assert_eq!("rate_limited", second["code"]);
+    assert_eq!(429, second["status"]);
+    assert_eq!(100, second["retry_after"]);
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn subscribe_daily() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge wrappers mocks
+    let mock_pokemon_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path(pokemon::POKEMON_SERVICE_PATH))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "count": 1,
+            "results": [
+                { "name": "charizard", "url": "https://pokeapi.co/api/v2/pokemon-species/6/" }
+            ]
+        })))
+        .expect(2)
+        .mount(&mock_pokemon_server)
+        .await;
+
+    let shakespeare_wrapper = shakespeare::ShakespeareWrapper::with_base_url("http://127.0.0.1:9");
+    let pokemon_wrapper = pokemon::PokemonWrapper::with_base_url(&mock_pokemon_server.uri());
+    let state = State::new(shakespeare_wrapper, pokemon_wrapper);
+    state
+        .cache
+        .insert("charizard", "Spits fire", "Spits fire, forsooth");
+    let mut stream = connect(state.clone()).await?;
+    let mut other_stream = connect(state).await?;
+
+    // act
+    let request_id = "0f0c2b7e-4a8e-4b8e-9d0f-6a2b5c1d3e4f";
+    send(
+        &mut stream,
+        json!({ "type": "subscribe_daily", "request_id": request_id }),
+    )
+    .await;
+    let subscribed = receive(&mut stream).await;
+    let daily = receive(&mut stream).await;
+
+    // the other client gets the same pokemon, without listing the species again
+    send(&mut other_stream, json!({ "type": "subscribe_daily" })).await;
+    receive(&mut other_stream).await;
+    let other_daily = receive(&mut other_stream).await;
+
+    // assert
+    assert_json_eq!(
+        subscribed,
+        json!({ "request_id": request_id, "type": "subscribed" })
+    );
+    assert_json_eq!(
+        daily,
+        json!({
+            "request_id": request_id,
+            "type": "daily",
+            "name": "charizard",
+            "description": "Spits fire, forsooth"
+        })
+    );
+    assert_eq!("charizard", other_daily["name"]);
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn messages_charged_to_client() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge, the handshake and the first lookup take the 2 tokens of the client
+    let shakespeare_wrapper = shakespeare::ShakespeareWrapper::with_base_url("http://127.0.0.1:9");
+    let pokemon_wrapper = pokemon::PokemonWrapper::with_base_url("http://127.0.0.1:9");
+    let state = State::new(shakespeare_wrapper, pokemon_wrapper);
+    state
+        .cache
+        .insert("charizard", "Spits fire", "Spits fire, forsooth");
+    let mut app = server::build_with_state(state).await;
+    app.with(RateLimitMiddleware::new(&RateLimitConfig {
+        enabled: true,
+        capacity: 2,
+        refill_per_sec: 0.01,
+        trusted_proxies: vec![],
+    }));
+    let mut stream = connect_app(app).await?;
+
+    // act
+    let lookup = json!({ "type": "lookup", "name": "charizard" });
+    send(&mut stream, lookup.clone()).await;
+    let first = receive(&mut stream).await;
+    send(&mut stream, lookup).await;
+    let second = receive(&mut stream).await;
+
+    // assert
+    assert_eq!("pokemon", first["type"]);
+    assert_eq!("error", second["type"]);
+    assert_eq!("rate_limited", second["code"]);
+    assert_eq!(100, second["retry_after"]);
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn invalid_translations_not_charged() -> tide::Result<()> {
+    dotenv::dotenv().ok();
+
+    // arrenge, the handshake and the lookup take the 2 tokens of the client
+    let shakespeare_wrapper = shakespeare::ShakespeareWrapper::with_base_url("http://127.0.0.1:9");
+    let pokemon_wrapper = pokemon::PokemonWrapper::with_base_url("http://127.0.0.1:9");
+    let state = State::new(shakespeare_wrapper, pokemon_wrapper);
+    state
+        .cache
+        .insert("charizard", "Spits fire", "Spits fire, forsooth");
+    let mut app = server::build_with_state(state).await;
+    app.with(RateLimitMiddleware::new(&RateLimitConfig {
+        enabled: true,
+        capacity: 2,
+        refill_per_sec: 0.01,
+        trusted_proxies: vec![],
+    }));
+    let mut stream = connect_app(app).await?;
+
+    // act
+    send(&mut stream, json!({ "type": "translate", "text": "   " })).await;
+    let blank = receive(&mut stream).await;
+    let yoda = json!({ "type": "translate", "text": "Hello", "style": "yoda" });
+    send(&mut stream, yoda).await;
+    let unsupported = receive(&mut stream).await;
+    send(
+        &mut stream,
+        json!({ "type": "lookup", "name": "charizard" }),
+    )
+    .await;
+    let lookup = receive(&mut stream).await;
+
+    // assert
+    assert_eq!("invalid_text", blank["code"]);
+    assert_eq!("unsupported_style", unsupported["code"]);
+    assert_eq!("pokemon", lookup["type"]);
+
+    Ok(())
+}